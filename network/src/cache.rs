@@ -83,6 +83,10 @@ impl<K: Eq + Hash, V> ExpiringMap<K, V> {
   pub fn prune_expired(&mut self) {
     self.data.retain(|_, v| v.1.elapsed() < self.lifespan);
   }
+
+  pub fn retain(&mut self, mut f: impl FnMut(&K, &V) -> bool) {
+    self.data.retain(|k, v| f(k, &v.0));
+  }
 }
 
 #[derive(Debug)]
@@ -66,6 +66,11 @@ pub struct Config {
   /// for shuffle response connections, etc.
   pub pending_timeout: Duration,
 
+  /// Maximum number of requests from peers that are queued on a topic
+  /// until they are read by the application. Requests beyond that are
+  /// dropped and fail on the requesting side.
+  pub max_pending_requests: usize,
+
   /// This is a periodic event that triggers all topics to perform
   /// maintenance tasks. It gets emitted to topics regardless of
   /// other topic activity.
@@ -127,6 +132,7 @@ impl Default for Config {
       shuffle_interval: Duration::from_secs(180), // 3 minutes
       maintenance_tick_interval: Duration::from_secs(5),
       pending_timeout: Duration::from_secs(15),
+      max_pending_requests: 64,
       dedupe_interval: Some(Duration::from_secs(5)),
       max_transmit_size: 1024 * 1024, // 1MB
       listen_addrs: vec![
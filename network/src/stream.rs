@@ -12,7 +12,7 @@ use {
   crate::{
     channel::Channel,
     codec::Codec,
-    upgrade::{self, ProtocolUpgrade},
+    upgrade::{self, Protocol, ProtocolUpgrade},
     wire,
     Config,
   },
@@ -34,7 +34,7 @@ use {
     pin::Pin,
     task::{Context, Poll},
   },
-  tracing::{error, warn},
+  tracing::{debug, error, warn},
 };
 
 /// State of the protocol inbound async reader
@@ -86,6 +86,10 @@ pub struct SubstreamHandler {
   /// A single long-lived outbound substream state machine
   outbound_stream: Option<OutboundSubstreamState>,
 
+  /// Protocol version negotiated for the outbound substream. Messages
+  /// the peer can't decode under this version are not sent.
+  outbound_protocol: Protocol,
+
   /// By default this is set to `KeepAlive::Yes`.
   /// When we want to terminate this connection gracefully then this
   /// value is set to `KeepAlive::No`, and the protocol will close
@@ -112,6 +116,7 @@ impl SubstreamHandler {
       ),
       inbound_stream: None,
       outbound_stream: None,
+      outbound_protocol: Protocol::V1_1,
       keep_alive: KeepAlive::Yes,
       outbound_messages: Channel::new(),
     }
@@ -139,7 +144,7 @@ impl ConnectionHandler for SubstreamHandler {
   /// The `substream` objest is an async reader over a muxer.
   fn inject_fully_negotiated_inbound(
     &mut self,
-    substream: Framed<Negotiated<SubstreamBox>, Codec>,
+    (substream, _): (Framed<Negotiated<SubstreamBox>, Codec>, Protocol),
     _: Self::InboundOpenInfo,
   ) {
     // we're ready to start receiving frames from the remote peer over this
@@ -151,11 +156,12 @@ impl ConnectionHandler for SubstreamHandler {
   /// Protocol negotiated successfully with the remote peer for outbound frames.
   fn inject_fully_negotiated_outbound(
     &mut self,
-    substream: Framed<Negotiated<SubstreamBox>, Codec>,
+    (substream, protocol): (Framed<Negotiated<SubstreamBox>, Codec>, Protocol),
     _: Self::OutboundOpenInfo,
   ) {
     // we're ready to start sending frames to the remote peer over this stream.
     // Put the outbound state machine in idle state and store the stream object.
+    self.outbound_protocol = protocol;
     self.outbound_stream =
       Some(OutboundSubstreamState::AwaitingWrite(substream));
   }
@@ -295,8 +301,17 @@ impl SubstreamHandler {
       ) {
         Some(OutboundSubstreamState::AwaitingWrite(substream)) => {
           if let Poll::Ready(Some(msg)) = self.outbound_messages.poll_recv(cx) {
-            self.outbound_stream =
-              Some(OutboundSubstreamState::PendingWrite(substream, msg));
+            if self.outbound_protocol.supports(&msg.action) {
+              self.outbound_stream =
+                Some(OutboundSubstreamState::PendingWrite(substream, msg));
+            } else {
+              debug!(
+                "Dropping {:?} unsupported by peer protocol {:?}",
+                msg, self.outbound_protocol
+              );
+              self.outbound_stream =
+                Some(OutboundSubstreamState::AwaitingWrite(substream));
+            }
           } else {
             self.outbound_stream =
               Some(OutboundSubstreamState::AwaitingWrite(substream));
@@ -15,6 +15,8 @@ use {
       Join,
       Message,
       Neighbour,
+      Request,
+      Response,
       Shuffle,
       ShuffleReply,
     },
@@ -40,7 +42,7 @@ use {
     time::Instant,
   },
   thiserror::Error,
-  tokio::sync::mpsc::UnboundedSender,
+  tokio::sync::{mpsc::UnboundedSender, oneshot},
   tracing::{debug, error, warn},
 };
 
@@ -51,6 +53,9 @@ pub enum Error {
 
   #[error("No peers connected")]
   NoConnectedPeers,
+
+  #[error("Request was not answered by the peer")]
+  RequestFailed,
 }
 
 #[derive(Debug)]
@@ -68,6 +73,56 @@ pub enum Event {
   Tick,
 }
 
+/// A request received from one of the active peers of a topic.
+///
+/// The response is sent back to the requesting peer through
+/// [`InboundRequest::respond`]. Requests that are dropped without
+/// a response eventually fail on the requesting side.
+#[derive(Debug)]
+pub struct InboundRequest {
+  id: u64,
+  peer: PeerId,
+  connection: ConnectionId,
+  payload: Vec<u8>,
+  topic: String,
+  max_transmit_size: usize,
+  cmdtx: UnboundedSender<Command>,
+}
+
+impl InboundRequest {
+  /// Identity of the peer that sent the request.
+  pub fn peer(&self) -> &PeerId {
+    &self.peer
+  }
+
+  pub fn payload(&self) -> &[u8] {
+    &self.payload
+  }
+
+  /// Sends the response to the requesting peer.
+  pub fn respond(self, data: Vec<u8>) -> Result<(), Error> {
+    if data.len() > self.max_transmit_size {
+      return Err(Error::MessageTooLarge);
+    }
+
+    self
+      .cmdtx
+      .send(Command::SendMessage {
+        peer: self.peer,
+        connection: self.connection,
+        msg: Message::new(
+          self.topic,
+          Action::Response(Response {
+            id: self.id,
+            payload: data.into(),
+          }),
+        ),
+      })
+      .expect("network lifetime > topic lifetime");
+    Ok(())
+  }
+}
+
 /// Here the topic implementation lives. It is in an internal
 /// struct because the public interface must be Send + Sync so
 /// it can be moved across different threads. Access to this
@@ -93,6 +148,17 @@ struct TopicInner {
   /// Events emitted to listeners on new messages received on this topic.
   outmsgs: Channel<Vec<u8>>,
 
+  /// Requests from active peers emitted to listeners on this topic.
+  inrequests: Channel<InboundRequest>,
+
+  /// Number of requests in `inrequests` not read by listeners yet.
+  queued_requests: usize,
+
+  /// Requests sent by this node to one of its active peers that
+  /// are waiting for a response. Requests that are not answered
+  /// before they expire or the peer disconnects fail.
+  pending_requests: ExpiringMap<u64, (PeerId, oneshot::Sender<Vec<u8>>)>,
+
   /// Commands to the network layer
   cmdtx: UnboundedSender<Command>,
 
@@ -183,6 +249,57 @@ impl Topic {
 
     Ok(())
   }
+
+  /// Sends a request to a random active peer and waits for its response.
+  ///
+  /// Requests are not propagated beyond the chosen peer, they are
+  /// meant for fetching data that was missed from gossip.
+  pub async fn request(&self, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let response = {
+      let mut inner = self.inner.write();
+      if data.len() > inner.network_config.max_transmit_size {
+        return Err(Error::MessageTooLarge);
+      }
+
+      let (peer, connection) = inner
+        .active_peers
+        .iter()
+        .choose(&mut thread_rng())
+        .map(|(peer, (connection, _))| (*peer, *connection))
+        .ok_or(Error::NoConnectedPeers)?;
+
+      let id = thread_rng().gen();
+      let (tx, rx) = oneshot::channel();
+      inner.pending_requests.insert(id, (peer, tx));
+      inner.send_message(
+        peer,
+        connection,
+        Message::new(
+          inner.topic_config.name.clone(),
+          Action::Request(Request {
+            id,
+            payload: data.into(),
+          }),
+        ),
+      );
+      rx
+    };
+
+    response.await.map_err(|_| Error::RequestFailed)
+  }
+
+  /// Requests sent to this node by its active peers on this topic.
+  pub fn requests(&self) -> impl Stream<Item = InboundRequest> + Unpin {
+    let inner = Arc::clone(&self.inner);
+    futures::stream::poll_fn(move |cx| {
+      let mut inner = inner.write();
+      let request = inner.inrequests.poll_recv(cx);
+      if let Poll::Ready(Some(_)) = request {
+        inner.queued_requests -= 1;
+      }
+      request
+    })
+  }
 }
 
 // internal api
@@ -213,6 +330,9 @@ impl Topic {
     Self {
       inner: Arc::new(RwLock::new(TopicInner {
         outmsgs: Channel::new(),
+        inrequests: Channel::new(),
+        queued_requests: 0,
+        pending_requests: ExpiringMap::new(timeout),
         last_shuffle: Instant::now(),
         active_peers: HashMap::new(),
         passive_peers: HashMap::new(),
@@ -284,6 +404,8 @@ impl TopicInner {
         self.consume_shuffle_reply(sender, sr, connection)
       }
       Action::Gossip(b) => self.consume_gossip(sender, b, connection),
+      Action::Request(r) => self.consume_request(sender, r, connection),
+      Action::Response(r) => self.consume_response(sender, r),
     }
   }
 
@@ -408,6 +530,7 @@ impl TopicInner {
     self.move_active_to_passive(peer);
     self.pending_shuffle_replies.remove(&peer);
     self.pending_disconnects.remove(&peer);
+    self.pending_requests.retain(|_, (p, _)| *p != peer);
   }
 
   fn handle_tick(&mut self) {
@@ -417,6 +540,7 @@ impl TopicInner {
     self.pending_joins.prune_expired();
     self.pending_neighbours.prune_expired();
     self.pending_shuffle_replies.prune_expired();
+    self.pending_requests.prune_expired();
 
     if self.last_shuffle.elapsed() > self.network_config.shuffle_interval {
       self.initiate_shuffle();
@@ -1168,6 +1292,72 @@ impl TopicInner {
 
     self.outmsgs.send(msg.into());
   }
+
+  /// Invoked when an active peer asks this node for some data.
+  ///
+  /// Those requests are emitted to listeners on this topic requests
+  /// and are never forwarded to other peers.
+  fn consume_request(
+    &mut self,
+    sender: PeerId,
+    msg: Request,
+    connection: ConnectionId,
+  ) {
+    // only active peers are allowed to send this message.
+    if !self.is_active(&sender) {
+      self.disconnect(sender, connection, "request from inactive peer");
+      return;
+    }
+
+    increment_counter!(
+      "received_request",
+      "topic" => self.topic_config.name.clone());
+
+    // nobody is reading requests fast enough, or at all.
+    if self.queued_requests >= self.network_config.max_pending_requests {
+      increment_counter!(
+        "dropped_request",
+        "topic" => self.topic_config.name.clone());
+      debug!(
+        "{}: dropping request {} from {sender}",
+        self.topic_config.name, msg.id
+      );
+      return;
+    }
+
+    self.queued_requests += 1;
+    self.inrequests.send(InboundRequest {
+      id: msg.id,
+      peer: sender,
+      connection,
+      payload: msg.payload.into(),
+      topic: self.topic_config.name.clone(),
+      max_transmit_size: self.network_config.max_transmit_size,
+      cmdtx: self.cmdtx.clone(),
+    });
+  }
+
+  /// Invoked when a peer answers a request sent by this node.
+  ///
+  /// Responses are accepted only from the peer that was asked.
+  fn consume_response(&mut self, sender: PeerId, msg: Response) {
+    if !matches!(
+      self.pending_requests.get(&msg.id),
+      Some((peer, _)) if *peer == sender)
+    {
+      debug!(
+        "{}: unsolicited response {} from {sender}",
+        self.topic_config.name, msg.id
+      );
+      return;
+    }
+
+    let (_, tx) = self
+      .pending_requests
+      .remove(&msg.id)
+      .expect("checked above");
+    let _ = tx.send(msg.payload.into());
+  }
 }
 
 impl TopicInner {
@@ -1268,3 +1458,114 @@ impl Stream for Topic {
     self.inner.write().outmsgs.poll_recv(cx)
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::{Config, Event, Topic},
+    crate::{
+      network::Command,
+      wire::{Action, Message, Request},
+    },
+    futures::StreamExt,
+    libp2p::{core::connection::ConnectionId, PeerId},
+    std::collections::HashSet,
+    tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver},
+  };
+
+  /// A topic that has `peer` in its active view, along with
+  /// the commands it sends to the network layer.
+  fn topic(
+    this: PeerId,
+    peer: PeerId,
+    network_config: crate::Config,
+  ) -> (Topic, UnboundedReceiver<Command>) {
+    let (cmdtx, cmdrx) = unbounded_channel();
+    let topic = Topic::new(
+      Config {
+        name: "/test".into(),
+        bootstrap: HashSet::new(),
+      },
+      network_config,
+      this.into(),
+      cmdtx,
+    );
+    topic
+      .inner
+      .write()
+      .active_peers
+      .insert(peer, (ConnectionId::new(0), HashSet::new()));
+    (topic, cmdrx)
+  }
+
+  /// Delivers the next message sent by a topic to another topic.
+  async fn deliver(
+    sender: PeerId,
+    cmdrx: &mut UnboundedReceiver<Command>,
+    receiver: &mut Topic,
+  ) {
+    match cmdrx.recv().await {
+      Some(Command::SendMessage { msg, .. }) => receiver.inject_event(
+        Event::MessageReceived(sender, msg, ConnectionId::new(0)),
+      ),
+      other => panic!("unexpected command {other:?}"),
+    }
+  }
+
+  #[tokio::test]
+  async fn request_response_roundtrip() {
+    let (alice, bob) = (PeerId::random(), PeerId::random());
+    let (mut alice_topic, mut alice_cmds) =
+      topic(alice, bob, Default::default());
+    let (mut bob_topic, mut bob_cmds) = topic(bob, alice, Default::default());
+
+    let response = tokio::spawn({
+      let alice_topic = alice_topic.clone();
+      async move { alice_topic.request(b"ping".to_vec()).await }
+    });
+
+    deliver(alice, &mut alice_cmds, &mut bob_topic).await;
+    let request = bob_topic.requests().next().await.unwrap();
+    assert_eq!(request.peer(), &alice);
+    assert_eq!(request.payload(), b"ping");
+    request.respond(b"pong".to_vec()).unwrap();
+
+    deliver(bob, &mut bob_cmds, &mut alice_topic).await;
+    assert_eq!(response.await.unwrap().unwrap(), b"pong");
+  }
+
+  #[tokio::test]
+  async fn unread_requests_are_bounded() {
+    let (alice, bob) = (PeerId::random(), PeerId::random());
+    let (mut topic, _cmds) = topic(bob, alice, crate::Config {
+      max_pending_requests: 2,
+      ..Default::default()
+    });
+
+    let request = |id| {
+      Event::MessageReceived(
+        alice,
+        Message::new(
+          "/test".into(),
+          Action::Request(Request {
+            id,
+            payload: vec![].into(),
+          }),
+        ),
+        ConnectionId::new(0),
+      )
+    };
+
+    // the third request is dropped while nobody reads them
+    for id in 0..3 {
+      topic.inject_event(request(id));
+    }
+    let mut requests = topic.requests();
+    assert_eq!(requests.next().await.unwrap().id, 0);
+    assert_eq!(requests.next().await.unwrap().id, 1);
+
+    // and there is room for more once they are read
+    topic.inject_event(request(3));
+    assert_eq!(requests.next().await.unwrap().id, 3);
+  }
+}
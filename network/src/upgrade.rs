@@ -1,9 +1,9 @@
 use {
-  crate::codec::Codec,
+  crate::{codec::Codec, wire::Action},
   asynchronous_codec::Framed,
   futures::{future, AsyncRead, AsyncWrite},
   libp2p::{core::UpgradeInfo, InboundUpgrade, OutboundUpgrade},
-  std::{future::Future, iter, pin::Pin},
+  std::{array, future::Future, pin::Pin},
   thiserror::Error,
 };
 
@@ -22,6 +22,50 @@ pub enum Error {
   Deserialization(#[from] rmp_serde::decode::Error),
 }
 
+/// Versions of the gossip protocol spoken by this node.
+///
+/// Both are advertised when negotiating a substream, so nodes that only
+/// know the original version can still join the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+  /// The original protocol, without topic requests and responses.
+  V1_0,
+
+  /// Adds requests and responses between active peers of a topic.
+  V1_1,
+}
+
+impl Protocol {
+  /// Unique libp2p protocol identifier of this version.
+  pub const fn id(self) -> &'static [u8] {
+    match self {
+      Protocol::V1_0 => b"/anoma/gossip/1.0",
+      Protocol::V1_1 => b"/anoma/gossip/1.1",
+    }
+  }
+
+  /// Whether a peer speaking this version can decode the action.
+  ///
+  /// Peers fail to decode unknown actions and drop the connection,
+  /// so those are never sent to them.
+  pub fn supports(self, action: &Action) -> bool {
+    match self {
+      Protocol::V1_0 => {
+        !matches!(action, Action::Request(_) | Action::Response(_))
+      }
+      Protocol::V1_1 => true,
+    }
+  }
+
+  fn from_id(id: &[u8]) -> Self {
+    if id == Protocol::V1_0.id() {
+      Protocol::V1_0
+    } else {
+      Protocol::V1_1
+    }
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct ProtocolUpgrade {
   max_transmit_size: usize,
@@ -35,18 +79,18 @@ impl ProtocolUpgrade {
 
 impl UpgradeInfo for ProtocolUpgrade {
   type Info = &'static [u8];
-  type InfoIter = iter::Once<Self::Info>;
+  type InfoIter = array::IntoIter<Self::Info, 2>;
 
   fn protocol_info(&self) -> Self::InfoIter {
-    // unique libp2p protocol identifier used when connecting
+    // unique libp2p protocol identifiers used when connecting
     // to other nodes and negotiating common protocols between
-    // both endpoints.
-    iter::once(b"/anoma/gossip/1.0")
+    // both endpoints, in order of preference.
+    [Protocol::V1_1.id(), Protocol::V1_0.id()].into_iter()
   }
 }
 
 type NetworkFrame<Socket> = Framed<Socket, Codec>;
-type UpgradeResult<Socket> = Result<NetworkFrame<Socket>, Error>;
+type UpgradeResult<Socket> = Result<(NetworkFrame<Socket>, Protocol), Error>;
 
 /// Invoked when a remote node is trying to connect to us
 /// This logic is used to start the protocol-specific handshake
@@ -56,13 +100,13 @@ where
 {
   type Error = Error;
   type Future = Pin<Box<dyn Future<Output = UpgradeResult<Socket>> + Send>>;
-  type Output = NetworkFrame<Socket>;
+  type Output = (NetworkFrame<Socket>, Protocol);
 
   // handshake succeeded, both endpoints support this protocol
-  fn upgrade_inbound(self, socket: Socket, _: Self::Info) -> Self::Future {
-    Box::pin(future::ok(Framed::new(
-      socket,
-      Codec::new(self.max_transmit_size),
+  fn upgrade_inbound(self, socket: Socket, info: Self::Info) -> Self::Future {
+    Box::pin(future::ok((
+      Framed::new(socket, Codec::new(self.max_transmit_size)),
+      Protocol::from_id(info),
     )))
   }
 }
@@ -75,13 +119,40 @@ where
 {
   type Error = Error;
   type Future = Pin<Box<dyn Future<Output = UpgradeResult<Socket>> + Send>>;
-  type Output = NetworkFrame<Socket>;
+  type Output = (NetworkFrame<Socket>, Protocol);
 
   // handshake succeeded, both endpoints support this protocol
-  fn upgrade_outbound(self, socket: Socket, _: Self::Info) -> Self::Future {
-    Box::pin(future::ok(Framed::new(
-      socket,
-      Codec::new(self.max_transmit_size),
+  fn upgrade_outbound(self, socket: Socket, info: Self::Info) -> Self::Future {
+    Box::pin(future::ok((
+      Framed::new(socket, Codec::new(self.max_transmit_size)),
+      Protocol::from_id(info),
     )))
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::Protocol,
+    crate::wire::{Action, Request},
+    libp2p::core::UpgradeInfo,
+  };
+
+  #[test]
+  fn legacy_peers_get_no_requests() {
+    let upgrade = super::ProtocolUpgrade::new(1024);
+    let ids: Vec<_> = upgrade.protocol_info().collect();
+    assert_eq!(ids, [Protocol::V1_1.id(), Protocol::V1_0.id()]);
+    assert_eq!(Protocol::from_id(b"/anoma/gossip/1.0"), Protocol::V1_0);
+    assert_eq!(Protocol::from_id(b"/anoma/gossip/1.1"), Protocol::V1_1);
+
+    let request = Action::Request(Request {
+      id: 1,
+      payload: vec![1].into(),
+    });
+    let gossip = Action::Gossip(vec![1].into());
+    assert!(Protocol::V1_1.supports(&request));
+    assert!(Protocol::V1_0.supports(&gossip));
+    assert!(!Protocol::V1_0.supports(&request));
+  }
+}
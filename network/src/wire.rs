@@ -116,6 +116,31 @@ pub struct ShuffleReply {
   pub peers: HashSet<AddressablePeer>,
 }
 
+/// Asks a single active peer of a topic for some data.
+///
+/// Unlike gossip, requests are not forwarded to other peers. The
+/// receiving peer is expected to reply with a [`Response`] that
+/// carries the same id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Request {
+  /// Randomly generated by the requesting node and
+  /// used to match responses with pending requests.
+  pub id: u64,
+
+  /// Opaque request contents, interpreted by the topic users.
+  pub payload: Bytes,
+}
+
+/// Sent as a reply to a [`Request`] to the requesting peer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+  /// Id of the request this response answers.
+  pub id: u64,
+
+  /// Opaque response contents, interpreted by the topic users.
+  pub payload: Bytes,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Action {
   Join(Join),
@@ -124,6 +149,8 @@ pub enum Action {
   Shuffle(Shuffle),
   ShuffleReply(ShuffleReply),
   Gossip(Bytes),
+  Request(Request),
+  Response(Response),
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -6,10 +6,27 @@ use {
   serde::{Deserialize, Serialize},
 };
 
+/// Information about the block in which a transaction is executed.
+///
+/// It is made available to predicates through the `PredicateContext`,
+/// so they can express time locks, deadlines and vesting schedules.
+#[derive(
+  Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct BlockContext {
+  pub height: u64,
+
+  /// Block production time in seconds since unix epoch.
+  pub timestamp: u64,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Block {
   pub height: u64,
   pub parent: Multihash,
+
+  /// Block production time in seconds since unix epoch.
+  pub timestamp: u64,
   pub transactions: Vec<Transaction>,
 
   #[serde(skip)]
@@ -17,10 +34,15 @@ pub struct Block {
 }
 
 impl Block {
-  pub fn new(parent: &Block, transactions: Vec<Transaction>) -> Self {
+  pub fn new(
+    parent: &Block,
+    timestamp: u64,
+    transactions: Vec<Transaction>,
+  ) -> Self {
     Self {
       height: parent.height + 1,
       parent: *parent.hash(),
+      timestamp,
       transactions,
       hash_cache: Default::default(),
     }
@@ -30,11 +52,20 @@ impl Block {
     Self {
       height: 0,
       parent: Multihash::default(),
+      timestamp: 0,
       transactions: vec![],
       hash_cache: OnceCell::new(),
     }
   }
 
+  /// The context exposed to predicates of transactions in this block.
+  pub fn context(&self) -> BlockContext {
+    BlockContext {
+      height: self.height,
+      timestamp: self.timestamp,
+    }
+  }
+
   pub fn hash(&self) -> &Multihash {
     self.hash_cache.get_or_init(|| {
       let mut hasher = Sha3_256::default();
@@ -49,6 +80,7 @@ impl core::fmt::Debug for Block {
     f.debug_struct("Block")
       .field("height", &self.height)
       .field("parent", &self.parent.to_b58())
+      .field("timestamp", &self.timestamp)
       .field("hash", &self.hash().to_b58())
       .field("transactions", &self.transactions)
       .finish()
@@ -29,6 +29,9 @@ pub struct Intent<R: Repr = Basic> {
 
   #[serde(skip)]
   hash_cache: OnceCell<Multihash>,
+
+  #[serde(skip)]
+  signing_hash_cache: OnceCell<Multihash>,
 }
 
 impl<R: Repr> Intent<R> {
@@ -41,6 +44,7 @@ impl<R: Repr> Intent<R> {
       expectations,
       calldata: Calldata::new(),
       hash_cache: OnceCell::new(),
+      signing_hash_cache: OnceCell::new(),
     }
   }
 
@@ -54,6 +58,7 @@ impl<R: Repr> Intent<R> {
       expectations,
       calldata,
       hash_cache: OnceCell::new(),
+      signing_hash_cache: OnceCell::new(),
     }
   }
 }
@@ -83,7 +88,7 @@ impl<R: Repr> Intent<R> {
   /// This hash is used as the message when signatures need
   /// to be attached to intents.
   pub fn signing_hash(&self) -> &Multihash {
-    self.hash_cache.get_or_init(|| {
+    self.signing_hash_cache.get_or_init(|| {
       let mut hasher = Sha3_256::default();
       hasher.update(&rmp_serde::to_vec(&self.recent_blockhash).unwrap());
       hasher.update(&rmp_serde::to_vec(&self.expectations).unwrap());
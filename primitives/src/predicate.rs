@@ -1,5 +1,12 @@
 use {
-  crate::{Address, Calldata, Basic, ExpandedAccountChange, Repr},
+  crate::{
+    Address,
+    Basic,
+    BlockContext,
+    Calldata,
+    ExpandedAccountChange,
+    Repr,
+  },
   alloc::{
     boxed::Box,
     collections::BTreeMap,
@@ -211,15 +218,20 @@ impl<T> ExpressionTree<T> {
 /// This context object is passed to predicates during evaluation stage.
 /// It contains all input key-value pairs attached to predicates and
 /// a list of all mutated accounts by a transaction.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct PredicateContext {
-  /// Intent input key-value pair groupped by the intent hash.
+  /// Intent input key-value pair groupped by the intent signing hash.
   /// Could include things like signature or other arbitrary
-  /// input parameters to predicates.
+  /// input parameters to predicates. Calldata of intents with the
+  /// same signing hash is merged, as long as they don't carry different
+  /// values under the same key.
   pub calldata: BTreeMap<Multihash, Calldata>,
 
   /// Changes to accounts that are modified by a transaction.
   pub proposals: BTreeMap<Address, ExpandedAccountChange>,
+
+  /// The block in which the transaction is executed.
+  pub block: BlockContext,
 }
 
 #[cfg(test)]
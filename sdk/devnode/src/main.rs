@@ -54,20 +54,23 @@ async fn main() -> anyhow::Result<()> {
 
   // start network and get topic handles for txs and blocks
   let (mut txs_topic, blocks_topic) = start_network(&settings)?;
+  let mut block_requests = blocks_topic.requests();
 
   // start time-based block production trigger
   let mut interval = interval(settings.block_time());
   interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
   let history_length = NonZeroUsize::new(64).expect("compile time constant");
-  let mut code_cache = InMemoryStateStore::default();
-  let mut state_store = InMemoryStateStore::default();
-  let mut mempool = Mempool::new(BlockStateBuilder::new(
-    history_length,
-    &mut state_store,
-    &mut code_cache,
-    std::iter::once(Block::zero()),
-  )?);
+  let mut mempool = Mempool::new(
+    BlockStateBuilder::new(
+      history_length,
+      InMemoryStateStore::default(),
+      InMemoryStateStore::default(),
+      std::iter::once(Block::zero()),
+    )?,
+    settings.pre_execute(),
+    settings.max_block_size(),
+  );
 
   loop {
     tokio::select! {
@@ -76,6 +79,15 @@ async fn main() -> anyhow::Result<()> {
           mempool.consume(tx);
         }
       }
+      Some(request) = block_requests.next() => {
+        // peers that missed some blocks from gossip
+        if let Ok(query) = from_slice(request.payload()) {
+          let blocks = mempool.serve(&query);
+          if let Err(e) = request.respond(to_vec(&blocks)?) {
+            warn!("failed to respond to block request: {e:?}");
+          }
+        }
+      }
       _ = interval.tick() => {
         let block = mempool.produce();
         info!("produced block {} (#{}) on top of {} with {} transactions.",
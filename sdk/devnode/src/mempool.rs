@@ -1,34 +1,523 @@
 use {
-  anoma_sdk::BlockStateBuilder,
-  anoma_primitives::{Block, Transaction},
+  anoma_primitives::{
+    Address,
+    Block,
+    BlockContext,
+    Code,
+    Param,
+    PredicateTree,
+    Transaction,
+  },
+  anoma_sdk::{token::STDPRED_ADDRESS, BlockRequest, BlockStateBuilder},
+  anoma_vm::{execute, Overlayed, State, StateDiff, TransactionRefs},
+  std::{
+    collections::HashSet,
+    time::{SystemTime, UNIX_EPOCH},
+  },
+  tracing::{debug, warn},
 };
 
-pub struct Mempool<'s> {
+/// A transaction that was executed on arrival against the chain
+/// state combined with the effects of all pending transactions that
+/// arrived before it.
+struct Pending {
+  tx: Transaction,
+  refs: TransactionRefs,
+  diff: StateDiff,
+  deadline: Deadline,
+}
+
+/// Bounds of the blocks a transaction can still be included in, as far
+/// as the stdpred time-locks required by its intents tell. Checking them
+/// is much cheaper than executing the transaction again in every block.
+struct Deadline {
+  /// First block height at which the transaction is no longer valid.
+  height: u64,
+
+  /// First block timestamp at which the transaction is no longer valid.
+  timestamp: u64,
+}
+
+impl Deadline {
+  fn of(tx: &Transaction) -> Self {
+    let mut deadline = Self {
+      height: u64::MAX,
+      timestamp: u64::MAX,
+    };
+    for intent in &tx.intents {
+      deadline.restrict(&intent.expectations);
+    }
+    deadline
+  }
+
+  /// Only time-locks that must hold for the whole tree bound the
+  /// transaction, those under `Or` or `Not` may never be evaluated.
+  fn restrict(&mut self, tree: &PredicateTree) {
+    match tree {
+      PredicateTree::And(l, r) => {
+        self.restrict(l);
+        self.restrict(r);
+      }
+      PredicateTree::Id(predicate) => {
+        let Code::AccountRef(address, entrypoint) = &predicate.code else {
+          return;
+        };
+        if address.to_string() != STDPRED_ADDRESS {
+          return;
+        }
+
+        let inline = |ix: usize| match predicate.params.get(ix) {
+          Some(Param::Inline(value)) => {
+            rmp_serde::from_slice::<u64>(value).ok()
+          }
+          _ => None,
+        };
+        let (bound, limit) = match entrypoint.as_str() {
+          "before_height" => (&mut self.height, inline(0)),
+          "between_heights" => {
+            (&mut self.height, inline(1).map(|e| e.saturating_add(1)))
+          }
+          "before_timestamp" => (&mut self.timestamp, inline(0)),
+          "between_timestamps" => {
+            (&mut self.timestamp, inline(1).map(|e| e.saturating_add(1)))
+          }
+          _ => return,
+        };
+        if let Some(limit) = limit {
+          *bound = limit.min(*bound);
+        }
+      }
+      PredicateTree::Not(_) | PredicateTree::Or(..) => {}
+    }
+  }
+
+  fn passed(&self, block: &BlockContext) -> bool {
+    block.height >= self.height || block.timestamp >= self.timestamp
+  }
+}
+
+pub struct Mempool {
+  /// Transactions waiting for a block when pre-execution is off.
   txs: Vec<Transaction>,
-  blocks: BlockStateBuilder<'s>,
+
+  /// Pre-executed transactions and their combined effects.
+  pending: Vec<Pending>,
+  overlay: StateDiff,
+  pre_execute: bool,
+  max_block_size: usize,
+  blocks: BlockStateBuilder,
 }
 
-impl<'s> Mempool<'s> {
-  pub fn new(block_consumer: BlockStateBuilder<'s>) -> Self {
+impl Mempool {
+  /// Creates a new mempool on top of the given chain state.
+  ///
+  /// When `pre_execute` is set, every transaction is executed as soon as it
+  /// arrives against the current chain state and all pending transactions,
+  /// and transactions that fail are never included in a block. At most
+  /// `max_block_size` of them are included in one block, and the rest wait
+  /// for the next one. Otherwise all transactions are included in the next
+  /// block and are only executed when it is applied.
+  pub fn new(
+    block_consumer: BlockStateBuilder,
+    pre_execute: bool,
+    max_block_size: usize,
+  ) -> Self {
     Self {
       txs: vec![],
+      pending: vec![],
+      overlay: StateDiff::default(),
+      pre_execute,
+      max_block_size,
       blocks: block_consumer,
     }
   }
 
   pub fn consume(&mut self, tx: Transaction) {
-    self.txs.push(tx);
+    if !self.pre_execute {
+      self.txs.push(tx);
+      return;
+    }
+
+    match self.try_execute(tx) {
+      Ok(pending) => {
+        self.overlay.apply(pending.diff.clone());
+        self.pending.push(pending);
+      }
+      Err((hash, err)) => {
+        warn!("dropping transaction {hash}: {err}");
+      }
+    }
   }
 
   pub fn produce(&mut self) -> Block {
-    let txs = std::mem::take(&mut self.txs);
-    let parent = self.blocks.last();
-    let block = Block::new(parent, txs);
+    let next = self.next_block();
+    if !self.pre_execute {
+      let txs = std::mem::take(&mut self.txs);
+      return self.commit(next, txs);
+    }
+
+    self.expire(&next);
+    let count = self.pending.len().min(self.max_block_size);
+    let committed: Vec<_> = self.pending.drain(..count).collect();
+
+    // accounts locked for writing by transactions in this block.
+    let dirty: HashSet<Address> = committed
+      .iter()
+      .flat_map(|p| p.refs.writes().cloned())
+      .collect();
+
+    let txs = committed.into_iter().map(|p| p.tx).collect();
+    let block = self.commit(next, txs);
+    self.revalidate(dirty);
+    block
+  }
+
+  fn commit(&mut self, context: BlockContext, txs: Vec<Transaction>) -> Block {
+    let block = Block::new(self.blocks.last(), context.timestamp, txs);
     self
       .blocks
       .consume(block.clone())
       .expect("height and parent verified here");
+    block
+  }
+
+  /// Recently produced blocks that match a request of a peer that
+  /// missed them from gossip.
+  pub fn serve(&self, request: &BlockRequest) -> Vec<Block> {
+    self.blocks.serve(request)
+  }
+
+  /// The context of the block that is going to be produced next.
+  ///
+  /// Block timestamps never go backwards, even if the system clock does.
+  fn next_block(&self) -> BlockContext {
+    let parent = self.blocks.last();
+    let now = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_secs())
+      .unwrap_or_default();
+    BlockContext {
+      height: parent.height + 1,
+      timestamp: now.max(parent.timestamp),
+    }
+  }
+
+  /// Executes a transaction against the chain state overlayed with all
+  /// pending transactions and records the accounts it locks.
+  ///
+  /// Transactions are executed in the context of the next block, which
+  /// is where they are expected to be included.
+  #[allow(clippy::result_large_err)]
+  fn try_execute(
+    &self,
+    tx: Transaction,
+  ) -> Result<Pending, (String, anoma_vm::RuntimeError)> {
+    let hash = bs58::encode(tx.hash().to_bytes()).into_string();
+    let state = Overlayed::new(&self.blocks, &self.overlay);
+    let refs = TransactionRefs::new(&tx, &state);
+    let block = self.next_block();
+    match execute(tx.clone(), block, &state, self.blocks.codecache()) {
+      Ok(diff) => {
+        debug!("transaction {hash} pre-executed successfully");
+        let deadline = Deadline::of(&tx);
+        Ok(Pending {
+          tx,
+          refs,
+          diff,
+          deadline,
+        })
+      }
+      Err(e) => Err((hash, e)),
+    }
+  }
+
+  /// Drops pending transactions that can't be included in the given
+  /// block because one of their time-locks has passed. Transactions
+  /// that depend on the accounts they would have written are revalidated.
+  fn expire(&mut self, block: &BlockContext) {
+    let mut dirty = HashSet::new();
+    self.pending.retain(|pending| {
+      if !pending.deadline.passed(block) {
+        return true;
+      }
+
+      let hash = bs58::encode(pending.tx.hash().to_bytes()).into_string();
+      warn!("dropping expired transaction {hash}");
+      dirty.extend(pending.refs.writes().cloned());
+      false
+    });
+
+    if !dirty.is_empty() {
+      self.revalidate(dirty);
+    }
+  }
 
+  /// Called after a block is committed to the chain state or pending
+  /// transactions expire. All pending transactions that read or write
+  /// any of the accounts locked by the committed or expired transactions
+  /// are executed again on top of the new state, and those that are no
+  /// longer valid are dropped.
+  ///
+  /// Accounts written by re-executed or dropped transactions are locked
+  /// as well, so transactions that depend on them are also revalidated.
+  fn revalidate(&mut self, mut dirty: HashSet<Address>) {
+    self.overlay = StateDiff::default();
+    for pending in std::mem::take(&mut self.pending) {
+      let conflicts = pending
+        .refs
+        .writes()
+        .chain(pending.refs.reads())
+        .any(|addr| dirty.contains(addr));
+
+      if !conflicts {
+        self.overlay.apply(pending.diff.clone());
+        self.pending.push(pending);
+        continue;
+      }
+
+      dirty.extend(pending.refs.writes().cloned());
+      match self.try_execute(pending.tx) {
+        Ok(pending) => {
+          dirty.extend(pending.refs.writes().cloned());
+          self.overlay.apply(pending.diff.clone());
+          self.pending.push(pending);
+        }
+        Err((hash, err)) => {
+          warn!("dropping invalidated transaction {hash}: {err}");
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::{Deadline, Mempool},
+    anoma_primitives::{
+      Account,
+      AccountChange,
+      Block,
+      Code,
+      Intent,
+      Param,
+      Predicate,
+      PredicateTree,
+      Transaction,
+    },
+    anoma_sdk::{BlockStateBuilder, InMemoryStateStore, State, StateDiff},
+    multihash::MultihashDigest,
+    once_cell::sync::Lazy,
+    std::num::NonZeroUsize,
+    wasmer::{Cranelift, Module, Store},
+  };
+
+  const STDPRED: &[u8] = include_bytes!(
+    "../../../target/wasm32-unknown-unknown/release/stdpred.wasm"
+  );
+
+  /// Compiling predicates is slow in debug builds, so stdpred is compiled
+  /// once and served from the code cache.
+  static STDPRED_COMPILED: Lazy<Vec<u8>> = Lazy::new(|| {
+    Module::from_binary(&Store::new(Cranelift::default()), STDPRED)
+      .unwrap()
+      .serialize()
+      .unwrap()
+      .to_vec()
+  });
+
+  fn account(state: Vec<u8>) -> Account {
+    Account {
+      state,
+      predicates: PredicateTree::Id(Predicate {
+        code: Code::Inline(vec![]),
+        params: vec![],
+      }),
+    }
+  }
+
+  fn mempool(pre_execute: bool, max_block_size: usize) -> Mempool {
+    // standard predicates are needed by transactions with expectations
+    let mut stdpred = StateDiff::default();
+    stdpred.set("/stdpred/v1".parse().unwrap(), account(STDPRED.to_vec()));
+    let mut state = InMemoryStateStore::default();
+    state.apply(stdpred);
+
+    let mut compiled = StateDiff::default();
+    let codehash = multihash::Code::Sha3_256.digest(STDPRED);
+    compiled.set(
+      format!(
+        "/predcache/{}",
+        bs58::encode(codehash.to_bytes()).into_string()
+      )
+      .parse()
+      .unwrap(),
+      account(STDPRED_COMPILED.clone()),
+    );
+    let mut codecache = InMemoryStateStore::default();
+    codecache.apply(compiled);
+
+    Mempool::new(
+      BlockStateBuilder::new(
+        NonZeroUsize::new(8).unwrap(),
+        state,
+        codecache,
+        std::iter::once(Block::zero()),
+      )
+      .unwrap(),
+      pre_execute,
+      max_block_size,
+    )
+  }
+
+  fn create(address: &str, state: u8) -> Transaction {
+    Transaction::new(
+      vec![],
+      [(
+        address.parse().unwrap(),
+        AccountChange::CreateAccount(account(vec![state])),
+      )]
+      .into(),
+    )
+  }
+
+  fn replace(address: &str, state: u8) -> Transaction {
+    Transaction::new(
+      vec![],
+      [(
+        address.parse().unwrap(),
+        AccountChange::ReplaceState(vec![state]),
+      )]
+      .into(),
+    )
+  }
+
+  fn stdpred(entrypoint: &str, params: &[u64]) -> PredicateTree {
+    PredicateTree::Id(Predicate {
+      code: Code::AccountRef("/stdpred/v1".parse().unwrap(), entrypoint.into()),
+      params: params
+        .iter()
+        .map(|p| Param::Inline(rmp_serde::to_vec(p).unwrap()))
+        .collect(),
+    })
+  }
+
+  /// A transaction that is only valid in blocks below a given height.
+  /// It creates an account that any later transaction can change.
+  fn expiring(address: &str, height: u64) -> Transaction {
+    let mut account = account(vec![1]);
+    account.predicates = PredicateTree::Id(Predicate {
+      code: Code::AccountRef("/stdpred/v1".parse().unwrap(), "constant".into()),
+      params: vec![Param::Inline(rmp_serde::to_vec(&true).unwrap())],
+    });
+    Transaction::new(
+      vec![Intent::new(
+        multihash::Code::Sha3_256.digest(b"recent"),
+        stdpred("before_height", &[height]),
+      )],
+      [(
+        address.parse().unwrap(),
+        AccountChange::CreateAccount(account),
+      )]
+      .into(),
+    )
+  }
+
+  fn hashes(block: &Block) -> Vec<String> {
     block
+      .transactions
+      .iter()
+      .map(|tx| bs58::encode(tx.hash().to_bytes()).into_string())
+      .collect()
+  }
+
+  fn hash(tx: &Transaction) -> String {
+    bs58::encode(tx.hash().to_bytes()).into_string()
+  }
+
+  #[test]
+  fn without_pre_execution_everything_goes_into_the_next_block() {
+    let mut mempool = mempool(false, 1);
+    let txs = [create("/a", 1), create("/a", 2), replace("/missing", 1)];
+    for tx in &txs {
+      mempool.consume(tx.clone());
+    }
+
+    // invalid transactions are not filtered and blocks are not capped
+    let block = mempool.produce();
+    assert_eq!(hashes(&block), txs.iter().map(hash).collect::<Vec<_>>());
+    assert!(mempool.produce().transactions.is_empty());
+  }
+
+  #[test]
+  fn pre_execution_drops_invalid_transactions() {
+    let mut mempool = mempool(true, 1);
+    let create_a = create("/a", 1);
+    let create_b = create("/b", 1);
+
+    mempool.consume(create_a.clone());
+    mempool.consume(create("/a", 2)); // already created by a pending tx
+    mempool.consume(create_b.clone());
+    mempool.consume(replace("/missing", 1));
+
+    // blocks are capped and pending transactions wait for the next one
+    assert_eq!(hashes(&mempool.produce()), vec![hash(&create_a)]);
+    assert_eq!(hashes(&mempool.produce()), vec![hash(&create_b)]);
+    assert!(mempool.produce().transactions.is_empty());
+  }
+
+  #[test]
+  fn pre_execution_drops_expired_transactions() {
+    let mut mempool = mempool(true, 1);
+    let create_a = create("/a", 1);
+    let expiring_b = expiring("/b", 2);
+    let replace_b = replace("/b", 2);
+    let expiring_c = expiring("/c", 3);
+
+    // all of them are valid in block 1
+    mempool.consume(create_a.clone());
+    mempool.consume(expiring_b);
+    mempool.consume(replace_b);
+    mempool.consume(expiring_c.clone());
+    assert_eq!(mempool.pending.len(), 4);
+    assert_eq!(hashes(&mempool.produce()), vec![hash(&create_a)]);
+
+    // b expires before block 2, and so does the replacement of the
+    // account it creates, while c doesn't depend on it.
+    assert_eq!(hashes(&mempool.produce()), vec![hash(&expiring_c)]);
+    assert!(mempool.pending.is_empty());
+    assert!(mempool.produce().transactions.is_empty());
+
+    // already expired on arrival
+    mempool.consume(expiring("/d", 4));
+    assert!(mempool.pending.is_empty());
+  }
+
+  #[test]
+  fn deadlines_of_time_locks() {
+    let tx = |expectations| {
+      Transaction::new(
+        vec![Intent::new(
+          multihash::Code::Sha3_256.digest(b"recent"),
+          expectations,
+        )],
+        Default::default(),
+      )
+    };
+    let deadline = Deadline::of(&tx(PredicateTree::And(
+      Box::new(stdpred("between_heights", &[1, 5])),
+      Box::new(PredicateTree::And(
+        Box::new(stdpred("before_height", &[10])),
+        Box::new(stdpred("before_timestamp", &[100])),
+      )),
+    )));
+    assert_eq!((deadline.height, deadline.timestamp), (6, 100));
+
+    // alternatives don't bound the transaction
+    let deadline = Deadline::of(&tx(PredicateTree::Or(
+      Box::new(stdpred("before_height", &[2])),
+      Box::new(stdpred("after_height", &[8])),
+    )));
+    assert_eq!((deadline.height, deadline.timestamp), (u64::MAX, u64::MAX));
   }
 }
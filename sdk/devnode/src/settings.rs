@@ -45,6 +45,17 @@ pub struct SystemSettings {
     value_name = "DURATION",
     default_value = "2s")]
   block_time: Duration,
+
+  /// Maximum number of pre-executed transactions included in one block
+  #[clap(long,
+    value_name = "COUNT",
+    default_value = "10000")]
+  max_block_size: usize,
+
+  /// Execute transactions when they arrive in the mempool and drop
+  /// invalid ones before they get included in a block
+  #[clap(long)]
+  pre_execute: bool,
 }
 
 impl SystemSettings {
@@ -79,4 +90,12 @@ impl SystemSettings {
   pub fn block_time(&self) -> std::time::Duration {
     self.block_time.into()
   }
+
+  pub fn max_block_size(&self) -> usize {
+    self.max_block_size
+  }
+
+  pub fn pre_execute(&self) -> bool {
+    self.pre_execute
+  }
 }
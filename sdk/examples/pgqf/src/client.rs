@@ -2,6 +2,7 @@ use {
   crate::{
     io::{
       install_bytecode,
+      request_blocks,
       send_and_confirm_intents,
       send_and_confirm_transaction,
       start_network,
@@ -20,12 +21,21 @@ use {
     PredicateTree,
     Transaction,
   },
-  anoma_sdk::{BlockchainWatcher, InMemoryStateStore},
+  anoma_sdk::{
+    token_wallet_address,
+    Abi,
+    Arg,
+    BlockchainWatcher,
+    InMemoryStateStore,
+    IntentBuilder,
+    Wallet,
+  },
   clap::Parser,
-  ed25519_dalek::{Keypair, Signer},
+  ed25519_dalek::Keypair,
   futures::{future::join_all, StreamExt},
   model::{Campaign, Donation},
   multihash::Multihash,
+  once_cell::sync::Lazy,
   rand::{seq::SliceRandom, Rng},
   rmp_serde::{from_slice, to_vec},
   std::{
@@ -41,6 +51,16 @@ mod io;
 mod model;
 mod settings;
 
+const STDPRED_BYTECODE: &[u8] = include_bytes!(
+  "../../../../target/wasm32-unknown-unknown/release/stdpred.wasm"
+);
+
+/// Describes all predicates exported by the standard predicate library,
+/// used to build type-checked invocations of stdpred predicates.
+static STDPRED_ABI: Lazy<Abi> = Lazy::new(|| {
+  Abi::from_wasm(STDPRED_BYTECODE).expect("stdpred is built with its ABI")
+});
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
   tracing_subscriber::fmt::init();
@@ -49,6 +69,7 @@ async fn main() -> anyhow::Result<()> {
   info!("Client options: {opts:?}");
 
   let (transactions, blocks, intents) = start_network(&opts)?;
+  let blocks_topic = blocks.clone();
   let mut blocks = blocks
     .filter_map(|bytes| ready(from_slice::<Block>(&bytes).ok()))
     .boxed();
@@ -63,23 +84,18 @@ async fn main() -> anyhow::Result<()> {
   let campaign_end = campaign_start + 100;
   info!("Campaign lifetime [{campaign_start}, {campaign_end}]");
 
-  #[allow(clippy::box_default)]
   let mut watcher = BlockchainWatcher::new(
     NonZeroUsize::new(64).unwrap(),
-    Box::leak(Box::new(InMemoryStateStore::default())),
-    Box::leak(Box::new(InMemoryStateStore::default())),
+    InMemoryStateStore::default(),
+    InMemoryStateStore::default(),
     std::iter::once(recent_block),
     blocks,
-  )?;
+  )?
+  .with_backfill(move |request| request_blocks(blocks_topic.clone(), request));
 
   info!("Installing Standard Predicate Library...");
   send_and_confirm_transaction(
-    install_bytecode(
-      "/stdpred".parse()?,
-      include_bytes!(
-        "../../../../target/wasm32-unknown-unknown/release/stdpred.wasm"
-      ),
-    )?,
+    install_bytecode("/stdpred".parse()?, STDPRED_BYTECODE)?,
     &transactions,
     &mut watcher,
   )
@@ -127,7 +143,7 @@ async fn main() -> anyhow::Result<()> {
       campaign_start,
       campaign_end,
       "/token/usdc/spring-2023.eth".parse()?,
-      *watcher.most_recent_block().await.hash(),
+      *watcher.most_recent_block().hash(),
     )?,
     &transactions,
     &mut watcher,
@@ -137,10 +153,7 @@ async fn main() -> anyhow::Result<()> {
 
   // then add one project
   let tx = send_and_confirm_intents(
-    create_project_intents(
-      "project1",
-      *watcher.most_recent_block().await.hash(),
-    )?,
+    create_project_intents("project1", *watcher.most_recent_block().hash())?,
     &intents,
     &mut watcher,
   )
@@ -150,18 +163,9 @@ async fn main() -> anyhow::Result<()> {
   // then add another three projects
   let tx = send_and_confirm_intents(
     [
-      create_project_intents(
-        "project2",
-        *watcher.most_recent_block().await.hash(),
-      )?,
-      create_project_intents(
-        "project3",
-        *watcher.most_recent_block().await.hash(),
-      )?,
-      create_project_intents(
-        "project4",
-        *watcher.most_recent_block().await.hash(),
-      )?,
+      create_project_intents("project2", *watcher.most_recent_block().hash())?,
+      create_project_intents("project3", *watcher.most_recent_block().hash())?,
+      create_project_intents("project4", *watcher.most_recent_block().hash())?,
     ]
     .into_iter()
     .flatten(),
@@ -180,7 +184,7 @@ async fn main() -> anyhow::Result<()> {
       1200,
       &wallets[0],
       treasury_address.clone(),
-      *watcher.most_recent_block().await.hash(),
+      *watcher.most_recent_block().hash(),
     )?,
     &intents,
     &mut watcher,
@@ -196,13 +200,13 @@ async fn main() -> anyhow::Result<()> {
         1800,
         &wallets[1],
         treasury_address.clone(),
-        *watcher.most_recent_block().await.hash(),
+        *watcher.most_recent_block().hash(),
       )?,
       create_donation_to_matching_pool_intents(
         1200,
         &wallets[2],
         treasury_address.clone(),
-        *watcher.most_recent_block().await.hash(),
+        *watcher.most_recent_block().hash(),
       )?,
     ]
     .into_iter()
@@ -235,7 +239,7 @@ async fn main() -> anyhow::Result<()> {
       project,
       amount,
       format!("/token/usdc/little-wallet-{i}.eth").parse()?,
-      *watcher.most_recent_block().await.hash(),
+      *watcher.most_recent_block().hash(),
     )?;
     if intents.gossip(to_vec(&donation_intent)?).is_ok() {
       info!("Donated {amount} to {project}");
@@ -273,7 +277,7 @@ async fn main() -> anyhow::Result<()> {
     std::iter::once(create_funding_redistribution_intent(
       matching_pool_amount,
       donation_amounts,
-      *watcher.most_recent_block().await.hash(),
+      *watcher.most_recent_block().hash(),
     )),
     &intents,
     &mut watcher,
@@ -405,46 +409,33 @@ fn create_donation_to_matching_pool_intents(
   treasury: Address,
   blockhash: Multihash,
 ) -> anyhow::Result<impl Iterator<Item = Intent>> {
-  let from_addr: Address = format!(
-    "/token/usdc/{}",
-    bs58::encode(&from.public.to_bytes()).into_string()
-  )
-  .parse()?;
+  let from_addr = token_wallet_address(&"/token/usdc".parse()?, &from.public);
 
-  let mut intent = Intent::new(
-    blockhash,
-    PredicateTree::And(
-      Box::new(PredicateTree::Id(Predicate {
-        code: Code::AccountRef("/stdpred".parse()?, "uint_less_than_by".into()),
-        params: vec![
-          Param::AccountRef(from_addr.clone()),
-          Param::ProposalRef(from_addr),
-          Param::Inline(to_vec(&amount)?),
-        ],
-      })),
-      Box::new(PredicateTree::Id(Predicate {
-        code: Code::AccountRef(
-          "/stdpred".parse()?,
-          "uint_greater_than_by".into(),
-        ),
-        params: vec![
-          Param::AccountRef(treasury.clone()),
-          Param::ProposalRef(treasury),
-          Param::Inline(to_vec(&amount)?),
-        ],
-      })),
-    ),
-  );
+  let stdpred = &*STDPRED_ABI;
 
   // sign the intent with the donor private key
   // for the payment to be authorized by the token predicates
-  intent.calldata.insert(
-    bs58::encode(&from.public.to_bytes()).into_string(),
-    from
-      .sign(intent.signing_hash().to_bytes().as_slice())
-      .to_bytes()
-      .to_vec(),
-  );
+  let intent = IntentBuilder::new(blockhash)
+    .expect(PredicateTree::Id(stdpred.predicate(
+      "/stdpred".parse()?,
+      "uint_less_than_by",
+      [
+        Arg::AccountRef(from_addr.clone()),
+        Arg::ProposalRef(from_addr),
+        Arg::value(&amount)?,
+      ],
+    )?))
+    .expect(PredicateTree::Id(stdpred.predicate(
+      "/stdpred".parse()?,
+      "uint_greater_than_by",
+      [
+        Arg::AccountRef(treasury.clone()),
+        Arg::ProposalRef(treasury),
+        Arg::value(&amount)?,
+      ],
+    )?))
+    .sign(from)
+    .build()?;
 
   Ok(std::iter::once(intent))
 }
@@ -461,40 +452,38 @@ fn create_project_donation_intent(
   let project_wallet: Address =
     format!("/token/usdc/project-{name}.eth").parse()?;
 
+  let stdpred = &*STDPRED_ABI;
   Ok(Intent::new(
     blockhash,
     PredicateTree::And(
       Box::new(PredicateTree::And(
-        Box::new(PredicateTree::Id(Predicate {
-          code: Code::AccountRef(
-            "/stdpred".parse()?,
-            "uint_greater_than_by".into(),
-          ),
-          params: vec![
-            Param::ProposalRef(project_wallet.clone()),
-            Param::AccountRef(project_wallet),
-            Param::Inline(to_vec(&amount)?),
+        Box::new(PredicateTree::Id(stdpred.predicate(
+          "/stdpred".parse()?,
+          "uint_greater_than_by",
+          [
+            Arg::ProposalRef(project_wallet.clone()),
+            Arg::AccountRef(project_wallet),
+            Arg::value(&amount)?,
           ],
-        })),
-        Box::new(PredicateTree::Id(Predicate {
-          code: Code::AccountRef(
-            "/stdpred".parse()?,
-            "uint_less_than_by".into(),
-          ),
-          params: vec![
-            Param::ProposalRef(from.clone()),
-            Param::AccountRef(from),
-            Param::Inline(to_vec(&amount)?),
+        )?)),
+        Box::new(PredicateTree::Id(stdpred.predicate(
+          "/stdpred".parse()?,
+          "uint_less_than_by",
+          [
+            Arg::ProposalRef(from.clone()),
+            Arg::AccountRef(from),
+            Arg::value(&amount)?,
           ],
-        })),
+        )?)),
       )),
-      Box::new(PredicateTree::Id(Predicate {
-        code: Code::AccountRef("/stdpred".parse()?, "bytes_equal".into()),
-        params: vec![
-          Param::ProposalRef(donation_address),
-          Param::Inline(to_vec(&Donation::default())?),
+      Box::new(PredicateTree::Id(stdpred.predicate(
+        "/stdpred".parse()?,
+        "bytes_equal",
+        [
+          Arg::ProposalRef(donation_address),
+          Arg::Raw(to_vec(&Donation::default())?),
         ],
-      })),
+      )?)),
     ),
   ))
 }
@@ -525,21 +514,17 @@ pub async fn prepare_wallets_and_token_program(
   transactions_topic: &Topic,
   watcher: &mut BlockchainWatcher,
 ) -> anyhow::Result<Vec<(Keypair, Address)>> {
-  // generate as many wallets as the caller wants
-  let wallets: Vec<_> = (0..count)
-    .map(|_| Keypair::generate(&mut rand::thread_rng()))
-    .collect();
+  // derive as many keypairs as the caller wants from a single wallet
+  let wallet = Wallet::generate();
+  let wallets = (0..count as u32)
+    .map(|index| wallet.keypair(index))
+    .collect::<Result<Vec<_>, _>>()?;
 
   // for each keypair, generate its token wallet address.
+  let token: Address = "/token/usdc".parse()?;
   let wallet_addresses: Vec<_> = wallets
     .iter()
-    .map(|w| {
-      Address::new(format!(
-        "/token/usdc/{}",
-        bs58::encode(&w.public.to_bytes()).into_string()
-      ))
-      .expect("should never fail. validated at compile time")
-    })
+    .map(|w| token_wallet_address(&token, &w.public))
     .collect();
 
   let wallets: Vec<_> = wallets
@@ -16,11 +16,11 @@ use {
     PredicateTree,
     Transaction,
   },
-  anoma_sdk::BlockchainWatcher,
+  anoma_sdk::{BlockRequest, BlockchainWatcher},
   futures::future::join_all,
-  rmp_serde::to_vec,
+  rmp_serde::{from_slice, to_vec},
   std::time::Duration,
-  tracing::info,
+  tracing::{info, warn},
 };
 
 /// Gossips an intent through p2p to solvers and awaits a produced block
@@ -130,6 +130,22 @@ pub fn install_bytecode(
   ))
 }
 
+/// Asks a random peer on the blocks topic for blocks that
+/// were missed from gossip.
+pub async fn request_blocks(
+  blocks_topic: Topic,
+  request: BlockRequest,
+) -> Vec<Block> {
+  let response = match blocks_topic.request(to_vec(&request).unwrap()).await {
+    Ok(response) => response,
+    Err(e) => {
+      warn!("block request {request:?} failed: {e:?}");
+      return vec![];
+    }
+  };
+  from_slice(&response).unwrap_or_default()
+}
+
 /// (transactions, blocks, intents) topic handles
 #[allow(dead_code)]
 pub fn start_network(
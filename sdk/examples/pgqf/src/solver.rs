@@ -1,5 +1,8 @@
 use {
-  crate::{io::start_network, settings::SystemSettings},
+  crate::{
+    io::{request_blocks, start_network},
+    settings::SystemSettings,
+  },
   anoma_network::topic::Topic,
   anoma_predicates_sdk::Predicate,
   anoma_primitives::{Block, Code, Intent, Param, PredicateTree},
@@ -7,6 +10,7 @@ use {
     BlockchainWatcher,
     ExpressionPattern,
     InMemoryStateStore,
+    IntentPool,
     ParamPattern,
     Query,
   },
@@ -29,6 +33,7 @@ async fn main() -> anyhow::Result<()> {
 
   let (txs, blocks, intents) = start_network(&opts)?;
 
+  let blocks_topic = blocks.clone();
   let mut blocks = blocks
     .filter_map(|bytes| ready(from_slice::<Block>(&bytes).ok()))
     .boxed();
@@ -41,28 +46,37 @@ async fn main() -> anyhow::Result<()> {
   info!("First observed block: {recent_block:?}");
 
   // This will accumulate global blockchain state changes from incoming blocks
-  let chain_state: &'static mut InMemoryStateStore = Box::leak(Box::default());
-
-  #[allow(clippy::box_default)]
-  BlockchainWatcher::new(
+  let watcher = BlockchainWatcher::new(
     NonZeroUsize::new(64).unwrap(),
-    chain_state,
-    Box::leak(Box::new(InMemoryStateStore::default())),
+    InMemoryStateStore::default(),
+    InMemoryStateStore::default(),
     std::iter::once(recent_block),
     blocks,
-  )?;
+  )?
+  .with_backfill(move |request| request_blocks(blocks_topic.clone(), request));
+
+  // Intents waiting for counterparties, without those
+  // that were already included in blocks or expired.
+  let mut pool = IntentPool::new();
 
   loop {
     tokio::select! {
       Some(intent) = intents.next() => {
         info!("received an intent: {intent:?}");
-        try_match_and_fill_intent(intent, &txs);
+        pool.sync(&watcher);
+        if pool.insert(intent.clone()) {
+          try_match_and_fill_intent(&intent, &pool, &txs);
+        }
       }
     }
   }
 }
 
-fn try_match_and_fill_intent(intent: Intent, _txtopic: &Topic) {
+fn try_match_and_fill_intent(
+  intent: &Intent,
+  pool: &IntentPool,
+  _txtopic: &Topic,
+) {
   let expression = &intent.expectations;
 
   if let Some(_matches) = expression.matches(create_project_intent_pattern()) {
@@ -70,10 +84,14 @@ fn try_match_and_fill_intent(intent: Intent, _txtopic: &Topic) {
   } else if let Some(_matches) =
     expression.matches(create_matching_pool_donation_intents_pattern())
   {
+    // matching pool donations are spread over all known projects
+    let _projects = pool.matching(create_project_intent_pattern());
     // todo
   } else if let Some(_matches) =
     expression.matches(create_project_donation_intent_pattern())
   {
+    // donations are filled with intents of the project they are made to
+    let _counterparties: Vec<_> = pool.counterparties(intent).collect();
     // todo
   }
 }
@@ -0,0 +1,498 @@
+use {
+  anoma_primitives::{Address, Code, Param, Predicate},
+  serde::{Deserialize, Serialize, Serializer},
+  std::collections::BTreeMap,
+  thiserror::Error,
+};
+
+mod descriptor;
+
+/// Name of the WASM custom section where the predicates SDK
+/// stores the description of all predicates exported by a module.
+pub const ABI_SECTION: &str = "anoma_abi";
+
+#[derive(Debug, Error)]
+pub enum Error {
+  #[error("Invalid WASM module: {0}")]
+  InvalidWasm(&'static str),
+
+  #[error("WASM module has no ABI section")]
+  MissingAbi,
+
+  #[error("Invalid ABI description: {0}")]
+  InvalidAbi(#[from] serde_json::Error),
+
+  #[error("Entrypoint '{0}' is not exported by this module")]
+  UnknownEntrypoint(String),
+
+  #[error("Entrypoint '{entrypoint}' expects {expected} params, got {actual}")]
+  ArgumentCount {
+    entrypoint: String,
+    expected: usize,
+    actual: usize,
+  },
+
+  #[error("Param '{param}' expects a value of type {expected}, got {actual}")]
+  TypeMismatch {
+    param: String,
+    expected: String,
+    actual: String,
+  },
+
+  #[error("Param '{0}' expects raw bytes, not a MessagePack value")]
+  ExpectingRaw(String),
+
+  #[error("Param '{0}' expects a MessagePack value, not raw bytes")]
+  ExpectingValue(String),
+
+  #[error("Failed to encode argument value: {0}")]
+  Encoding(#[from] rmp_serde::encode::Error),
+}
+
+/// How the predicate expects a parameter value to be encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+  /// Value is decoded from MessagePack into the declared type.
+  Msgpack,
+
+  /// Value is passed to the predicate as undecoded bytes.
+  Raw,
+
+  /// The predicate receives the parameter along with information about
+  /// how it was resolved, the value encoding is up to the predicate.
+  Expanded,
+}
+
+/// Describes one parameter of a predicate entrypoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParamSpec {
+  pub name: String,
+
+  /// Shape of the value in the serde data model, e.g. `u64`, `string`,
+  /// `seq<u8>` or `map<string,u64>`. Structs and enums are identified by
+  /// the name they report to serde.
+  #[serde(rename = "type")]
+  pub ty: String,
+  pub encoding: Encoding,
+}
+
+/// Describes a predicate function exported by a WASM module.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entrypoint {
+  #[serde(rename = "entrypoint")]
+  pub name: String,
+  pub docs: String,
+
+  /// Expected parameters in order, or `None` if the predicate
+  /// decodes its parameters on its own.
+  pub params: Option<Vec<ParamSpec>>,
+}
+
+/// An argument to a predicate that is being constructed through
+/// an ABI description.
+#[derive(Debug, Clone)]
+pub enum Arg {
+  /// A MessagePack encoded value along with the descriptor of its type.
+  Value { ty: String, bytes: Vec<u8> },
+
+  /// Bytes passed to the predicate as they are.
+  Raw(Vec<u8>),
+
+  /// Value resolved from the current state of an account.
+  AccountRef(Address),
+
+  /// Value resolved from the proposed state of an account.
+  ProposalRef(Address),
+
+  /// Value resolved from intent calldata.
+  CalldataRef(String),
+}
+
+impl Arg {
+  /// Encodes a value as MessagePack while remembering how serde sees
+  /// its type, so it can be checked against the type declared by the
+  /// predicate.
+  pub fn value<T: Serialize + ?Sized>(value: &T) -> Result<Self, Error> {
+    Ok(Self::Value {
+      ty: descriptor::describe(value)?,
+      bytes: rmp_serde::to_vec(value)?,
+    })
+  }
+
+  /// Encodes bytes as a MessagePack binary rather than a sequence of
+  /// integers, for params described as `bytes`, such as public keys or
+  /// the big-endian bytes of 256bit integers.
+  pub fn bytes(bytes: &[u8]) -> Self {
+    struct Binary<'a>(&'a [u8]);
+
+    impl Serialize for Binary<'_> {
+      fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_bytes(self.0)
+      }
+    }
+
+    Self::value(&Binary(bytes)).expect("infallible encoding")
+  }
+
+  fn into_param(self, spec: Option<&ParamSpec>) -> Result<Param, Error> {
+    Ok(match self {
+      Arg::Value { ty, bytes } => {
+        if let Some(spec) = spec {
+          if spec.encoding == Encoding::Raw {
+            return Err(Error::ExpectingRaw(spec.name.clone()));
+          }
+          if spec.encoding == Encoding::Msgpack
+            && !descriptor::compatible(&spec.ty, &ty)
+          {
+            return Err(Error::TypeMismatch {
+              param: spec.name.clone(),
+              expected: spec.ty.clone(),
+              actual: ty,
+            });
+          }
+        }
+        Param::Inline(bytes)
+      }
+      Arg::Raw(bytes) => {
+        if let Some(spec) = spec {
+          if spec.encoding == Encoding::Msgpack {
+            return Err(Error::ExpectingValue(spec.name.clone()));
+          }
+        }
+        Param::Inline(bytes)
+      }
+      // references are resolved by the VM at execution time
+      Arg::AccountRef(addr) => Param::AccountRef(addr),
+      Arg::ProposalRef(addr) => Param::ProposalRef(addr),
+      Arg::CalldataRef(key) => Param::CalldataRef(key),
+    })
+  }
+}
+
+/// Description of all predicates exported by a WASM module.
+///
+/// Predicates built with the predicates SDK carry this description in
+/// the `anoma_abi` custom section of their bytecode. It can also be stored
+/// next to the bytecode as a JSON file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Abi {
+  entrypoints: BTreeMap<String, Entrypoint>,
+}
+
+impl Abi {
+  /// Reads the ABI description embedded in the bytecode of a WASM module.
+  pub fn from_wasm(bytecode: &[u8]) -> Result<Self, Error> {
+    let mut found = false;
+    let mut entrypoints = BTreeMap::new();
+    for payload in custom_sections(bytecode, ABI_SECTION)? {
+      found = true;
+      for line in payload.split(|b| *b == b'\n').filter(|l| !l.is_empty()) {
+        let entrypoint: Entrypoint = serde_json::from_slice(line)?;
+        entrypoints.insert(entrypoint.name.clone(), entrypoint);
+      }
+    }
+
+    match found {
+      true => Ok(Self { entrypoints }),
+      false => Err(Error::MissingAbi),
+    }
+  }
+
+  /// Reads an ABI description stored as a JSON array of entrypoints.
+  pub fn from_json(json: &str) -> Result<Self, Error> {
+    let entrypoints: Vec<Entrypoint> = serde_json::from_str(json)?;
+    Ok(Self {
+      entrypoints: entrypoints
+        .into_iter()
+        .map(|e| (e.name.clone(), e))
+        .collect(),
+    })
+  }
+
+  /// Serializes the ABI description as a JSON array of entrypoints,
+  /// suitable for storing next to the WASM module.
+  pub fn to_json(&self) -> String {
+    serde_json::to_string_pretty(&self.entrypoints.values().collect::<Vec<_>>())
+      .expect("serializing plain data never fails")
+  }
+
+  pub fn entrypoint(&self, name: &str) -> Option<&Entrypoint> {
+    self.entrypoints.get(name)
+  }
+
+  pub fn entrypoints(&self) -> impl Iterator<Item = &Entrypoint> {
+    self.entrypoints.values()
+  }
+
+  /// Builds a predicate that invokes an entrypoint of the module deployed
+  /// at a given address.
+  ///
+  /// Fails if the entrypoint is not exported by the module, the number of
+  /// arguments is different than the number of declared params, or any of
+  /// the inline values has a different type or encoding than declared.
+  /// Account, proposal and calldata references are resolved at execution
+  /// time so their values are not checked.
+  pub fn predicate(
+    &self,
+    module: Address,
+    entrypoint: &str,
+    args: impl IntoIterator<Item = Arg>,
+  ) -> Result<Predicate, Error> {
+    let spec = self
+      .entrypoint(entrypoint)
+      .ok_or_else(|| Error::UnknownEntrypoint(entrypoint.to_string()))?;
+
+    let args: Vec<_> = args.into_iter().collect();
+    if let Some(ref params) = spec.params {
+      if params.len() != args.len() {
+        return Err(Error::ArgumentCount {
+          entrypoint: entrypoint.to_string(),
+          expected: params.len(),
+          actual: args.len(),
+        });
+      }
+    }
+
+    Ok(Predicate {
+      code: Code::AccountRef(module, entrypoint.to_string()),
+      params: args
+        .into_iter()
+        .enumerate()
+        .map(|(ix, arg)| {
+          arg.into_param(spec.params.as_ref().map(|params| &params[ix]))
+        })
+        .collect::<Result<_, _>>()?,
+    })
+  }
+}
+
+/// Returns payloads of all custom sections with a given name.
+fn custom_sections<'a>(
+  bytecode: &'a [u8],
+  name: &str,
+) -> Result<Vec<&'a [u8]>, Error> {
+  const HEADER: &[u8] = b"\0asm\x01\0\0\0";
+  if !bytecode.starts_with(HEADER) {
+    return Err(Error::InvalidWasm("missing module header"));
+  }
+
+  let mut sections = vec![];
+  let mut cursor = &bytecode[HEADER.len()..];
+  while let Some((&id, rest)) = cursor.split_first() {
+    let (size, rest) = read_leb128(rest)?;
+    if rest.len() < size {
+      return Err(Error::InvalidWasm("truncated section"));
+    }
+    let (content, rest) = rest.split_at(size);
+    if id == 0 {
+      let (name_len, content) = read_leb128(content)?;
+      if content.len() < name_len {
+        return Err(Error::InvalidWasm("truncated custom section name"));
+      }
+      let (section_name, payload) = content.split_at(name_len);
+      if section_name == name.as_bytes() {
+        sections.push(payload);
+      }
+    }
+    cursor = rest;
+  }
+  Ok(sections)
+}
+
+fn read_leb128(bytes: &[u8]) -> Result<(usize, &[u8]), Error> {
+  let mut value = 0usize;
+  for (ix, byte) in bytes.iter().enumerate().take(5) {
+    value |= ((byte & 0x7f) as usize) << (ix * 7);
+    if byte & 0x80 == 0 {
+      return Ok((value, &bytes[ix + 1..]));
+    }
+  }
+  Err(Error::InvalidWasm("invalid LEB128 integer"))
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::{descriptor::compatible, Abi, Arg, Error},
+    anoma_primitives::{Address, Code, Param},
+    serde::Serialize,
+    std::collections::BTreeMap,
+  };
+
+  fn wasm_with_abi(entries: &[&str]) -> Vec<u8> {
+    let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+    for entry in entries {
+      let mut content = vec![b"anoma_abi".len() as u8];
+      content.extend_from_slice(b"anoma_abi");
+      content.extend_from_slice(entry.as_bytes());
+      wasm.push(0);
+      let mut size = content.len();
+      while size >= 0x80 {
+        wasm.push((size & 0x7f) as u8 | 0x80);
+        size >>= 7;
+      }
+      wasm.push(size as u8);
+      wasm.extend(content);
+    }
+    wasm
+  }
+
+  fn descriptor<T: Serialize + ?Sized>(value: &T) -> String {
+    match Arg::value(value).unwrap() {
+      Arg::Value { ty, .. } => ty,
+      _ => unreachable!(),
+    }
+  }
+
+  #[test]
+  fn serde_type_descriptors() {
+    #[derive(Serialize)]
+    struct Donation {
+      amount: u64,
+    }
+
+    let address: Address = "/token/usdx".parse().unwrap();
+    assert_eq!(descriptor(&5u64), "u64");
+    assert_eq!(descriptor("text"), "string");
+    assert_eq!(descriptor(&String::new()), "string");
+    assert_eq!(descriptor(&vec![1u8, 2]), "seq<u8>");
+    assert_eq!(descriptor(&[1u8, 2][..]), "seq<u8>");
+    assert_eq!(descriptor(&Vec::<u64>::new()), "seq<_>");
+    assert_eq!(descriptor(&(1u64, "a")), "tuple<u64,string>");
+    assert_eq!(
+      descriptor(&Some(vec![address.clone()])),
+      "option<seq<Address>>"
+    );
+    assert_eq!(
+      descriptor(&BTreeMap::from([(vec![1u8], vec![2u8])])),
+      "map<seq<u8>,seq<u8>>"
+    );
+    assert_eq!(descriptor(&Donation { amount: 1 }), "Donation");
+
+    assert!(compatible("seq<u64>", "seq<_>"));
+    assert!(compatible("map<seq<u8>,u64>", "map<_,_>"));
+    assert!(compatible("option<Address>", "option<Address>"));
+    assert!(!compatible("seq<u64>", "seq<u32>"));
+    assert!(!compatible("u64", "_x"));
+    assert!(!compatible("seq<u8>", "seq<u8"));
+  }
+
+  #[test]
+  fn build_stdpred_predicates() {
+    let abi = Abi::from_wasm(include_bytes!(
+      "../../../target/wasm32-unknown-unknown/release/stdpred.wasm"
+    ))
+    .unwrap();
+
+    let mut amount = [0u8; 32];
+    amount[0] = 1; // 2^248, does not fit in u128
+    let stdpred: Address = "/stdpred/v1".parse().unwrap();
+    let wallet: Address = "/token/usdx/wallet".parse().unwrap();
+    let predicate = abi
+      .predicate(stdpred.clone(), "uint256_greater_than_by", [
+        Arg::ProposalRef(wallet.clone()),
+        Arg::AccountRef(wallet.clone()),
+        Arg::bytes(&amount),
+      ])
+      .unwrap();
+    assert_eq!(
+      predicate.params[2],
+      Param::Inline({
+        let mut encoded = vec![0xc4, 32]; // bin 8 of 32 bytes
+        encoded.extend(amount);
+        encoded
+      })
+    );
+
+    assert!(matches!(
+      abi.predicate(stdpred, "uint256_equal", [
+        Arg::AccountRef(wallet),
+        Arg::value(&amount.to_vec()).unwrap(),
+      ]),
+      Err(Error::TypeMismatch { .. })
+    ));
+  }
+
+  #[test]
+  fn load_and_build_predicates() {
+    let wasm = wasm_with_abi(&[
+      concat!(
+        r#"{"entrypoint":"uint_less_than_by","docs":"","params":["#,
+        r#"{"name":"first","type":"u64","encoding":"msgpack"},"#,
+        r#"{"name":"second","type":"u64","encoding":"msgpack"},"#,
+        r#"{"name":"by","type":"u64","encoding":"msgpack"}]}"#,
+        "\n",
+        r#"{"entrypoint":"bytes_equal","docs":"","params":["#,
+        r#"{"name":"first","type":"seq<u8>","encoding":"raw"},"#,
+        r#"{"name":"second","type":"seq<u8>","encoding":"raw"}]}"#,
+        "\n"
+      ),
+      concat!(
+        r#"{"entrypoint":"legacy","docs":"Untyped","params":null}"#,
+        "\n"
+      ),
+    ]);
+
+    let abi = Abi::from_wasm(&wasm).unwrap();
+    assert_eq!(abi.entrypoints().count(), 3);
+    assert_eq!(Abi::from_json(&abi.to_json()).unwrap(), abi);
+
+    let stdpred: Address = "/stdpred".parse().unwrap();
+    let wallet: Address = "/token/usdx/wallet".parse().unwrap();
+    let predicate = abi
+      .predicate(stdpred.clone(), "uint_less_than_by", [
+        Arg::AccountRef(wallet.clone()),
+        Arg::ProposalRef(wallet.clone()),
+        Arg::value(&5u64).unwrap(),
+      ])
+      .unwrap();
+
+    assert_eq!(
+      predicate.code,
+      Code::AccountRef(stdpred.clone(), "uint_less_than_by".into())
+    );
+    assert_eq!(predicate.params, vec![
+      Param::AccountRef(wallet.clone()),
+      Param::ProposalRef(wallet.clone()),
+      Param::Inline(rmp_serde::to_vec(&5u64).unwrap()),
+    ]);
+
+    assert!(matches!(
+      abi.predicate(stdpred.clone(), "uint_less_than_by", [
+        Arg::AccountRef(wallet.clone()),
+        Arg::ProposalRef(wallet.clone()),
+        Arg::value("5").unwrap(),
+      ]),
+      Err(Error::TypeMismatch { .. })
+    ));
+
+    assert!(matches!(
+      abi.predicate(stdpred.clone(), "uint_less_than_by", [
+        Arg::value(&5u64).unwrap()
+      ]),
+      Err(Error::ArgumentCount { .. })
+    ));
+
+    assert!(matches!(
+      abi.predicate(stdpred.clone(), "bytes_equal", [
+        Arg::ProposalRef(wallet.clone()),
+        Arg::value(&vec![1u8, 2]).unwrap()
+      ]),
+      Err(Error::ExpectingRaw(_))
+    ));
+
+    assert!(abi
+      .predicate(stdpred.clone(), "bytes_equal", [
+        Arg::ProposalRef(wallet.clone()),
+        Arg::Raw(vec![1, 2]),
+      ])
+      .is_ok());
+
+    assert!(abi.predicate(stdpred.clone(), "legacy", []).is_ok());
+    assert!(matches!(
+      abi.predicate(stdpred, "missing", []),
+      Err(Error::UnknownEntrypoint(_))
+    ));
+  }
+}
@@ -0,0 +1,446 @@
+//! Type descriptors in terms of the serde data model.
+//!
+//! A descriptor says how a value is encoded rather than what rust type
+//! it has, so values can be checked against the params declared by a
+//! predicate no matter which crate, module or compiler version defined
+//! their types. Descriptors look like `u64`, `string`, `bytes`, `unit`,
+//! `option<u64>`, `seq<u8>`, `tuple<u64,string>` or `map<string,u64>`.
+//! Structs and enums are described by the name they report to serde, and
+//! `_` stands for an element type that could not be observed, such as the
+//! elements of an empty sequence.
+
+use {
+  rmp_serde::encode::Error,
+  serde::{
+    ser::{
+      SerializeMap,
+      SerializeSeq,
+      SerializeStruct,
+      SerializeStructVariant,
+      SerializeTuple,
+      SerializeTupleStruct,
+      SerializeTupleVariant,
+    },
+    Serialize,
+    Serializer,
+  },
+};
+
+/// Describes the shape of a value as seen by serde.
+pub(super) fn describe<T: Serialize + ?Sized>(
+  value: &T,
+) -> Result<String, Error> {
+  value.serialize(Describer)
+}
+
+/// Checks if a value described by `actual` can be decoded as a param
+/// described by `expected`.
+pub(super) fn compatible(expected: &str, actual: &str) -> bool {
+  match (Shape::parse(expected), Shape::parse(actual)) {
+    (Some(expected), Some(actual)) => expected.accepts(&actual),
+    _ => false,
+  }
+}
+
+/// A parsed descriptor.
+struct Shape<'a> {
+  name: &'a str,
+  args: Vec<Shape<'a>>,
+}
+
+impl<'a> Shape<'a> {
+  fn parse(descriptor: &'a str) -> Option<Self> {
+    match Self::parse_prefix(descriptor)? {
+      (shape, "") => Some(shape),
+      _ => None,
+    }
+  }
+
+  fn parse_prefix(input: &'a str) -> Option<(Self, &'a str)> {
+    let end = input.find(['<', ',', '>']).unwrap_or(input.len());
+    let (name, mut rest) = input.split_at(end);
+    if name.is_empty() {
+      return None;
+    }
+
+    let mut args = vec![];
+    if let Some(inner) = rest.strip_prefix('<') {
+      rest = inner;
+      loop {
+        let (arg, inner) = Self::parse_prefix(rest)?;
+        args.push(arg);
+        match inner.as_bytes().first()? {
+          b',' => rest = &inner[1..],
+          b'>' => {
+            rest = &inner[1..];
+            break;
+          }
+          _ => return None,
+        }
+      }
+    }
+    Some((Self { name, args }, rest))
+  }
+
+  fn accepts(&self, actual: &Shape) -> bool {
+    actual.name == "_"
+      || (self.name == actual.name
+        && self.args.len() == actual.args.len()
+        && self
+          .args
+          .iter()
+          .zip(&actual.args)
+          .all(|(e, a)| e.accepts(a)))
+  }
+}
+
+struct Describer;
+
+impl Serializer for Describer {
+  type Error = Error;
+  type Ok = String;
+  type SerializeMap = Entries;
+  type SerializeSeq = Elements;
+  type SerializeStruct = Named;
+  type SerializeStructVariant = Named;
+  type SerializeTuple = Elements;
+  type SerializeTupleStruct = Named;
+  type SerializeTupleVariant = Named;
+
+  fn serialize_bool(self, _: bool) -> Result<String, Error> {
+    Ok("bool".into())
+  }
+
+  fn serialize_i8(self, _: i8) -> Result<String, Error> {
+    Ok("i8".into())
+  }
+
+  fn serialize_i16(self, _: i16) -> Result<String, Error> {
+    Ok("i16".into())
+  }
+
+  fn serialize_i32(self, _: i32) -> Result<String, Error> {
+    Ok("i32".into())
+  }
+
+  fn serialize_i64(self, _: i64) -> Result<String, Error> {
+    Ok("i64".into())
+  }
+
+  fn serialize_i128(self, _: i128) -> Result<String, Error> {
+    Ok("i128".into())
+  }
+
+  fn serialize_u8(self, _: u8) -> Result<String, Error> {
+    Ok("u8".into())
+  }
+
+  fn serialize_u16(self, _: u16) -> Result<String, Error> {
+    Ok("u16".into())
+  }
+
+  fn serialize_u32(self, _: u32) -> Result<String, Error> {
+    Ok("u32".into())
+  }
+
+  fn serialize_u64(self, _: u64) -> Result<String, Error> {
+    Ok("u64".into())
+  }
+
+  fn serialize_u128(self, _: u128) -> Result<String, Error> {
+    Ok("u128".into())
+  }
+
+  fn serialize_f32(self, _: f32) -> Result<String, Error> {
+    Ok("f32".into())
+  }
+
+  fn serialize_f64(self, _: f64) -> Result<String, Error> {
+    Ok("f64".into())
+  }
+
+  fn serialize_char(self, _: char) -> Result<String, Error> {
+    Ok("char".into())
+  }
+
+  fn serialize_str(self, _: &str) -> Result<String, Error> {
+    Ok("string".into())
+  }
+
+  fn serialize_bytes(self, _: &[u8]) -> Result<String, Error> {
+    Ok("bytes".into())
+  }
+
+  fn serialize_none(self) -> Result<String, Error> {
+    Ok("option<_>".into())
+  }
+
+  fn serialize_some<T: Serialize + ?Sized>(
+    self,
+    value: &T,
+  ) -> Result<String, Error> {
+    Ok(format!("option<{}>", describe(value)?))
+  }
+
+  fn serialize_unit(self) -> Result<String, Error> {
+    Ok("unit".into())
+  }
+
+  fn serialize_unit_struct(self, name: &'static str) -> Result<String, Error> {
+    Ok(name.into())
+  }
+
+  fn serialize_unit_variant(
+    self,
+    name: &'static str,
+    _: u32,
+    _: &'static str,
+  ) -> Result<String, Error> {
+    Ok(name.into())
+  }
+
+  fn serialize_newtype_struct<T: Serialize + ?Sized>(
+    self,
+    name: &'static str,
+    _: &T,
+  ) -> Result<String, Error> {
+    Ok(name.into())
+  }
+
+  fn serialize_newtype_variant<T: Serialize + ?Sized>(
+    self,
+    name: &'static str,
+    _: u32,
+    _: &'static str,
+    _: &T,
+  ) -> Result<String, Error> {
+    Ok(name.into())
+  }
+
+  fn serialize_seq(self, _: Option<usize>) -> Result<Elements, Error> {
+    Ok(Elements::seq())
+  }
+
+  fn serialize_tuple(self, _: usize) -> Result<Elements, Error> {
+    Ok(Elements::tuple())
+  }
+
+  fn serialize_tuple_struct(
+    self,
+    name: &'static str,
+    _: usize,
+  ) -> Result<Named, Error> {
+    Ok(Named(name))
+  }
+
+  fn serialize_tuple_variant(
+    self,
+    name: &'static str,
+    _: u32,
+    _: &'static str,
+    _: usize,
+  ) -> Result<Named, Error> {
+    Ok(Named(name))
+  }
+
+  fn serialize_map(self, _: Option<usize>) -> Result<Entries, Error> {
+    Ok(Entries::default())
+  }
+
+  fn serialize_struct(
+    self,
+    name: &'static str,
+    _: usize,
+  ) -> Result<Named, Error> {
+    Ok(Named(name))
+  }
+
+  fn serialize_struct_variant(
+    self,
+    name: &'static str,
+    _: u32,
+    _: &'static str,
+    _: usize,
+  ) -> Result<Named, Error> {
+    Ok(Named(name))
+  }
+}
+
+/// Describes sequences by their first element and tuples by all of them.
+struct Elements {
+  tuple: bool,
+  elements: Vec<String>,
+}
+
+impl Elements {
+  fn seq() -> Self {
+    Self {
+      tuple: false,
+      elements: vec![],
+    }
+  }
+
+  fn tuple() -> Self {
+    Self {
+      tuple: true,
+      elements: vec![],
+    }
+  }
+
+  fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+    if self.tuple || self.elements.is_empty() {
+      self.elements.push(describe(value)?);
+    }
+    Ok(())
+  }
+}
+
+impl SerializeSeq for Elements {
+  type Error = Error;
+  type Ok = String;
+
+  fn serialize_element<T: Serialize + ?Sized>(
+    &mut self,
+    value: &T,
+  ) -> Result<(), Error> {
+    self.push(value)
+  }
+
+  fn end(self) -> Result<String, Error> {
+    Ok(format!(
+      "seq<{}>",
+      self.elements.first().map(String::as_str).unwrap_or("_")
+    ))
+  }
+}
+
+impl SerializeTuple for Elements {
+  type Error = Error;
+  type Ok = String;
+
+  fn serialize_element<T: Serialize + ?Sized>(
+    &mut self,
+    value: &T,
+  ) -> Result<(), Error> {
+    self.push(value)
+  }
+
+  fn end(self) -> Result<String, Error> {
+    Ok(format!("tuple<{}>", self.elements.join(",")))
+  }
+}
+
+/// Describes maps by their first entry.
+#[derive(Default)]
+struct Entries {
+  key: Option<String>,
+  value: Option<String>,
+}
+
+impl SerializeMap for Entries {
+  type Error = Error;
+  type Ok = String;
+
+  fn serialize_key<T: Serialize + ?Sized>(
+    &mut self,
+    key: &T,
+  ) -> Result<(), Error> {
+    if self.key.is_none() {
+      self.key = Some(describe(key)?);
+    }
+    Ok(())
+  }
+
+  fn serialize_value<T: Serialize + ?Sized>(
+    &mut self,
+    value: &T,
+  ) -> Result<(), Error> {
+    if self.value.is_none() {
+      self.value = Some(describe(value)?);
+    }
+    Ok(())
+  }
+
+  fn end(self) -> Result<String, Error> {
+    Ok(format!(
+      "map<{},{}>",
+      self.key.as_deref().unwrap_or("_"),
+      self.value.as_deref().unwrap_or("_")
+    ))
+  }
+}
+
+/// Structs and enums are described only by their name.
+struct Named(&'static str);
+
+impl Named {
+  fn end(self) -> Result<String, Error> {
+    Ok(self.0.into())
+  }
+}
+
+impl SerializeTupleStruct for Named {
+  type Error = Error;
+  type Ok = String;
+
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    _: &T,
+  ) -> Result<(), Error> {
+    Ok(())
+  }
+
+  fn end(self) -> Result<String, Error> {
+    Named::end(self)
+  }
+}
+
+impl SerializeTupleVariant for Named {
+  type Error = Error;
+  type Ok = String;
+
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    _: &T,
+  ) -> Result<(), Error> {
+    Ok(())
+  }
+
+  fn end(self) -> Result<String, Error> {
+    Named::end(self)
+  }
+}
+
+impl SerializeStruct for Named {
+  type Error = Error;
+  type Ok = String;
+
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    _: &'static str,
+    _: &T,
+  ) -> Result<(), Error> {
+    Ok(())
+  }
+
+  fn end(self) -> Result<String, Error> {
+    Named::end(self)
+  }
+}
+
+impl SerializeStructVariant for Named {
+  type Error = Error;
+  type Ok = String;
+
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    _: &'static str,
+    _: &T,
+  ) -> Result<(), Error> {
+    Ok(())
+  }
+
+  fn end(self) -> Result<String, Error> {
+    Named::end(self)
+  }
+}
@@ -1,10 +1,24 @@
 use {
   anoma_primitives::{Account, Address, Block, Code, Predicate, PredicateTree},
-  anoma_vm::{execute_many, State, StateDiff},
+  anoma_vm::{
+    execute_many,
+    InMemoryStateStore,
+    SnapshotChunk,
+    SnapshotError,
+    SnapshotReader,
+    SnapshotWriter,
+    State,
+    StateDiff,
+    StateSnapshot,
+  },
   multihash::{Multihash, MultihashDigest},
-  std::{collections::VecDeque, num::NonZeroUsize},
+  serde::{Deserialize, Serialize},
+  std::{
+    collections::{HashMap, VecDeque},
+    num::NonZeroUsize,
+  },
   thiserror::Error,
-  tracing::info,
+  tracing::{info, warn},
   wasmer::{Cranelift, Module, Store},
 };
 
@@ -18,20 +32,78 @@ pub enum Error {
 
   #[error("Must be initialized with at least one existing block")]
   NoInitialBlocks,
+
+  #[error("Block {0:?} was already consumed")]
+  DuplicateBlock(Multihash),
+
+  #[error("Block at height {0} can't be rolled back")]
+  IrreversibleBlock(u64),
+
+  #[error("Invalid state snapshot: {0}")]
+  Snapshot(#[from] SnapshotError),
+}
+
+/// Asks peers for blocks that were missed from gossip.
+///
+/// Peers answer with the MessagePack encoded list of matching
+/// blocks from their recent history, see [`BlockStateBuilder::serve`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BlockRequest {
+  /// Blocks of the current fork at heights `from..=to`.
+  ByHeight { from: u64, to: u64 },
+
+  /// A block with the given hash.
+  ByHash(Multihash),
+}
+
+/// Blocks applied by [`BlockStateBuilder::consume`] or
+/// [`BlockStateBuilder::enqueue`].
+#[derive(Debug, Default)]
+pub struct Consumed {
+  /// Applied blocks, from the oldest to the most recent one.
+  pub applied: Vec<Block>,
+
+  /// Blocks rolled back before applying the first block,
+  /// starting with the most recent one.
+  pub orphaned: Vec<Block>,
+}
+
+/// A recently consumed block along with the diff that reverts its
+/// changes to the state. Blocks the builder was initialized with
+/// were never applied by it, so they have no inverse diff.
+struct RecentBlock {
+  block: Block,
+  undo: Option<StateDiff>,
 }
 
 /// This type can be used to accumulate state changes from blocks produced
 /// by validators. It is useful everywhere where a node (solver, client, etc)
 /// need to maintain an up-to-date state of the chain and be able to query
 /// current accounts state.
-pub struct BlockStateBuilder<'s> {
+///
+/// The builder owns the accounts state and the cache of precompiled
+/// predicates, both can be taken back with [`BlockStateBuilder::into_inner`].
+///
+/// Blocks are expected to extend the most recent block. Blocks that build
+/// on top of an older block from the recent history start a competing fork
+/// that is kept aside. Once a competing fork gets strictly higher than the
+/// current one, all blocks after the fork point are rolled back and the
+/// blocks of the higher fork are applied instead. Forks can only branch
+/// off the recent history, so `history_len` is also the deepest supported
+/// reorg.
+///
+/// Blocks that arrive before their parent can be kept aside with
+/// [`BlockStateBuilder::enqueue`] until the gap is backfilled.
+pub struct BlockStateBuilder<S = InMemoryStateStore, C = InMemoryStateStore> {
   history_len: usize,
-  state: &'s mut dyn State,
-  codecache: &'s mut dyn State,
-  recent: VecDeque<Block>,
+  state: S,
+  codecache: C,
+  recent: VecDeque<RecentBlock>,
+  forks: HashMap<Multihash, Block>,
+  pending: HashMap<Multihash, Block>,
 }
 
-impl<'s> State for BlockStateBuilder<'s> {
+impl<S: State, C: State> State for BlockStateBuilder<S, C> {
   fn get(&self, address: &Address) -> Option<Account> {
     self.state.get(address)
   }
@@ -44,15 +116,17 @@ impl<'s> State for BlockStateBuilder<'s> {
   }
 }
 
-impl<'s> BlockStateBuilder<'s> {
+impl<S: State, C: State> BlockStateBuilder<S, C> {
   #[allow(clippy::result_large_err)]
   pub fn new(
     history_len: NonZeroUsize,
-    state: &'s mut dyn State,
-    codecache: &'s mut dyn State,
+    state: S,
+    codecache: C,
     recent: impl Iterator<Item = Block>,
   ) -> Result<Self, Error> {
-    let recent: VecDeque<_> = recent.collect();
+    let recent: VecDeque<_> = recent
+      .map(|block| RecentBlock { block, undo: None })
+      .collect();
 
     if recent.is_empty() {
       return Err(Error::NoInitialBlocks);
@@ -63,48 +137,162 @@ impl<'s> BlockStateBuilder<'s> {
       state,
       codecache,
       recent,
+      forks: HashMap::new(),
+      pending: HashMap::new(),
     })
   }
 
+  /// Creates a builder on top of a state snapshot instead of replaying all
+  /// blocks since genesis.
+  ///
+  /// The given state is expected to be empty. Every chunk is verified
+  /// against the snapshot before it is applied, and the builder then
+  /// follows blocks built on top of the snapshot block.
+  #[allow(clippy::result_large_err)]
+  pub fn from_snapshot(
+    history_len: NonZeroUsize,
+    mut state: S,
+    mut codecache: C,
+    snapshot: StateSnapshot,
+    chunks: impl Iterator<Item = SnapshotChunk>,
+  ) -> Result<Self, Error> {
+    let mut reader = SnapshotReader::new(snapshot);
+    for chunk in chunks {
+      let diff = reader.read(&chunk)?;
+      codecache.apply(try_precompile_predicates(&diff));
+      state.apply(diff);
+    }
+    let block = reader.finish()?;
+    info!(
+      "Bootstrapped state from snapshot at height {}",
+      block.height
+    );
+    Self::new(history_len, state, codecache, std::iter::once(block))
+  }
+
   pub fn last(&self) -> &Block {
-    self
+    &self
       .recent
       .front()
       .expect("asserted that there must be at least one recent block")
+      .block
   }
 
+  /// Recent blocks of the current fork, starting with the most recent one.
   pub fn recent(&self) -> impl Iterator<Item = &Block> {
-    self.recent.iter()
+    self.recent.iter().map(|recent| &recent.block)
   }
 
+  /// Precompiled predicates bytecode of all WASM accounts observed so far.
+  ///
+  /// Useful when executing transactions against this state outside of
+  /// block consumption, such as when validating them in a mempool.
+  pub fn codecache(&self) -> &C {
+    &self.codecache
+  }
+
+  /// Gives back the accounts state and the code cache.
+  pub fn into_inner(self) -> (S, C) {
+    (self.state, self.codecache)
+  }
+
+  /// Applies the state changes of a block.
+  ///
+  /// A block that does not extend the most recent block is kept aside on
+  /// its fork, and nothing is applied until that fork becomes strictly
+  /// higher than the current one. Then all blocks after the fork point are
+  /// rolled back and the blocks of the new fork are applied.
   #[allow(clippy::result_large_err)]
-  pub fn consume(&mut self, block: Block) -> Result<(), Error> {
-    let prev_height = self.last().height;
-    let prev_hash = *self.last().hash();
+  pub fn consume(&mut self, block: Block) -> Result<Consumed, Error> {
+    if self.knows(block.hash()) {
+      return Err(Error::DuplicateBlock(*block.hash()));
+    }
 
-    if prev_hash != block.parent {
-      return Err(Error::InvalidBlockParent(block.parent, prev_hash));
+    let Some(parent_height) = self.height_of(&block.parent) else {
+      return Err(Error::InvalidBlockParent(block.parent, *self.last().hash()));
+    };
+
+    if parent_height + 1 != block.height {
+      return Err(Error::InvalidBlockHeight(block.height, parent_height + 1));
     }
 
-    if prev_height + 1 != block.height {
-      return Err(Error::InvalidBlockHeight(block.height, prev_height + 1));
+    if block.parent == *self.last().hash() {
+      self.execute(block.clone());
+      return Ok(Consumed {
+        applied: vec![block],
+        orphaned: vec![],
+      });
     }
 
-    self.recent.push_front(block.clone());
-    if self.recent.len() > self.history_len {
-      self.recent.pop_back();
+    // walk back the competing fork to the block it branches off
+    let mut branch = vec![block];
+    while let Some(parent) = self.forks.get(&branch[branch.len() - 1].parent) {
+      branch.push(parent.clone());
+    }
+    let fork_point = branch[branch.len() - 1].parent;
+    let Some(depth) = self
+      .recent
+      .iter()
+      .position(|recent| *recent.block.hash() == fork_point)
+    else {
+      return Err(Error::InvalidBlockParent(fork_point, *self.last().hash()));
+    };
+
+    if let Some(recent) = self.recent.range(..depth).find(|r| r.undo.is_none())
+    {
+      return Err(Error::IrreversibleBlock(recent.block.height));
+    }
+
+    if branch[0].height <= self.last().height {
+      let block = branch.swap_remove(0);
+      self.forks.insert(*block.hash(), block);
+      self.prune_forks();
+      return Ok(Consumed::default());
+    }
+
+    // the competing fork is higher, switch to it.
+    let mut orphaned = Vec::with_capacity(depth);
+    for _ in 0..depth {
+      let recent = self.recent.pop_front().expect("depth < recent.len()");
+      self.state.apply(recent.undo.expect("verified above"));
+      self
+        .forks
+        .insert(*recent.block.hash(), recent.block.clone());
+      orphaned.push(recent.block);
     }
 
+    info!(
+      "Rolled back {} blocks, forking at height {}",
+      orphaned.len(),
+      branch[branch.len() - 1].height - 1
+    );
+
+    branch.reverse();
+    for block in branch.iter() {
+      self.forks.remove(block.hash());
+      self.execute(block.clone());
+    }
+    self.prune_forks();
+
+    Ok(Consumed {
+      applied: branch,
+      orphaned,
+    })
+  }
+
+  /// Executes a block on top of the most recent block.
+  fn execute(&mut self, block: Block) {
     let txhashes: Vec<_> =
       block.transactions.iter().map(|tx| *tx.hash()).collect();
 
     let results = execute_many(
-      self.state, //
-      self.codecache,
-      block.transactions.into_iter(),
+      block.context(),
+      &self.state,
+      &self.codecache,
+      block.transactions.iter().cloned(),
     );
 
-    for (result, tx) in results.iter().zip(txhashes.into_iter()) {
+    for (result, tx) in results.iter().zip(txhashes) {
       info!(
         "Transaction {} result: {result:?}",
         bs58::encode(tx.to_bytes()).into_string()
@@ -117,12 +305,198 @@ impl<'s> BlockStateBuilder<'s> {
       .reduce(|acc, e| acc.merge(e))
       .unwrap_or_default();
 
+    let undo = inverse(&self.state, &statediff);
     self.codecache.apply(try_precompile_predicates(&statediff));
     self.state.apply(statediff);
-    Ok(())
+
+    self.recent.push_front(RecentBlock {
+      block,
+      undo: Some(undo),
+    });
+    if self.recent.len() > self.history_len {
+      self.recent.pop_back();
+    }
+  }
+
+  /// Checks if a block is in the recent history or on a competing fork.
+  fn knows(&self, hash: &Multihash) -> bool {
+    self.height_of(hash).is_some()
+  }
+
+  fn height_of(&self, hash: &Multihash) -> Option<u64> {
+    self
+      .recent()
+      .chain(self.forks.values())
+      .find(|block| block.hash() == hash)
+      .map(|block| block.height)
+  }
+
+  /// Forgets blocks of competing forks that branch off blocks that are
+  /// no longer in the recent history. At most `history_len` of them are
+  /// kept, the lowest ones are the furthest from overtaking the current
+  /// fork and are dropped first.
+  fn prune_forks(&mut self) {
+    let oldest = self.recent.back().expect("at least one block").block.height;
+    self.forks.retain(|_, block| block.height > oldest);
+    while self.forks.len() > self.history_len {
+      let lowest = *self
+        .forks
+        .values()
+        .min_by_key(|block| block.height)
+        .expect("not empty")
+        .hash();
+      self.forks.remove(&lowest);
+    }
+  }
+
+  /// Applies a block that may have arrived out of order.
+  ///
+  /// Blocks whose parent is not known yet are kept aside until it is
+  /// consumed, [`BlockStateBuilder::missing`] tells which blocks to fetch
+  /// from peers to fill the gap. Once a block is applied, all blocks kept
+  /// aside that extend it are applied as well. At most `history_len`
+  /// blocks are kept aside, the highest ones are dropped first.
+  #[allow(clippy::result_large_err)]
+  pub fn enqueue(&mut self, block: Block) -> Result<Consumed, Error> {
+    if !self.knows(&block.parent) {
+      // blocks at or below the oldest recent block can
+      // never be attached to any block we know about.
+      let oldest = self.recent.back().expect("at least one block");
+      if block.height <= oldest.block.height {
+        return Err(Error::InvalidBlockParent(
+          block.parent,
+          *self.last().hash(),
+        ));
+      }
+
+      self.pending.insert(*block.hash(), block);
+      if self.pending.len() > self.history_len {
+        let highest = *self
+          .pending
+          .values()
+          .max_by_key(|block| block.height)
+          .expect("not empty")
+          .hash();
+        self.pending.remove(&highest);
+      }
+      return Ok(Consumed::default());
+    }
+
+    let mut consumed = self.consume(block)?;
+
+    while let Some(next) = self
+      .pending
+      .values()
+      .find(|pending| self.knows(&pending.parent))
+      .map(|pending| *pending.hash())
+    {
+      let next = self.pending.remove(&next).expect("found above");
+      match self.consume(next) {
+        Ok(next) => {
+          // blocks applied earlier in this call that got rolled back
+          // were never reported as applied.
+          for block in next.orphaned {
+            let hash = block.hash();
+            match consumed.applied.iter().position(|b| b.hash() == hash) {
+              Some(at) => {
+                consumed.applied.remove(at);
+              }
+              None => consumed.orphaned.push(block),
+            }
+          }
+          consumed.applied.extend(next.applied);
+        }
+        Err(e) => warn!("dropping block kept aside: {e:?}"),
+      }
+    }
+
+    // blocks kept aside that can no longer be attached
+    let oldest = self.recent.back().expect("at least one block").block.height;
+    self.pending.retain(|_, pending| pending.height > oldest);
+
+    Ok(consumed)
+  }
+
+  /// Requests for blocks that are needed to apply the blocks kept
+  /// aside by [`BlockStateBuilder::enqueue`].
+  ///
+  /// Gaps ahead of the most recent block are requested by height,
+  /// otherwise the missing parent is requested by its hash.
+  pub fn missing(&self) -> Vec<BlockRequest> {
+    let last = self.last().height;
+    let mut requests = vec![];
+    for block in self.pending.values() {
+      let attached =
+        self.pending.contains_key(&block.parent) || self.knows(&block.parent);
+      if attached {
+        continue;
+      }
+
+      let request = if block.height > last + 1 {
+        BlockRequest::ByHeight {
+          from: last + 1,
+          to: (block.height - 1).min(last + self.history_len as u64),
+        }
+      } else {
+        BlockRequest::ByHash(block.parent)
+      };
+
+      if !requests.contains(&request) {
+        requests.push(request);
+      }
+    }
+    requests
+  }
+
+  /// Blocks from the recent history that match a request of a peer,
+  /// from the oldest to the most recent one.
+  pub fn serve(&self, request: &BlockRequest) -> Vec<Block> {
+    let mut blocks: Vec<_> = self
+      .recent()
+      .filter(|block| match request {
+        BlockRequest::ByHeight { from, to } => {
+          (*from..=*to).contains(&block.height)
+        }
+        BlockRequest::ByHash(hash) => block.hash() == hash,
+      })
+      .cloned()
+      .collect();
+    blocks.reverse();
+    blocks
+  }
+}
+
+impl<C: State> BlockStateBuilder<InMemoryStateStore, C> {
+  /// Exports the current state as of the most recent block, with at most
+  /// `chunk_size` accounts in every chunk.
+  pub fn snapshot(
+    &self,
+    chunk_size: NonZeroUsize,
+  ) -> SnapshotWriter<impl Iterator<Item = (Address, Account)> + '_> {
+    SnapshotWriter::new(
+      self.last().clone(),
+      self
+        .state
+        .iter()
+        .map(|(address, account)| (address.clone(), account.clone())),
+      chunk_size,
+    )
   }
 }
 
+/// Builds a diff that restores the current state of
+/// all accounts modified by the given diff.
+fn inverse(state: &dyn State, diff: &StateDiff) -> StateDiff {
+  let mut undo = StateDiff::default();
+  for (address, _) in diff.iter() {
+    match state.get(address) {
+      Some(account) => undo.set(address.clone(), account),
+      None => undo.remove(address),
+    };
+  }
+  undo
+}
+
 fn try_precompile_predicates(diff: &StateDiff) -> StateDiff {
   const WASM_SIG: &[u8] = b"\0asm";
   let mut output = StateDiff::default();
@@ -156,3 +530,294 @@ fn try_precompile_predicates(diff: &StateDiff) -> StateDiff {
   }
   output
 }
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::{BlockRequest, BlockStateBuilder, Error},
+    anoma_primitives::{
+      Account,
+      AccountChange,
+      Address,
+      Block,
+      Code,
+      Predicate,
+      PredicateTree,
+      Transaction,
+    },
+    anoma_vm::{InMemoryStateStore, State},
+    multihash::Multihash,
+    std::num::NonZeroUsize,
+  };
+
+  fn create(address: &str) -> Transaction {
+    Transaction::new(
+      vec![],
+      [(
+        address.parse().unwrap(),
+        AccountChange::CreateAccount(Account {
+          state: address.as_bytes().to_vec(),
+          predicates: PredicateTree::Id(Predicate {
+            code: Code::Inline(vec![]),
+            params: vec![],
+          }),
+        }),
+      )]
+      .into(),
+    )
+  }
+
+  fn exists(builder: &BlockStateBuilder, address: &str) -> bool {
+    builder.get(&address.parse::<Address>().unwrap()).is_some()
+  }
+
+  fn hashes(blocks: &[Block]) -> Vec<Multihash> {
+    blocks.iter().map(|block| *block.hash()).collect()
+  }
+
+  #[test]
+  fn fork_rolls_back_state() {
+    let zero = Block::zero();
+    let mut builder = BlockStateBuilder::new(
+      NonZeroUsize::new(16).unwrap(),
+      InMemoryStateStore::default(),
+      InMemoryStateStore::default(),
+      std::iter::once(zero.clone()),
+    )
+    .unwrap();
+
+    let b1 = Block::new(&zero, 1, vec![create("/a")]);
+    let b2 = Block::new(&b1, 2, vec![create("/b")]);
+    assert!(builder.consume(b1.clone()).unwrap().orphaned.is_empty());
+    assert!(builder.consume(b2.clone()).unwrap().orphaned.is_empty());
+    assert!(exists(&builder, "/a") && exists(&builder, "/b"));
+    assert!(matches!(
+      builder.consume(b2.clone()),
+      Err(Error::DuplicateBlock(_))
+    ));
+
+    // a competing fork at b1 is kept aside until it gets higher
+    let c2 = Block::new(&b1, 3, vec![create("/c")]);
+    assert!(builder.consume(c2.clone()).unwrap().applied.is_empty());
+    assert_eq!(builder.last().hash(), b2.hash());
+    assert!(exists(&builder, "/b") && !exists(&builder, "/c"));
+
+    let c3 = Block::new(&c2, 4, vec![create("/d")]);
+    let consumed = builder.consume(c3.clone()).unwrap();
+    assert_eq!(hashes(&consumed.orphaned), vec![*b2.hash()]);
+    assert_eq!(hashes(&consumed.applied), vec![*c2.hash(), *c3.hash()]);
+    assert!(exists(&builder, "/a") && exists(&builder, "/c"));
+    assert!(!exists(&builder, "/b"));
+
+    // deeper fork at genesis
+    let d1 = Block::new(&zero, 5, vec![]);
+    let d2 = Block::new(&d1, 6, vec![]);
+    let d3 = Block::new(&d2, 7, vec![]);
+    let d4 = Block::new(&d3, 8, vec![]);
+    for block in [&d1, &d2, &d3] {
+      assert!(builder.consume(block.clone()).unwrap().applied.is_empty());
+      assert_eq!(builder.last().hash(), c3.hash());
+    }
+    let consumed = builder.consume(d4.clone()).unwrap();
+    assert_eq!(hashes(&consumed.orphaned), vec![
+      *c3.hash(),
+      *c2.hash(),
+      *b1.hash()
+    ]);
+    assert_eq!(hashes(&consumed.applied), vec![
+      *d1.hash(),
+      *d2.hash(),
+      *d3.hash(),
+      *d4.hash()
+    ]);
+    assert!(!exists(&builder, "/a") && !exists(&builder, "/c"));
+    assert_eq!(builder.last().hash(), d4.hash());
+    assert_eq!(builder.recent().count(), 5);
+  }
+
+  #[test]
+  fn stale_blocks_dont_roll_back() {
+    let zero = Block::zero();
+    let mut builder = BlockStateBuilder::new(
+      NonZeroUsize::new(16).unwrap(),
+      InMemoryStateStore::default(),
+      InMemoryStateStore::default(),
+      std::iter::once(zero.clone()),
+    )
+    .unwrap();
+
+    let b1 = Block::new(&zero, 1, vec![create("/a")]);
+    let b2 = Block::new(&b1, 2, vec![create("/b")]);
+    let c2 = Block::new(&b1, 3, vec![create("/c")]);
+    let c3 = Block::new(&c2, 4, vec![]);
+    for block in [&b1, &b2, &c2, &c3] {
+      builder.consume(block.clone()).unwrap();
+    }
+    assert_eq!(builder.last().hash(), c3.hash());
+
+    // b2 arrives again after the switch to the higher fork
+    assert!(matches!(
+      builder.consume(b2.clone()),
+      Err(Error::DuplicateBlock(_))
+    ));
+
+    // a late block at the same height as the current one
+    let e3 = Block::new(&c2, 5, vec![create("/e")]);
+    assert!(builder.consume(e3.clone()).unwrap().applied.is_empty());
+    assert_eq!(builder.last().hash(), c3.hash());
+    assert!(exists(&builder, "/c") && !exists(&builder, "/b"));
+    assert!(!exists(&builder, "/e"));
+
+    // the orphaned fork is applied again once it gets higher
+    let b3 = Block::new(&b2, 6, vec![]);
+    let b4 = Block::new(&b3, 7, vec![]);
+    assert!(builder.consume(b3.clone()).unwrap().applied.is_empty());
+    let consumed = builder.consume(b4.clone()).unwrap();
+    assert_eq!(hashes(&consumed.orphaned), vec![*c3.hash(), *c2.hash()]);
+    assert_eq!(hashes(&consumed.applied), vec![
+      *b2.hash(),
+      *b3.hash(),
+      *b4.hash()
+    ]);
+    assert!(exists(&builder, "/b") && !exists(&builder, "/c"));
+    assert_eq!(builder.last().hash(), b4.hash());
+  }
+
+  #[test]
+  fn initial_blocks_are_irreversible() {
+    let zero = Block::zero();
+    let b1 = Block::new(&zero, 1, vec![]);
+    let mut builder = BlockStateBuilder::new(
+      NonZeroUsize::new(16).unwrap(),
+      InMemoryStateStore::default(),
+      InMemoryStateStore::default(),
+      [b1.clone(), zero.clone()].into_iter(),
+    )
+    .unwrap();
+
+    assert!(matches!(
+      builder.consume(Block::new(&zero, 2, vec![])),
+      Err(Error::IrreversibleBlock(1))
+    ));
+    assert!(builder.consume(Block::new(&b1, 2, vec![])).is_ok());
+  }
+
+  #[test]
+  fn out_of_order_blocks_are_backfilled() {
+    let zero = Block::zero();
+    let mut builder = BlockStateBuilder::new(
+      NonZeroUsize::new(16).unwrap(),
+      InMemoryStateStore::default(),
+      InMemoryStateStore::default(),
+      std::iter::once(zero.clone()),
+    )
+    .unwrap();
+
+    let b1 = Block::new(&zero, 1, vec![create("/a")]);
+    let b2 = Block::new(&b1, 2, vec![create("/b")]);
+    let b3 = Block::new(&b2, 3, vec![create("/c")]);
+    let b4 = Block::new(&b3, 4, vec![]);
+
+    // blocks with unknown parents are kept aside
+    assert!(builder.enqueue(b4.clone()).unwrap().applied.is_empty());
+    assert!(builder.enqueue(b3.clone()).unwrap().applied.is_empty());
+    assert_eq!(builder.missing(), vec![BlockRequest::ByHeight {
+      from: 1,
+      to: 2
+    }]);
+    assert_eq!(builder.last().hash(), zero.hash());
+
+    // the gap is filled, all blocks are applied in order
+    assert!(builder.enqueue(b2.clone()).unwrap().applied.is_empty());
+    assert_eq!(builder.missing(), vec![BlockRequest::ByHeight {
+      from: 1,
+      to: 1
+    }]);
+    let consumed = builder.enqueue(b1.clone()).unwrap();
+    let applied: Vec<_> = consumed.applied.iter().map(|b| *b.hash()).collect();
+    assert_eq!(applied, vec![
+      *b1.hash(),
+      *b2.hash(),
+      *b3.hash(),
+      *b4.hash()
+    ]);
+    assert!(consumed.orphaned.is_empty());
+    assert!(builder.missing().is_empty());
+    assert_eq!(builder.last().hash(), b4.hash());
+    assert!(exists(&builder, "/a") && exists(&builder, "/c"));
+
+    // parents on other forks are requested by hash
+    let c4 = Block::new(&b3, 5, vec![]);
+    let c5 = Block::new(&c4, 6, vec![]);
+    assert!(builder.enqueue(c5.clone()).unwrap().applied.is_empty());
+    assert_eq!(builder.missing(), vec![BlockRequest::ByHash(*c4.hash())]);
+    let consumed = builder.enqueue(c4.clone()).unwrap();
+    assert_eq!(consumed.applied.len(), 2);
+    assert_eq!(consumed.orphaned[0].hash(), b4.hash());
+    assert_eq!(builder.last().hash(), c5.hash());
+
+    // blocks that can never be attached are rejected
+    let mut unattached = Block::zero();
+    unattached.parent = *Block::new(&zero, 7, vec![]).hash();
+    assert!(matches!(
+      builder.enqueue(unattached),
+      Err(Error::InvalidBlockParent(..))
+    ));
+
+    // peers are served from the recent history
+    let served = builder.serve(&BlockRequest::ByHeight { from: 2, to: 3 });
+    let served: Vec<_> = served.iter().map(|b| *b.hash()).collect();
+    assert_eq!(served, vec![*b2.hash(), *b3.hash()]);
+    let served = builder.serve(&BlockRequest::ByHash(*b1.hash()));
+    assert_eq!(served.len(), 1);
+    assert_eq!(served[0].hash(), b1.hash());
+  }
+
+  #[test]
+  fn bootstrap_from_snapshot() {
+    let zero = Block::zero();
+    let mut source = BlockStateBuilder::new(
+      NonZeroUsize::new(16).unwrap(),
+      InMemoryStateStore::default(),
+      InMemoryStateStore::default(),
+      std::iter::once(zero.clone()),
+    )
+    .unwrap();
+
+    let b1 = Block::new(&zero, 1, vec![create("/a"), create("/b")]);
+    source.consume(b1.clone()).unwrap();
+
+    let mut writer = source.snapshot(NonZeroUsize::new(1).unwrap());
+    let chunks: Vec<_> = writer.by_ref().collect();
+    let snapshot = writer.finish();
+    assert_eq!(chunks.len(), 2);
+
+    // all chunks must be present
+    assert!(matches!(
+      BlockStateBuilder::from_snapshot(
+        NonZeroUsize::new(16).unwrap(),
+        InMemoryStateStore::default(),
+        InMemoryStateStore::default(),
+        snapshot.clone(),
+        chunks.iter().take(1).cloned(),
+      ),
+      Err(Error::Snapshot(_))
+    ));
+
+    let mut builder = BlockStateBuilder::from_snapshot(
+      NonZeroUsize::new(16).unwrap(),
+      InMemoryStateStore::default(),
+      InMemoryStateStore::default(),
+      snapshot,
+      chunks.into_iter(),
+    )
+    .unwrap();
+    assert_eq!(builder.last().hash(), b1.hash());
+    assert!(exists(&builder, "/a") && exists(&builder, "/b"));
+
+    // and follows blocks on top of the snapshot
+    let b2 = Block::new(&b1, 2, vec![create("/c")]);
+    builder.consume(b2).unwrap();
+    assert!(exists(&builder, "/c"));
+  }
+}
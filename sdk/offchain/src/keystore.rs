@@ -0,0 +1,381 @@
+//! Password protected storage of wallet seeds on disk.
+//!
+//! Every wallet is stored in its own `<name>.json` file in the keystore
+//! directory. The seed is encrypted with ChaCha20-Poly1305 using a key
+//! derived from the password with Argon2id, with parameters and a salt
+//! stored next to the ciphertext, so entries can be decrypted even if
+//! the defaults change.
+
+use {
+  crate::wallet::{self, Wallet},
+  argon2::{Algorithm, Argon2, Params, Version},
+  chacha20poly1305::{aead::Aead, ChaCha20Poly1305, Key, KeyInit, Nonce},
+  rand::RngCore,
+  serde::{Deserialize, Serialize},
+  std::{
+    fs,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+  },
+  thiserror::Error,
+  zeroize::Zeroize,
+};
+
+/// Version of the format of keystore entries.
+const VERSION: u32 = 1;
+
+/// Upper bounds of the key derivation parameters accepted from entries,
+/// so a tampered file can't make loading exhaust memory or cpu.
+const MAX_KDF: KdfParams = KdfParams {
+  memory: 4 * 1024 * 1024,
+  iterations: 64,
+  parallelism: 64,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+  #[error("Invalid wallet name {0}")]
+  InvalidName(String),
+
+  #[error("Wallet {0} does not exist")]
+  NotFound(String),
+
+  #[error("Wallet {0} already exists")]
+  AlreadyExists(String),
+
+  #[error("Invalid password for wallet {0}")]
+  InvalidPassword(String),
+
+  #[error("Unsupported keystore entry version {0}")]
+  UnsupportedVersion(u32),
+
+  #[error("Malformed keystore entry: {0}")]
+  MalformedEntry(#[from] serde_json::Error),
+
+  #[error("Malformed keystore entry field {0}")]
+  MalformedField(&'static str),
+
+  #[error("Key derivation parameters {0:?} exceed the supported limits")]
+  ExcessiveKdf(KdfParams),
+
+  #[error("Key derivation error: {0}")]
+  KeyDerivation(argon2::Error),
+
+  #[error("Keystore IO error: {0}")]
+  Io(#[from] std::io::Error),
+
+  #[error(transparent)]
+  Wallet(#[from] wallet::Error),
+}
+
+/// Argon2id parameters used to derive the encryption key of an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+  /// Memory size in KiB.
+  pub memory: u32,
+  pub iterations: u32,
+  pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+  fn default() -> Self {
+    Self {
+      memory: Params::DEFAULT_M_COST,
+      iterations: Params::DEFAULT_T_COST,
+      parallelism: Params::DEFAULT_P_COST,
+    }
+  }
+}
+
+/// Contents of a keystore file, binary values are base58 encoded.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+  version: u32,
+  kdf: KdfParams,
+  salt: String,
+  nonce: String,
+  ciphertext: String,
+}
+
+/// A directory of encrypted wallets.
+pub struct Keystore {
+  directory: PathBuf,
+  kdf: KdfParams,
+}
+
+impl Keystore {
+  /// Opens a keystore, creating its directory if it does not exist.
+  pub fn open(directory: impl AsRef<Path>) -> Result<Self, Error> {
+    fs::create_dir_all(&directory)?;
+    Ok(Self {
+      directory: directory.as_ref().to_path_buf(),
+      kdf: KdfParams::default(),
+    })
+  }
+
+  /// Sets the key derivation parameters of wallets stored from now on.
+  pub fn with_kdf(mut self, kdf: KdfParams) -> Self {
+    self.kdf = kdf;
+    self
+  }
+
+  /// Names of all wallets in the keystore, sorted.
+  pub fn list(&self) -> Result<Vec<String>, Error> {
+    let mut names = vec![];
+    for entry in fs::read_dir(&self.directory)? {
+      let path = entry?.path();
+      if path.extension().is_some_and(|ext| ext == "json") {
+        if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+          if valid_name(name) {
+            names.push(name.to_owned());
+          }
+        }
+      }
+    }
+    names.sort();
+    Ok(names)
+  }
+
+  pub fn contains(&self, name: &str) -> Result<bool, Error> {
+    Ok(self.path(name)?.exists())
+  }
+
+  /// Encrypts a wallet and stores it under a new name.
+  pub fn store(
+    &self,
+    name: &str,
+    wallet: &Wallet,
+    password: &str,
+  ) -> Result<(), Error> {
+    let path = self.path(name)?;
+    if path.exists() {
+      return Err(Error::AlreadyExists(name.into()));
+    }
+
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut key = derive_key(&self.kdf, password, &salt)?;
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+      .encrypt(Nonce::from_slice(&nonce), wallet.seed())
+      .expect("encryption of in-memory data");
+    key.zeroize();
+
+    let entry = serde_json::to_vec_pretty(&Entry {
+      version: VERSION,
+      kdf: self.kdf,
+      salt: bs58::encode(salt).into_string(),
+      nonce: bs58::encode(nonce).into_string(),
+      ciphertext: bs58::encode(ciphertext).into_string(),
+    })?;
+
+    // written aside and linked into place, so an interrupted store never
+    // leaves a truncated entry behind, and unlike a rename the link fails
+    // instead of replacing an entry stored concurrently under the same
+    // name. Names never start with a dot, so temporary files can't clash
+    // with another wallet.
+    let mut suffix = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut suffix);
+    let temp = self.directory.join(format!(
+      ".{name}.{}.tmp",
+      bs58::encode(suffix).into_string()
+    ));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let written = options.open(&temp).and_then(|mut file| {
+      file.write_all(&entry)?;
+      file.sync_all()?;
+      fs::hard_link(&temp, &path)
+    });
+    let _ = fs::remove_file(&temp);
+    written.map_err(|e| match e.kind() {
+      ErrorKind::AlreadyExists => Error::AlreadyExists(name.into()),
+      _ => Error::Io(e),
+    })
+  }
+
+  /// Decrypts a stored wallet.
+  pub fn load(&self, name: &str, password: &str) -> Result<Wallet, Error> {
+    let entry = fs::read(self.path(name)?).map_err(|e| match e.kind() {
+      ErrorKind::NotFound => Error::NotFound(name.into()),
+      _ => Error::Io(e),
+    })?;
+    let entry: Entry = serde_json::from_slice(&entry)?;
+    if entry.version != VERSION {
+      return Err(Error::UnsupportedVersion(entry.version));
+    }
+    if entry.kdf.memory > MAX_KDF.memory
+      || entry.kdf.iterations > MAX_KDF.iterations
+      || entry.kdf.parallelism > MAX_KDF.parallelism
+    {
+      return Err(Error::ExcessiveKdf(entry.kdf));
+    }
+
+    let salt = decode("salt", &entry.salt)?;
+    let nonce = decode("nonce", &entry.nonce)?;
+    let ciphertext = decode("ciphertext", &entry.ciphertext)?;
+    if nonce.len() != 12 {
+      return Err(Error::MalformedField("nonce"));
+    }
+
+    let mut key = derive_key(&entry.kdf, password, &salt)?;
+    let seed = ChaCha20Poly1305::new(Key::from_slice(&key))
+      .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice());
+    key.zeroize();
+
+    let mut seed = seed.map_err(|_| Error::InvalidPassword(name.into()))?;
+    let wallet = Wallet::from_seed(&seed);
+    seed.zeroize();
+    Ok(wallet?)
+  }
+
+  /// Deletes a stored wallet.
+  pub fn remove(&self, name: &str) -> Result<(), Error> {
+    fs::remove_file(self.path(name)?).map_err(|e| match e.kind() {
+      ErrorKind::NotFound => Error::NotFound(name.into()),
+      _ => Error::Io(e),
+    })
+  }
+
+  fn path(&self, name: &str) -> Result<PathBuf, Error> {
+    match valid_name(name) {
+      true => Ok(self.directory.join(format!("{name}.json"))),
+      false => Err(Error::InvalidName(name.into())),
+    }
+  }
+}
+
+/// Names are used as file names, so they are limited to characters
+/// that can't escape the keystore directory.
+fn valid_name(name: &str) -> bool {
+  !name.is_empty()
+    && !name.starts_with('.')
+    && name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || ['.', '-', '_'].contains(&c))
+}
+
+fn decode(field: &'static str, value: &str) -> Result<Vec<u8>, Error> {
+  bs58::decode(value)
+    .into_vec()
+    .map_err(|_| Error::MalformedField(field))
+}
+
+fn derive_key(
+  kdf: &KdfParams,
+  password: &str,
+  salt: &[u8],
+) -> Result<[u8; 32], Error> {
+  let params = Params::new(kdf.memory, kdf.iterations, kdf.parallelism, None)
+    .map_err(Error::KeyDerivation)?;
+  let mut key = [0u8; 32];
+  Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+    .hash_password_into(password.as_bytes(), salt, &mut key)
+    .map_err(Error::KeyDerivation)?;
+  Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::{Error, KdfParams, Keystore},
+    crate::Wallet,
+  };
+
+  fn keystore(directory: &std::path::Path) -> Keystore {
+    // cheap key derivation to keep tests fast
+    Keystore::open(directory).unwrap().with_kdf(KdfParams {
+      memory: 64,
+      iterations: 1,
+      parallelism: 1,
+    })
+  }
+
+  #[test]
+  fn store_and_load_wallets() {
+    let directory = tempfile::tempdir().unwrap();
+    let keystore = keystore(&directory.path().join("keys"));
+    assert!(keystore.list().unwrap().is_empty());
+
+    let wallet = Wallet::generate();
+    keystore.store("alice", &wallet, "secret").unwrap();
+    keystore
+      .store("bob.eth", &Wallet::generate(), "other")
+      .unwrap();
+    assert_eq!(keystore.list().unwrap(), vec!["alice", "bob.eth"]);
+    assert!(keystore.contains("alice").unwrap());
+
+    // the seed is not stored in plain text
+    let contents =
+      std::fs::read(directory.path().join("keys/alice.json")).unwrap();
+    assert!(!contents
+      .windows(wallet.seed().len())
+      .any(|w| w == wallet.seed()));
+
+    let loaded = keystore.load("alice", "secret").unwrap();
+    assert_eq!(loaded.seed(), wallet.seed());
+    assert_eq!(
+      loaded.keypair(3).unwrap().public,
+      wallet.keypair(3).unwrap().public
+    );
+
+    // entries are readable regardless of current kdf settings
+    let reopened = Keystore::open(directory.path().join("keys")).unwrap();
+    assert_eq!(
+      reopened.load("alice", "secret").unwrap().seed(),
+      wallet.seed()
+    );
+
+    assert!(matches!(
+      keystore.load("alice", "wrong"),
+      Err(Error::InvalidPassword(_))
+    ));
+    assert!(matches!(
+      keystore.store("alice", &Wallet::generate(), "secret"),
+      Err(Error::AlreadyExists(_))
+    ));
+    assert!(matches!(
+      keystore.load("carol", "secret"),
+      Err(Error::NotFound(_))
+    ));
+    for name in ["", "../alice", ".hidden", "a/b"] {
+      assert!(matches!(
+        keystore.load(name, "secret"),
+        Err(Error::InvalidName(_))
+      ));
+    }
+
+    keystore.remove("alice").unwrap();
+    assert_eq!(keystore.list().unwrap(), vec!["bob.eth"]);
+    assert!(matches!(keystore.remove("alice"), Err(Error::NotFound(_))));
+
+    // no temporary files are left behind
+    let files = std::fs::read_dir(directory.path().join("keys")).unwrap();
+    assert_eq!(files.count(), 1);
+  }
+
+  #[test]
+  fn reject_excessive_kdf_params() {
+    let directory = tempfile::tempdir().unwrap();
+    let keystore = keystore(directory.path());
+    keystore
+      .store("alice", &Wallet::generate(), "secret")
+      .unwrap();
+
+    let path = directory.path().join("alice.json");
+    let mut entry: serde_json::Value =
+      serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    entry["kdf"]["memory"] = u32::MAX.into();
+    std::fs::write(&path, serde_json::to_vec(&entry).unwrap()).unwrap();
+
+    assert!(matches!(
+      keystore.load("alice", "secret"),
+      Err(Error::ExcessiveKdf(_))
+    ));
+  }
+}
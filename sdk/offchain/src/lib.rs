@@ -1,10 +1,52 @@
+pub mod abi;
 mod builder;
+mod keystore;
+mod pool;
 mod query;
+pub mod token;
+mod transaction;
+mod wallet;
 mod watcher;
 
 pub use {
-  anoma_vm::{InMemoryStateStore, State, StateDiff},
-  builder::{BlockStateBuilder, Error as BlockStateBuilderError},
-  query::{ExpressionPattern, ParamPattern, Query},
+  abi::{Abi, Arg},
+  anoma_vm::{
+    InMemoryStateStore,
+    SnapshotChunk,
+    State,
+    StateDiff,
+    StateSnapshot,
+  },
+  builder::{
+    BlockRequest,
+    BlockStateBuilder,
+    Consumed,
+    Error as BlockStateBuilderError,
+  },
+  keystore::{Error as KeystoreError, KdfParams, Keystore},
+  pool::IntentPool,
+  query::{
+    ExpressionPattern,
+    MatchValue,
+    ParamPattern,
+    Pattern,
+    Query,
+    ValueConstraint,
+  },
+  transaction::{
+    change_params,
+    Error as TransactionBuilderError,
+    IntentBuilder,
+    TransactionBuilder,
+  },
+  wallet::{
+    require_signature,
+    sign_intent,
+    token_wallet_address,
+    wallet_id,
+    DerivationPath,
+    Error as WalletError,
+    Wallet,
+  },
   watcher::BlockchainWatcher,
 };
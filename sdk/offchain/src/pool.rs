@@ -0,0 +1,499 @@
+use {
+  crate::{
+    query::{MatchValue, Pattern},
+    BlockchainWatcher,
+    ExpressionPattern,
+  },
+  anoma_primitives::{Address, Block, Code, Intent, Param},
+  anoma_vm::State,
+  multihash::Multihash,
+  std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+  },
+};
+
+/// Hashes of intents that share some property.
+type Index<K> = HashMap<K, HashSet<Multihash>>;
+
+fn index<K: Eq + Hash>(index: &mut Index<K>, key: K, hash: Multihash) {
+  index.entry(key).or_default().insert(hash);
+}
+
+fn unindex<K: Eq + Hash>(index: &mut Index<K>, key: &K, hash: &Multihash) {
+  if let Some(hashes) = index.get_mut(key) {
+    hashes.remove(hash);
+    if hashes.is_empty() {
+      index.remove(key);
+    }
+  }
+}
+
+/// Accounts and predicates referenced by the expectations of an intent.
+#[derive(Default)]
+struct References {
+  accounts: HashSet<Address>,
+  entrypoints: HashSet<(Address, String)>,
+}
+
+impl References {
+  fn of(intent: &Intent) -> Self {
+    let mut refs = Self::default();
+    intent.expectations.for_each(&mut |predicate| {
+      if let Code::AccountRef(address, entrypoint) = &predicate.code {
+        refs
+          .entrypoints
+          .insert((address.clone(), entrypoint.clone()));
+      }
+      for param in &predicate.params {
+        if let Param::AccountRef(address) | Param::ProposalRef(address) = param
+        {
+          refs.accounts.insert(address.clone());
+        }
+      }
+    });
+    refs
+  }
+}
+
+/// An entrypoint that every intent matching the pattern must use.
+fn required_entrypoint(pattern: &Pattern) -> Option<(&Address, &str)> {
+  match pattern {
+    Pattern::Id(predicate) => match &predicate.code {
+      Code::AccountRef(address, entrypoint) => Some((address, entrypoint)),
+      Code::Inline(_) => None,
+    },
+    Pattern::Not(inner) | Pattern::Contains(inner) => {
+      required_entrypoint(inner)
+    }
+    Pattern::And(left, right) | Pattern::Or(left, right) => {
+      required_entrypoint(left).or_else(|| required_entrypoint(right))
+    }
+    Pattern::Any => None,
+  }
+}
+
+/// In-memory pool of intents that were not included in a block yet.
+///
+/// This is where solvers keep intents received from gossip until they
+/// find counterparties for them. Intents are indexed by the accounts
+/// their expectations reference and by the predicates they use, so
+/// looking up intents related to some account or predicate does not
+/// need to go through the entire pool.
+#[derive(Default)]
+pub struct IntentPool {
+  intents: HashMap<Multihash, Intent>,
+  by_account: Index<Address>,
+  by_code: Index<Address>,
+  by_entrypoint: Index<(Address, String)>,
+  by_blockhash: Index<Multihash>,
+
+  /// Recent blocks as of the last sync and the intents they include.
+  synced: Index<Multihash>,
+
+  /// Height of the most recent block as of the last sync.
+  synced_height: Option<u64>,
+}
+
+impl IntentPool {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn len(&self) -> usize {
+    self.intents.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.intents.is_empty()
+  }
+
+  pub fn get(&self, hash: &Multihash) -> Option<&Intent> {
+    self.intents.get(hash)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = &Intent> {
+    self.intents.values()
+  }
+
+  /// Adds an intent to the pool.
+  ///
+  /// Returns false if the intent is already in the pool, or if the
+  /// recent blocks observed by the last sync already include it or
+  /// no longer have its recent blockhash.
+  pub fn insert(&mut self, intent: Intent) -> bool {
+    let hash = *intent.hash();
+    if self.intents.contains_key(&hash) {
+      return false;
+    }
+
+    if self.synced_height.is_some() {
+      let expired = !self.synced.contains_key(&intent.recent_blockhash);
+      let included =
+        self.synced.values().any(|intents| intents.contains(&hash));
+      if expired || included {
+        return false;
+      }
+    }
+
+    let refs = References::of(&intent);
+    for account in refs.accounts {
+      index(&mut self.by_account, account, hash);
+    }
+    for (address, entrypoint) in refs.entrypoints {
+      index(&mut self.by_code, address.clone(), hash);
+      index(&mut self.by_entrypoint, (address, entrypoint), hash);
+    }
+    index(&mut self.by_blockhash, intent.recent_blockhash, hash);
+
+    self.intents.insert(hash, intent);
+    true
+  }
+
+  pub fn remove(&mut self, hash: &Multihash) -> Option<Intent> {
+    let intent = self.intents.remove(hash)?;
+
+    let refs = References::of(&intent);
+    for account in &refs.accounts {
+      unindex(&mut self.by_account, account, hash);
+    }
+    for entrypoint in &refs.entrypoints {
+      unindex(&mut self.by_code, &entrypoint.0, hash);
+      unindex(&mut self.by_entrypoint, entrypoint, hash);
+    }
+    unindex(&mut self.by_blockhash, &intent.recent_blockhash, hash);
+
+    Some(intent)
+  }
+
+  /// Intents that reference an account in their expectations,
+  /// either its current state or its proposed changes.
+  pub fn referencing<'a>(
+    &'a self,
+    account: &Address,
+  ) -> impl Iterator<Item = &'a Intent> {
+    self.lookup(&self.by_account, account)
+  }
+
+  /// Intents with expectations that use any predicate stored
+  /// in the given account.
+  pub fn using_code<'a>(
+    &'a self,
+    address: &Address,
+  ) -> impl Iterator<Item = &'a Intent> {
+    self.lookup(&self.by_code, address)
+  }
+
+  /// Intents with expectations that use a specific predicate.
+  pub fn using_entrypoint<'a>(
+    &'a self,
+    address: &Address,
+    entrypoint: &str,
+  ) -> impl Iterator<Item = &'a Intent> {
+    self.lookup(
+      &self.by_entrypoint,
+      &(address.clone(), entrypoint.to_owned()),
+    )
+  }
+
+  /// Other intents in the pool that reference any of the accounts
+  /// referenced by the given intent. Those are the candidates that
+  /// could be combined with it into a transaction.
+  pub fn counterparties<'a>(
+    &'a self,
+    intent: &Intent,
+  ) -> impl Iterator<Item = &'a Intent> {
+    let own = *intent.hash();
+    let hashes: HashSet<_> = References::of(intent)
+      .accounts
+      .iter()
+      .filter_map(|account| self.by_account.get(account))
+      .flatten()
+      .filter(|hash| **hash != own)
+      .collect();
+    hashes.into_iter().filter_map(|hash| self.intents.get(hash))
+  }
+
+  /// Intents whose expectations match a pattern, along with the
+  /// values captured by the pattern.
+  pub fn matching(
+    &self,
+    template: impl Into<Pattern>,
+  ) -> Vec<(&Intent, HashMap<String, MatchValue>)> {
+    let template = template.into();
+    let candidates: Box<dyn Iterator<Item = &Intent>> =
+      match required_entrypoint(&template) {
+        Some((address, entrypoint)) => {
+          Box::new(self.using_entrypoint(address, entrypoint))
+        }
+        None => Box::new(self.iter()),
+      };
+
+    candidates
+      .filter_map(|intent| {
+        intent
+          .expectations
+          .matches(template.clone())
+          .map(|captures| (intent, captures))
+      })
+      .collect()
+  }
+
+  /// Removes intents included in the transactions of a block.
+  pub fn remove_included(&mut self, block: &Block) -> Vec<Intent> {
+    block
+      .transactions
+      .iter()
+      .flat_map(|tx| tx.intents.iter())
+      .filter_map(|intent| self.remove(intent.hash()))
+      .collect()
+  }
+
+  /// Removes intents whose recent blockhash is not one of the given
+  /// blocks. Those intents are expired and would be rejected by the
+  /// chain.
+  pub fn expire<'a>(
+    &mut self,
+    recent: impl IntoIterator<Item = &'a Multihash>,
+  ) -> Vec<Intent> {
+    let recent: HashSet<_> = recent.into_iter().collect();
+    let expired: Vec<_> = self
+      .by_blockhash
+      .iter()
+      .filter(|(blockhash, _)| !recent.contains(blockhash))
+      .flat_map(|(_, hashes)| hashes.iter().cloned())
+      .collect();
+    expired
+      .iter()
+      .filter_map(|hash| self.remove(hash))
+      .collect()
+  }
+
+  /// Catches up with the blocks consumed by a watcher.
+  ///
+  /// Intents included in any of the recent blocks of the watcher are
+  /// removed, and so are intents that refer to blocks outside of its
+  /// recent history. Intents should be built against blocks observed
+  /// by the same watcher, otherwise they might be expired too early.
+  ///
+  /// Only blocks consumed since the previous sync are looked at.
+  pub fn sync<S, C>(&mut self, watcher: &BlockchainWatcher<S, C>)
+  where
+    S: State + 'static,
+    C: State + 'static,
+  {
+    watcher.snapshot(|state| {
+      // the watcher switches forks only to higher ones,
+      // so the same height means no new blocks.
+      let height = state.last().height;
+      if self.synced_height == Some(height) {
+        return;
+      }
+
+      let first = self.synced_height.replace(height).is_none();
+      let recent: HashSet<_> = state.recent().map(|b| *b.hash()).collect();
+      let evicted: Vec<_> = self
+        .synced
+        .keys()
+        .filter(|hash| !recent.contains(hash))
+        .cloned()
+        .collect();
+      for blockhash in evicted {
+        self.synced.remove(&blockhash);
+        let expired = self.by_blockhash.get(&blockhash).cloned();
+        for hash in expired.into_iter().flatten() {
+          self.remove(&hash);
+        }
+      }
+
+      for block in state.recent() {
+        if !self.synced.contains_key(block.hash()) {
+          self.remove_included(block);
+          let included = block
+            .transactions
+            .iter()
+            .flat_map(|tx| tx.intents.iter())
+            .map(|intent| *intent.hash())
+            .collect();
+          self.synced.insert(*block.hash(), included);
+        }
+      }
+
+      // intents inserted before the first sync may
+      // refer to blocks that were never observed.
+      if first {
+        self.expire(&recent);
+      }
+    })
+  }
+
+  fn lookup<'a, K: Eq + Hash>(
+    &'a self,
+    index: &'a Index<K>,
+    key: &K,
+  ) -> impl Iterator<Item = &'a Intent> {
+    index
+      .get(key)
+      .into_iter()
+      .flatten()
+      .filter_map(|hash| self.intents.get(hash))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::IntentPool,
+    crate::{BlockchainWatcher, InMemoryStateStore, ParamPattern, Pattern},
+    anoma_primitives::{
+      Address,
+      Block,
+      Code,
+      Intent,
+      Param,
+      Predicate,
+      PredicateTree,
+      Transaction,
+    },
+    futures::channel::mpsc::unbounded,
+    multihash::Multihash,
+    std::num::NonZeroUsize,
+  };
+
+  fn wallet(name: &str) -> Address {
+    format!("/token/usdx/{name}").parse().unwrap()
+  }
+
+  /// An intent that expects `from` to send `amount` to `to`.
+  fn transfer(
+    recent_blockhash: Multihash,
+    from: &Address,
+    to: &Address,
+    amount: u64,
+  ) -> Intent {
+    Intent::new(
+      recent_blockhash,
+      PredicateTree::Id(Predicate {
+        code: Code::AccountRef(
+          "/stdpred/v1".parse().unwrap(),
+          "token_sent".into(),
+        ),
+        params: vec![
+          Param::ProposalRef(from.clone()),
+          Param::AccountRef(to.clone()),
+          Param::Inline(rmp_serde::to_vec(&amount).unwrap()),
+        ],
+      }),
+    )
+  }
+
+  fn hashes<'a>(intents: impl Iterator<Item = &'a Intent>) -> Vec<Multihash> {
+    let mut hashes: Vec<_> = intents.map(|intent| *intent.hash()).collect();
+    hashes.sort_by_key(|hash| hash.to_bytes());
+    hashes
+  }
+
+  #[test]
+  fn indexes_and_counterparties() {
+    let blockhash = *Block::zero().hash();
+    let alice_to_bob = transfer(blockhash, &wallet("alice"), &wallet("bob"), 5);
+    let bob_to_carol = transfer(blockhash, &wallet("bob"), &wallet("carol"), 3);
+    let dave_to_erin = transfer(blockhash, &wallet("dave"), &wallet("erin"), 1);
+
+    let mut pool = IntentPool::new();
+    assert!(pool.insert(alice_to_bob.clone()));
+    assert!(pool.insert(bob_to_carol.clone()));
+    assert!(pool.insert(dave_to_erin.clone()));
+    assert!(!pool.insert(alice_to_bob.clone()));
+    assert_eq!(pool.len(), 3);
+
+    assert_eq!(
+      hashes(pool.referencing(&wallet("bob"))),
+      hashes([&alice_to_bob, &bob_to_carol].into_iter())
+    );
+    assert_eq!(
+      pool
+        .using_entrypoint(&"/stdpred/v1".parse().unwrap(), "token_sent")
+        .count(),
+      3
+    );
+    assert_eq!(pool.using_code(&"/stdpred/v1".parse().unwrap()).count(), 3);
+    assert_eq!(pool.using_code(&"/stdpred/v2".parse().unwrap()).count(), 0);
+
+    assert_eq!(hashes(pool.counterparties(&alice_to_bob)), vec![
+      *bob_to_carol.hash()
+    ]);
+    assert_eq!(pool.counterparties(&dave_to_erin).count(), 0);
+
+    // patterns are matched only against intents using the same predicate
+    let template = Pattern::Id(Predicate {
+      code: Code::AccountRef(
+        "/stdpred/v1".parse().unwrap(),
+        "token_sent".into(),
+      ),
+      params: vec![
+        ParamPattern::ProposalRef("from".into()),
+        ParamPattern::Exact(Param::AccountRef(wallet("carol"))),
+        ParamPattern::Any,
+      ],
+    });
+    let matches = pool.matching(template);
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].0.hash(), bob_to_carol.hash());
+
+    // removal cleans up all indexes
+    assert!(pool.remove(bob_to_carol.hash()).is_some());
+    assert_eq!(hashes(pool.referencing(&wallet("bob"))), vec![
+      *alice_to_bob.hash()
+    ]);
+    assert!(pool.referencing(&wallet("carol")).next().is_none());
+    assert_eq!(pool.counterparties(&alice_to_bob).count(), 0);
+  }
+
+  #[tokio::test]
+  async fn sync_with_watcher() {
+    let zero = Block::zero();
+    let (sender, stream) = unbounded();
+    let watcher = BlockchainWatcher::new(
+      NonZeroUsize::new(2).unwrap(),
+      InMemoryStateStore::default(),
+      InMemoryStateStore::default(),
+      std::iter::once(zero.clone()),
+      stream,
+    )
+    .unwrap();
+
+    let included = transfer(*zero.hash(), &wallet("alice"), &wallet("bob"), 1);
+    let expiring = transfer(*zero.hash(), &wallet("bob"), &wallet("carol"), 1);
+
+    let b1 = Block::new(&zero, 1, vec![Transaction::new(
+      vec![included.clone()],
+      Default::default(),
+    )]);
+    let pending = transfer(*b1.hash(), &wallet("carol"), &wallet("dave"), 1);
+
+    let mut pool = IntentPool::new();
+    pool.insert(included.clone());
+    pool.insert(expiring.clone());
+    pool.insert(pending.clone());
+
+    sender.unbounded_send(b1.clone()).unwrap();
+    watcher.await_block_height(1).await.unwrap();
+    pool.sync(&watcher);
+    assert_eq!(
+      hashes(pool.iter()),
+      hashes([&expiring, &pending].into_iter())
+    );
+
+    // late arrivals of intents that are already on chain
+    assert!(!pool.insert(included.clone()));
+
+    // the genesis block falls out of the recent history
+    sender.unbounded_send(Block::new(&b1, 2, vec![])).unwrap();
+    watcher.await_block_height(2).await.unwrap();
+    pool.sync(&watcher);
+    assert_eq!(hashes(pool.iter()), vec![*pending.hash()]);
+    assert!(!pool.insert(expiring));
+    assert!(!pool.insert(included));
+  }
+}
@@ -1,5 +1,14 @@
 use {
-  anoma_primitives::{Address, Basic, Code, Param, PredicateTree, Repr},
+  anoma_primitives::{
+    Address,
+    Basic,
+    Code,
+    ExpressionTree,
+    Param,
+    Predicate,
+    PredicateTree,
+    Repr,
+  },
   serde::{Deserialize, Serialize},
   std::collections::HashMap,
 };
@@ -12,6 +21,34 @@ pub enum ParamPattern {
   AccountRef(String),
   ProposalRef(String),
   CalldataRef(String),
+
+  /// Captures an inline param only if its value satisfies the constraint.
+  Constrained(String, ValueConstraint),
+}
+
+/// Constraints on the value of inline params.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ValueConstraint {
+  /// A MessagePack encoded u64 in the inclusive range `[min, max]`.
+  U64Range(u64, u64),
+}
+
+impl ValueConstraint {
+  fn satisfied_by(&self, value: &[u8]) -> bool {
+    match self {
+      ValueConstraint::U64Range(min, max) => match decode_u64(value) {
+        Some(value) => (*min..=*max).contains(&value),
+        None => false,
+      },
+    }
+  }
+}
+
+/// Decodes a MessagePack integer that spans the entire value.
+fn decode_u64(mut value: &[u8]) -> Option<u64> {
+  let decoded =
+    u64::deserialize(&mut rmp_serde::Deserializer::new(&mut value)).ok()?;
+  value.is_empty().then_some(decoded)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -29,100 +66,203 @@ impl Repr for Query {
   type Param = ParamPattern;
 }
 
+/// A template of an intent expression tree.
+///
+/// `And` and `Or` nodes match their operands in any order. Params that
+/// are captured under the same name must have the same value everywhere
+/// in the template. Every [`PredicateTree<Query>`] converts into a
+/// pattern that matches trees of the same shape.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Pattern {
+  Id(Predicate<Query>),
+  Not(Box<Pattern>),
+  And(Box<Pattern>, Box<Pattern>),
+  Or(Box<Pattern>, Box<Pattern>),
+
+  /// Matches any subtree, without capturing anything.
+  Any,
+
+  /// Matches a subtree that has a node matching the
+  /// pattern anywhere in it, including the subtree itself.
+  Contains(Box<Pattern>),
+}
+
+impl From<PredicateTree<Query>> for Pattern {
+  fn from(tree: PredicateTree<Query>) -> Self {
+    match tree {
+      ExpressionTree::Id(pred) => Pattern::Id(pred),
+      ExpressionTree::Not(t) => Pattern::Not(Box::new((*t).into())),
+      ExpressionTree::And(l, r) => {
+        Pattern::And(Box::new((*l).into()), Box::new((*r).into()))
+      }
+      ExpressionTree::Or(l, r) => {
+        Pattern::Or(Box::new((*l).into()), Box::new((*r).into()))
+      }
+    }
+  }
+}
+
+type Captures = HashMap<String, MatchValue>;
+
 /// Used to match expression tree patterns in intents.
 /// The primary use case is for solvers to identify intents they are interested
 /// in solving from the incoming intent gossip stream.
 pub trait ExpressionPattern {
-  fn matches(
-    &self,
-    template: PredicateTree<Query>,
-  ) -> Option<HashMap<String, MatchValue>>;
+  fn matches(&self, template: impl Into<Pattern>) -> Option<Captures>;
 }
 
 impl ExpressionPattern for PredicateTree<Basic> {
-  fn matches(
-    &self,
-    template: PredicateTree<Query>,
-  ) -> Option<HashMap<String, MatchValue>> {
-    type BasicTree = PredicateTree<Basic>;
-    type QueryTree = PredicateTree<Query>;
-
-    fn find_matches(
-      basic: &BasicTree,
-      template: &QueryTree,
-      matches: &mut HashMap<String, MatchValue>,
-    ) -> bool {
-      match (basic, template) {
-        (BasicTree::Id(b_pred), QueryTree::Id(p_pred)) => {
-          if b_pred.code == p_pred.code
-            && b_pred.params.len() == p_pred.params.len()
-          {
-            for (left, right) in b_pred.params.iter().zip(p_pred.params.iter())
-            {
-              match (left, right) {
-                (_, ParamPattern::Any) => {} // match but no captures
-                (left_p, ParamPattern::Exact(right_p)) => {
-                  return left_p == right_p;
-                }
-                (Param::Inline(val), ParamPattern::Inline(name)) => {
-                  matches.insert(name.clone(), MatchValue::Bytes(val.clone()));
-                }
-                (Param::AccountRef(val), ParamPattern::AccountRef(name)) => {
-                  matches
-                    .insert(name.clone(), MatchValue::Address(val.clone()));
-                }
-                (Param::ProposalRef(val), ParamPattern::ProposalRef(name)) => {
-                  matches
-                    .insert(name.clone(), MatchValue::Address(val.clone()));
-                }
-                (Param::CalldataRef(val), ParamPattern::CalldataRef(name)) => {
-                  matches.insert(name.clone(), MatchValue::String(val.clone()));
-                }
-                _ => return false,
-              }
-            }
-
-            true
-          } else {
-            false
-          }
-        }
-        (BasicTree::Not(left), QueryTree::Not(right)) => {
-          find_matches(left, right, matches)
-        }
-        (
-          BasicTree::And(left_left, left_right),
-          QueryTree::And(right_left, right_right),
-        ) => {
-          find_matches(left_left, right_left, matches)
-            && find_matches(left_right, right_right, matches)
+  fn matches(&self, template: impl Into<Pattern>) -> Option<Captures> {
+    find_matches(self, &template.into(), Captures::new()).next()
+  }
+}
+
+/// Records a captured value, unless the same name
+/// was already captured with a different value.
+fn capture(
+  mut captures: Captures,
+  name: &str,
+  value: MatchValue,
+) -> Option<Captures> {
+  match captures.get(name) {
+    Some(existing) if *existing != value => None,
+    Some(_) => Some(captures),
+    None => {
+      captures.insert(name.to_owned(), value);
+      Some(captures)
+    }
+  }
+}
+
+fn match_predicate(
+  basic: &Predicate<Basic>,
+  template: &Predicate<Query>,
+  mut captures: Captures,
+) -> Option<Captures> {
+  if basic.code != template.code || basic.params.len() != template.params.len()
+  {
+    return None;
+  }
+
+  for (param, pattern) in basic.params.iter().zip(template.params.iter()) {
+    captures = match (param, pattern) {
+      (_, ParamPattern::Any) => captures,
+      (param, ParamPattern::Exact(expected)) => {
+        if param != expected {
+          return None;
         }
-        (
-          BasicTree::Or(left_left, left_right),
-          QueryTree::Or(right_left, right_right),
-        ) => {
-          find_matches(left_left, right_left, matches)
-            && find_matches(left_right, right_right, matches)
+        captures
+      }
+      (Param::Inline(val), ParamPattern::Inline(name)) => {
+        capture(captures, name, MatchValue::Bytes(val.clone()))?
+      }
+      (Param::Inline(val), ParamPattern::Constrained(name, constraint)) => {
+        if !constraint.satisfied_by(val) {
+          return None;
         }
-        _ => false, // different tree shapes, not a match
+        capture(captures, name, MatchValue::Bytes(val.clone()))?
       }
-    }
+      (Param::AccountRef(val), ParamPattern::AccountRef(name)) => {
+        capture(captures, name, MatchValue::Address(val.clone()))?
+      }
+      (Param::ProposalRef(val), ParamPattern::ProposalRef(name)) => {
+        capture(captures, name, MatchValue::Address(val.clone()))?
+      }
+      (Param::CalldataRef(val), ParamPattern::CalldataRef(name)) => {
+        capture(captures, name, MatchValue::String(val.clone()))?
+      }
+      _ => return None,
+    };
+  }
+
+  Some(captures)
+}
 
-    let mut matches = HashMap::new();
-    match find_matches(self, &template, &mut matches) {
-      true => Some(matches),
-      false => None,
+/// Every way a subtree matches a pattern, as the captures of each
+/// alternative. Alternatives are explored lazily, so finding the first
+/// match does not enumerate the others.
+type Alternatives<'a> = Box<dyn Iterator<Item = Captures> + 'a>;
+
+/// Matches both operands of a binary node in either order. Captures
+/// from the first operand constrain the second one.
+fn match_commutative<'a>(
+  (left, right): (&'a PredicateTree<Basic>, &'a PredicateTree<Basic>),
+  (left_p, right_p): (&'a Pattern, &'a Pattern),
+  captures: Captures,
+) -> Alternatives<'a> {
+  let straight = find_matches(left, left_p, captures.clone())
+    .flat_map(move |captures| find_matches(right, right_p, captures));
+  let swapped = find_matches(left, right_p, captures)
+    .flat_map(move |captures| find_matches(right, left_p, captures));
+  Box::new(straight.chain(swapped))
+}
+
+fn find_matches<'a>(
+  basic: &'a PredicateTree<Basic>,
+  template: &'a Pattern,
+  captures: Captures,
+) -> Alternatives<'a> {
+  match (basic, template) {
+    (_, Pattern::Any) => Box::new(std::iter::once(captures)),
+    (_, Pattern::Contains(inner)) => {
+      let children: Vec<_> = match basic {
+        ExpressionTree::Id(_) => vec![],
+        ExpressionTree::Not(child) => vec![child.as_ref()],
+        ExpressionTree::And(l, r) | ExpressionTree::Or(l, r) => {
+          vec![l.as_ref(), r.as_ref()]
+        }
+      };
+      let nested = captures.clone();
+      Box::new(
+        find_matches(basic, inner, captures).chain(
+          children.into_iter().flat_map(move |child| {
+            find_matches(child, template, nested.clone())
+          }),
+        ),
+      )
+    }
+    (ExpressionTree::Id(pred), Pattern::Id(pattern)) => {
+      Box::new(match_predicate(pred, pattern, captures).into_iter())
     }
+    (ExpressionTree::Not(child), Pattern::Not(pattern)) => {
+      find_matches(child, pattern, captures)
+    }
+    (ExpressionTree::And(l, r), Pattern::And(lp, rp))
+    | (ExpressionTree::Or(l, r), Pattern::Or(lp, rp)) => {
+      match_commutative((l, r), (lp, rp), captures)
+    }
+    _ => Box::new(std::iter::empty()), // different tree shapes, not a match
   }
 }
 
 #[cfg(test)]
 mod tests {
   use {
-    crate::{query::MatchValue, ExpressionPattern, ParamPattern, Query},
+    crate::{
+      query::MatchValue,
+      ExpressionPattern,
+      ParamPattern,
+      Pattern,
+      Query,
+      ValueConstraint,
+    },
     anoma_primitives::{Basic, Code, Param, Predicate, PredicateTree},
   };
 
+  fn basic(entrypoint: &str, params: Vec<Param>) -> PredicateTree<Basic> {
+    PredicateTree::Id(Predicate {
+      code: Code::AccountRef("/stdpred".parse().unwrap(), entrypoint.into()),
+      params,
+    })
+  }
+
+  fn pattern(entrypoint: &str, params: Vec<ParamPattern>) -> Pattern {
+    Pattern::Id(Predicate {
+      code: Code::AccountRef("/stdpred".parse().unwrap(), entrypoint.into()),
+      params,
+    })
+  }
+
   #[test]
   fn matching_single_predicate() {
     let pred = PredicateTree::<Basic>::Id(Predicate {
@@ -385,4 +525,202 @@ mod tests {
     let result = pred.matches(pattern);
     assert!(result.is_none());
   }
+
+  #[test]
+  fn matching_exact_checks_all_params() {
+    let pred = basic("uint_equal", vec![
+      Param::Inline(b"zero".to_vec()),
+      Param::Inline(b"someval".to_vec()),
+    ]);
+
+    let result = pred
+      .matches(pattern("uint_equal", vec![
+        ParamPattern::Exact(Param::Inline(b"zero".to_vec())),
+        ParamPattern::Inline("param1".into()),
+      ]))
+      .unwrap();
+    assert_eq!(
+      result.get("param1").unwrap(),
+      &MatchValue::Bytes(b"someval".to_vec())
+    );
+
+    assert!(pred
+      .matches(pattern("uint_equal", vec![
+        ParamPattern::Exact(Param::Inline(b"zero".to_vec())),
+        ParamPattern::AccountRef("param1".into()),
+      ]))
+      .is_none());
+  }
+
+  #[test]
+  fn matching_commutative_operands() {
+    let pred = PredicateTree::Or(
+      Box::new(basic("pred1", vec![Param::Inline(b"val1".to_vec())])),
+      Box::new(basic("pred2", vec![Param::Inline(b"val2".to_vec())])),
+    );
+
+    let template = Pattern::Or(
+      Box::new(pattern("pred2", vec![ParamPattern::Inline("p2".into())])),
+      Box::new(pattern("pred1", vec![ParamPattern::Inline("p1".into())])),
+    );
+
+    let result = pred.matches(template).unwrap();
+    assert_eq!(
+      result.get("p1").unwrap(),
+      &MatchValue::Bytes(b"val1".to_vec())
+    );
+    assert_eq!(
+      result.get("p2").unwrap(),
+      &MatchValue::Bytes(b"val2".to_vec())
+    );
+
+    // the operator itself still has to match
+    let template = Pattern::And(
+      Box::new(pattern("pred2", vec![ParamPattern::Any])),
+      Box::new(pattern("pred1", vec![ParamPattern::Any])),
+    );
+    assert!(pred.matches(template).is_none());
+  }
+
+  #[test]
+  fn matching_wildcards_and_contains() {
+    let pred = PredicateTree::And(
+      Box::new(basic("signature", vec![Param::Inline(b"key".to_vec())])),
+      Box::new(PredicateTree::Or(
+        Box::new(PredicateTree::Not(Box::new(basic("pred1", vec![])))),
+        Box::new(basic("transfer", vec![Param::AccountRef(
+          "/token/usdx".parse().unwrap(),
+        )])),
+      )),
+    );
+
+    // any subtree on one side of the conjunction
+    let template = Pattern::And(
+      Box::new(Pattern::Any),
+      Box::new(pattern("signature", vec![ParamPattern::Inline(
+        "key".into(),
+      )])),
+    );
+    let result = pred.matches(template).unwrap();
+    assert_eq!(
+      result.get("key").unwrap(),
+      &MatchValue::Bytes(b"key".to_vec())
+    );
+
+    // a predicate nested anywhere in the tree
+    let template = Pattern::Contains(Box::new(pattern("transfer", vec![
+      ParamPattern::AccountRef("token".into()),
+    ])));
+    let result = pred.matches(template).unwrap();
+    assert_eq!(
+      result.get("token").unwrap(),
+      &MatchValue::Address("/token/usdx".parse().unwrap())
+    );
+
+    let template = Pattern::Contains(Box::new(Pattern::Not(Box::new(
+      pattern("pred1", vec![]),
+    ))));
+    assert!(pred.matches(template).is_some());
+
+    let template = Pattern::Contains(Box::new(pattern("pred2", vec![])));
+    assert!(pred.matches(template).is_none());
+  }
+
+  #[test]
+  fn matching_unifies_captures() {
+    let template = PredicateTree::<Query>::And(
+      Box::new(PredicateTree::Id(Predicate {
+        code: Code::AccountRef("/stdpred".parse().unwrap(), "pred1".into()),
+        params: vec![ParamPattern::AccountRef("wallet".into())],
+      })),
+      Box::new(PredicateTree::Id(Predicate {
+        code: Code::AccountRef("/stdpred".parse().unwrap(), "pred2".into()),
+        params: vec![ParamPattern::ProposalRef("wallet".into())],
+      })),
+    );
+
+    let pred = |second: &str| {
+      PredicateTree::And(
+        Box::new(basic("pred1", vec![Param::AccountRef(
+          "/wallet1".parse().unwrap(),
+        )])),
+        Box::new(basic("pred2", vec![Param::ProposalRef(
+          second.parse().unwrap(),
+        )])),
+      )
+    };
+
+    let result = pred("/wallet1").matches(template.clone()).unwrap();
+    assert_eq!(
+      result.get("wallet").unwrap(),
+      &MatchValue::Address("/wallet1".parse().unwrap())
+    );
+    assert!(pred("/wallet2").matches(template).is_none());
+  }
+
+  #[test]
+  fn matching_backtracks_over_alternatives() {
+    let p = |wallet: &str| {
+      basic("pred1", vec![Param::AccountRef(wallet.parse().unwrap())])
+    };
+    let pred = PredicateTree::And(
+      Box::new(PredicateTree::And(
+        Box::new(p("/wallet1")),
+        Box::new(p("/wallet2")),
+      )),
+      Box::new(basic("pred2", vec![Param::ProposalRef(
+        "/wallet2".parse().unwrap(),
+      )])),
+    );
+
+    // the first pred1 found binds a wallet that pred2 does not use
+    let template = Pattern::And(
+      Box::new(Pattern::Contains(Box::new(pattern("pred1", vec![
+        ParamPattern::AccountRef("wallet".into()),
+      ])))),
+      Box::new(pattern("pred2", vec![ParamPattern::ProposalRef(
+        "wallet".into(),
+      )])),
+    );
+    let result = pred.matches(template).unwrap();
+    assert_eq!(
+      result.get("wallet").unwrap(),
+      &MatchValue::Address("/wallet2".parse().unwrap())
+    );
+  }
+
+  #[test]
+  fn matching_constrained_values() {
+    let pred = |amount: u64| {
+      basic("uint_greater_than_by", vec![
+        Param::AccountRef("/wallet".parse().unwrap()),
+        Param::Inline(rmp_serde::to_vec(&amount).unwrap()),
+      ])
+    };
+
+    let template = pattern("uint_greater_than_by", vec![
+      ParamPattern::Any,
+      ParamPattern::Constrained(
+        "amount".into(),
+        ValueConstraint::U64Range(100, 1000),
+      ),
+    ]);
+
+    let result = pred(500).matches(template.clone()).unwrap();
+    assert_eq!(
+      result.get("amount").unwrap(),
+      &MatchValue::Bytes(rmp_serde::to_vec(&500u64).unwrap())
+    );
+    assert!(pred(100).matches(template.clone()).is_some());
+    assert!(pred(1000).matches(template.clone()).is_some());
+    assert!(pred(99).matches(template.clone()).is_none());
+    assert!(pred(1001).matches(template.clone()).is_none());
+
+    // values that are not u64 don't satisfy the constraint
+    let pred = basic("uint_greater_than_by", vec![
+      Param::AccountRef("/wallet".parse().unwrap()),
+      Param::Inline(b"not a number".to_vec()),
+    ]);
+    assert!(pred.matches(template).is_none());
+  }
 }
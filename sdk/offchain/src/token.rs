@@ -0,0 +1,283 @@
+//! Client side of the fungible token predicates in the standard predicate
+//! library.
+//!
+//! The types in this module mirror the MessagePack state layout of token
+//! and wallet accounts expected by `token` and `token_wallet` in stdpred,
+//! and the functions build intents and proposals for token transfers.
+
+use {
+  anoma_primitives::{
+    Account,
+    AccountChange,
+    Address,
+    Code,
+    Intent,
+    Param,
+    Predicate,
+    PredicateTree,
+  },
+  anoma_vm::State,
+  multihash::Multihash,
+  serde::{Deserialize, Serialize},
+  std::collections::BTreeMap,
+  thiserror::Error,
+};
+
+/// Address at which the standard predicate library is installed.
+pub const STDPRED_ADDRESS: &str = "/stdpred/v1";
+
+#[derive(Debug, Error)]
+pub enum Error {
+  #[error("Wallet {0} does not exist")]
+  WalletNotFound(Address),
+
+  #[error("Wallet {0} has insufficient balance")]
+  InsufficientBalance(Address),
+
+  #[error("Wallet {0} balance overflow")]
+  BalanceOverflow(Address),
+
+  #[error("Wallet {0} can't transfer tokens to itself")]
+  SelfTransfer(Address),
+
+  #[error("Invalid token state of {0}: {1}")]
+  InvalidState(Address, rmp_serde::decode::Error),
+
+  #[error("Failed to encode token state: {0}")]
+  Encoding(#[from] rmp_serde::encode::Error),
+}
+
+/// State of a token account, e.g. `/token/usdx`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenState {
+  /// base58 encoded ed25519 public key of the mint authority.
+  pub authority: String,
+
+  /// Sum of balances of all wallets of this token.
+  pub supply: u64,
+}
+
+/// State of a wallet account, e.g. `/token/usdx/alice.eth`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletState {
+  pub balance: u64,
+
+  #[serde(default)]
+  pub frozen: bool,
+
+  /// Amounts that spenders, identified by their base58 encoded ed25519
+  /// public key, are allowed to spend from this wallet.
+  #[serde(default)]
+  pub allowances: BTreeMap<String, u64>,
+}
+
+impl TokenState {
+  pub fn encode(&self) -> Result<Vec<u8>, Error> {
+    Ok(rmp_serde::to_vec_named(self)?)
+  }
+}
+
+impl WalletState {
+  pub fn encode(&self) -> Result<Vec<u8>, Error> {
+    Ok(rmp_serde::to_vec_named(self)?)
+  }
+
+  /// Reads the state of a wallet, `None` if the account does not exist.
+  pub fn load(
+    state: &dyn State,
+    address: &Address,
+  ) -> Result<Option<Self>, Error> {
+    state
+      .get(address)
+      .map(|account| {
+        rmp_serde::from_slice(&account.state)
+          .map_err(|e| Error::InvalidState(address.clone(), e))
+      })
+      .transpose()
+  }
+}
+
+pub(crate) fn stdpred(entrypoint: &str, params: Vec<Param>) -> PredicateTree {
+  PredicateTree::Id(Predicate {
+    code: Code::AccountRef(
+      STDPRED_ADDRESS.parse().expect("valid address"),
+      entrypoint.into(),
+    ),
+    params,
+  })
+}
+
+fn inline(value: &impl Serialize) -> Param {
+  Param::Inline(rmp_serde::to_vec(value).expect("infallible encoding"))
+}
+
+/// Predicates of a token account that enforce the supply invariants.
+pub fn token_predicates(token: &Address) -> PredicateTree {
+  stdpred("token", vec![Param::AccountRef(token.clone())])
+}
+
+/// Predicates of a wallet owned by a single ed25519 key.
+pub fn wallet_predicates(wallet: &Address, owner: &[u8]) -> PredicateTree {
+  stdpred("token_wallet", vec![
+    Param::AccountRef(wallet.clone()),
+    Param::Inline(owner.to_vec()),
+  ])
+}
+
+/// Creates an unsigned intent that expects exactly `amount` tokens to be
+/// debited from the sender wallet and credited to the recipient wallet.
+///
+/// To authorize the transfer, the sender signs the `signing_hash` of the
+/// intent and attaches the signature to its calldata under the base58
+/// encoded public key of the wallet owner.
+pub fn transfer_intent(
+  sender: &Address,
+  recipient: &Address,
+  amount: u64,
+  recent_blockhash: Multihash,
+) -> Intent {
+  Intent::new(
+    recent_blockhash,
+    PredicateTree::And(
+      Box::new(stdpred("token_sent", vec![inline(sender), inline(&amount)])),
+      Box::new(stdpred("token_received", vec![
+        inline(recipient),
+        inline(&amount),
+      ])),
+    ),
+  )
+}
+
+/// Creates account changes that move `amount` tokens between two wallets
+/// of the same token.
+///
+/// If the recipient wallet does not exist, it is created with
+/// `wallet_predicates` of `recipient_owner`, otherwise the owner is
+/// ignored.
+pub fn transfer_proposals(
+  state: &dyn State,
+  sender: &Address,
+  recipient: &Address,
+  amount: u64,
+  recipient_owner: Option<&[u8]>,
+) -> Result<BTreeMap<Address, AccountChange>, Error> {
+  // both changes would target the same account, and the credit would
+  // overwrite the debit.
+  if sender == recipient {
+    return Err(Error::SelfTransfer(sender.clone()));
+  }
+
+  let mut sender_wallet = WalletState::load(state, sender)?
+    .ok_or_else(|| Error::WalletNotFound(sender.clone()))?;
+  sender_wallet.balance = sender_wallet
+    .balance
+    .checked_sub(amount)
+    .ok_or_else(|| Error::InsufficientBalance(sender.clone()))?;
+
+  let recipient_change = match WalletState::load(state, recipient)? {
+    Some(mut wallet) => {
+      wallet.balance = wallet
+        .balance
+        .checked_add(amount)
+        .ok_or_else(|| Error::BalanceOverflow(recipient.clone()))?;
+      AccountChange::ReplaceState(wallet.encode()?)
+    }
+    None => {
+      let owner = recipient_owner
+        .ok_or_else(|| Error::WalletNotFound(recipient.clone()))?;
+      AccountChange::CreateAccount(Account {
+        state: WalletState {
+          balance: amount,
+          ..Default::default()
+        }
+        .encode()?,
+        predicates: wallet_predicates(recipient, owner),
+      })
+    }
+  };
+
+  Ok(
+    [
+      (
+        sender.clone(),
+        AccountChange::ReplaceState(sender_wallet.encode()?),
+      ),
+      (recipient.clone(), recipient_change),
+    ]
+    .into_iter()
+    .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::{transfer_proposals, wallet_predicates, Error, WalletState},
+    anoma_primitives::{Account, AccountChange, Address},
+    anoma_vm::{InMemoryStateStore, State, StateDiff},
+  };
+
+  fn wallet(address: &Address, balance: u64) -> StateDiff {
+    let mut diff = StateDiff::default();
+    diff.set(address.clone(), Account {
+      state: WalletState {
+        balance,
+        ..Default::default()
+      }
+      .encode()
+      .unwrap(),
+      predicates: wallet_predicates(address, &[1; 32]),
+    });
+    diff
+  }
+
+  #[test]
+  fn transfer_between_wallets() {
+    let alice: Address = "/token/usdx/alice.eth".parse().unwrap();
+    let bob: Address = "/token/usdx/bob.eth".parse().unwrap();
+
+    let mut state = InMemoryStateStore::default();
+    state.apply(wallet(&alice, 100));
+
+    assert!(matches!(
+      transfer_proposals(&state, &alice, &bob, 40, None),
+      Err(Error::WalletNotFound(_))
+    ));
+    assert!(matches!(
+      transfer_proposals(&state, &alice, &bob, 101, Some(&[2; 32])),
+      Err(Error::InsufficientBalance(_))
+    ));
+    assert!(matches!(
+      transfer_proposals(&state, &alice, &alice, 40, None),
+      Err(Error::SelfTransfer(_))
+    ));
+
+    let proposals =
+      transfer_proposals(&state, &alice, &bob, 40, Some(&[2; 32])).unwrap();
+    let AccountChange::ReplaceState(sender) = &proposals[&alice] else {
+      panic!("expected a state replacement of the sender wallet");
+    };
+    let AccountChange::CreateAccount(recipient) = &proposals[&bob] else {
+      panic!("expected the recipient wallet to be created");
+    };
+    assert_eq!(rmp_serde::from_slice::<WalletState>(sender).unwrap(), {
+      WalletState {
+        balance: 60,
+        ..Default::default()
+      }
+    });
+    assert_eq!(recipient.predicates, wallet_predicates(&bob, &[2; 32]));
+
+    state.apply(wallet(&bob, 5));
+    let proposals = transfer_proposals(&state, &alice, &bob, 40, None).unwrap();
+    let AccountChange::ReplaceState(recipient) = &proposals[&bob] else {
+      panic!("expected a state replacement of the recipient wallet");
+    };
+    assert_eq!(
+      rmp_serde::from_slice::<WalletState>(recipient)
+        .unwrap()
+        .balance,
+      45
+    );
+  }
+}
@@ -0,0 +1,378 @@
+//! Fluent builders of intents and transactions.
+//!
+//! Intents are signed only once their expectations are final, and
+//! transactions are executed against the current state before they are
+//! returned, so anything that would be rejected by the chain is caught
+//! locally.
+
+use {
+  crate::wallet::sign_intent,
+  anoma_primitives::{
+    Account,
+    AccountChange,
+    Address,
+    BlockContext,
+    Calldata,
+    Code,
+    Intent,
+    Param,
+    Predicate,
+    PredicateTree,
+    Transaction,
+  },
+  anoma_vm::{RuntimeError, State},
+  ed25519_dalek::Keypair,
+  multihash::Multihash,
+  serde::{de::DeserializeOwned, Serialize},
+  std::collections::BTreeMap,
+  thiserror::Error,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+  #[error("Intent has no expectations")]
+  NoExpectations,
+
+  #[error("Account {0} does not exist")]
+  AccountNotFound(Address),
+
+  #[error("Account {0} already has a conflicting change")]
+  ConflictingChange(Address),
+
+  #[error("Invalid state of account {0}: {1}")]
+  InvalidState(Address, rmp_serde::decode::Error),
+
+  #[error("Failed to encode account state: {0}")]
+  Encoding(#[from] rmp_serde::encode::Error),
+
+  #[error("Transaction rejected: {0}")]
+  Rejected(#[from] RuntimeError),
+}
+
+/// Params of predicates that compare the current state of an account
+/// with its proposed state, e.g. `uint_greater_than_by`.
+pub fn change_params(address: &Address) -> [Param; 2] {
+  [
+    Param::AccountRef(address.clone()),
+    Param::ProposalRef(address.clone()),
+  ]
+}
+
+/// Builds an intent and signs it.
+///
+/// Expectations added one after another are combined with `And`.
+/// Signatures of all signers are computed over the signing hash of the
+/// final expectations when the intent is built, and are attached to its
+/// calldata under the base58 encoded public key of the signer, which is
+/// what `require_ed25519_signature` expects.
+pub struct IntentBuilder<'k> {
+  recent_blockhash: Multihash,
+  expectations: Option<PredicateTree>,
+  calldata: Calldata,
+  signers: Vec<&'k Keypair>,
+}
+
+impl<'k> IntentBuilder<'k> {
+  pub fn new(recent_blockhash: Multihash) -> Self {
+    Self {
+      recent_blockhash,
+      expectations: None,
+      calldata: Calldata::new(),
+      signers: vec![],
+    }
+  }
+
+  /// Adds a predicate tree to the expectations of the intent.
+  pub fn expect(mut self, tree: PredicateTree) -> Self {
+    self.expectations = Some(match self.expectations {
+      Some(current) => PredicateTree::And(Box::new(current), Box::new(tree)),
+      None => tree,
+    });
+    self
+  }
+
+  /// Adds a single predicate to the expectations of the intent.
+  pub fn expect_predicate(self, code: Code, params: Vec<Param>) -> Self {
+    self.expect(PredicateTree::Id(Predicate { code, params }))
+  }
+
+  /// Expects a predicate to hold over the current and proposed state of
+  /// an account. The predicate gets the `AccountRef` and `ProposalRef`
+  /// of the account followed by the extra params.
+  pub fn expect_change(
+    self,
+    code: Code,
+    address: &Address,
+    extra: Vec<Param>,
+  ) -> Self {
+    let params = change_params(address).into_iter().chain(extra).collect();
+    self.expect_predicate(code, params)
+  }
+
+  pub fn calldata(mut self, key: impl Into<String>, value: Vec<u8>) -> Self {
+    self.calldata.insert(key.into(), value);
+    self
+  }
+
+  /// Signs the intent with the key when it is built.
+  pub fn sign(mut self, keypair: &'k Keypair) -> Self {
+    self.signers.push(keypair);
+    self
+  }
+
+  #[allow(clippy::result_large_err)]
+  pub fn build(self) -> Result<Intent, Error> {
+    let expectations = self.expectations.ok_or(Error::NoExpectations)?;
+    let mut intent =
+      Intent::with_calldata(self.recent_blockhash, expectations, self.calldata);
+
+    for signer in self.signers {
+      sign_intent(&mut intent, signer);
+    }
+
+    Ok(intent)
+  }
+}
+
+/// Builds a transaction on top of the current state and validates it.
+///
+/// The state is used to resolve current values of accounts whose state
+/// is updated, and together with the predicates cache to execute the
+/// transaction before it is returned.
+pub struct TransactionBuilder<'s> {
+  state: &'s dyn State,
+  codecache: &'s dyn State,
+  intents: Vec<Intent>,
+  proposals: BTreeMap<Address, AccountChange>,
+}
+
+impl<'s> TransactionBuilder<'s> {
+  pub fn new(state: &'s dyn State, codecache: &'s dyn State) -> Self {
+    Self {
+      state,
+      codecache,
+      intents: vec![],
+      proposals: BTreeMap::new(),
+    }
+  }
+
+  pub fn intent(mut self, intent: Intent) -> Self {
+    self.intents.push(intent);
+    self
+  }
+
+  pub fn create_account(mut self, address: Address, account: Account) -> Self {
+    self
+      .proposals
+      .insert(address, AccountChange::CreateAccount(account));
+    self
+  }
+
+  pub fn replace_state(mut self, address: Address, state: Vec<u8>) -> Self {
+    self
+      .proposals
+      .insert(address, AccountChange::ReplaceState(state));
+    self
+  }
+
+  pub fn replace_predicates(
+    mut self,
+    address: Address,
+    predicates: PredicateTree,
+  ) -> Self {
+    self
+      .proposals
+      .insert(address, AccountChange::ReplacePredicates(predicates));
+    self
+  }
+
+  pub fn delete_account(mut self, address: Address) -> Self {
+    self.proposals.insert(address, AccountChange::DeleteAccount);
+    self
+  }
+
+  /// Modifies the MessagePack encoded state of an account.
+  ///
+  /// The current value is the state proposed earlier by this transaction
+  /// if any, otherwise the state of the account. Accounts can't have their
+  /// state updated when their predicates are replaced or when they are
+  /// deleted by the same transaction. The modified value is
+  /// encoded with named fields.
+  #[allow(clippy::result_large_err)]
+  pub fn update_state<T>(
+    self,
+    address: &Address,
+    update: impl FnOnce(&mut T),
+  ) -> Result<Self, Error>
+  where
+    T: Serialize + DeserializeOwned,
+  {
+    let current = match self.proposals.get(address) {
+      Some(AccountChange::CreateAccount(account)) => account.state.clone(),
+      Some(AccountChange::ReplaceState(state)) => state.clone(),
+      Some(_) => return Err(Error::ConflictingChange(address.clone())),
+      None => {
+        self
+          .state
+          .get(address)
+          .ok_or_else(|| Error::AccountNotFound(address.clone()))?
+          .state
+      }
+    };
+
+    let mut value: T = rmp_serde::from_slice(&current)
+      .map_err(|e| Error::InvalidState(address.clone(), e))?;
+    update(&mut value);
+    let encoded = rmp_serde::to_vec_named(&value)?;
+
+    Ok(match self.proposals.get(address) {
+      Some(AccountChange::CreateAccount(account)) => {
+        let account = Account {
+          state: encoded,
+          predicates: account.predicates.clone(),
+        };
+        self.create_account(address.clone(), account)
+      }
+      _ => self.replace_state(address.clone(), encoded),
+    })
+  }
+
+  /// Executes the transaction in the context of the given block and
+  /// returns it only if it is accepted by all predicates.
+  #[allow(clippy::result_large_err)]
+  pub fn build(self, block: BlockContext) -> Result<Transaction, Error> {
+    let transaction = self.build_unchecked();
+    anoma_vm::execute(transaction.clone(), block, self.state, self.codecache)?;
+    Ok(transaction)
+  }
+
+  /// Returns the transaction without executing it.
+  pub fn build_unchecked(&self) -> Transaction {
+    Transaction::new(self.intents.clone(), self.proposals.clone())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::{Error, IntentBuilder, TransactionBuilder},
+    anoma_primitives::{
+      Account,
+      AccountChange,
+      Address,
+      BlockContext,
+      Code,
+      Intent,
+      Predicate,
+      PredicateTree,
+    },
+    anoma_vm::{InMemoryStateStore, RuntimeError, State, StateDiff},
+    ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature, Verifier},
+    multihash::{Code as HashCode, MultihashDigest},
+  };
+
+  fn keypair(seed: u8) -> Keypair {
+    let secret = SecretKey::from_bytes(&[seed; 32]).unwrap();
+    let public = PublicKey::from(&secret);
+    Keypair { secret, public }
+  }
+
+  fn stdpred(entrypoint: &str) -> Code {
+    Code::AccountRef("/stdpred/v1".parse().unwrap(), entrypoint.into())
+  }
+
+  #[test]
+  fn intent_signatures_cover_signing_hash() {
+    let alice = keypair(1);
+    let bob = keypair(2);
+    let address: Address = "/token/usdx/alice".parse().unwrap();
+
+    assert!(matches!(
+      IntentBuilder::new(HashCode::Sha3_256.digest(b"block"))
+        .sign(&alice)
+        .build(),
+      Err(Error::NoExpectations)
+    ));
+
+    let intent = IntentBuilder::new(HashCode::Sha3_256.digest(b"block"))
+      .expect_change(stdpred("uint_greater_than_by"), &address, vec![])
+      .expect_predicate(stdpred("require_ed25519_signature"), vec![])
+      .calldata("memo", b"hello".to_vec())
+      .sign(&alice)
+      .sign(&bob)
+      .build()
+      .unwrap();
+
+    assert!(matches!(intent.expectations, PredicateTree::And(_, _)));
+    assert_eq!(intent.calldata.len(), 3);
+
+    // signatures survive the trip over the wire and
+    // remain valid even though calldata is not signed
+    let intent: Intent =
+      rmp_serde::from_slice(&rmp_serde::to_vec(&intent).unwrap()).unwrap();
+    let message = intent.signing_hash().to_bytes();
+    for signer in [&alice, &bob] {
+      let key = bs58::encode(signer.public.as_bytes()).into_string();
+      let signature = Signature::from_bytes(&intent.calldata[&key]).unwrap();
+      assert!(signer.public.verify(&message, &signature).is_ok());
+    }
+  }
+
+  #[test]
+  fn update_state_resolves_current_values() {
+    let address: Address = "/token/usdx/alice".parse().unwrap();
+    let missing: Address = "/token/usdx/bob".parse().unwrap();
+    let mut diff = StateDiff::default();
+    diff.set(address.clone(), Account {
+      state: rmp_serde::to_vec(&100u64).unwrap(),
+      predicates: PredicateTree::Id(Predicate {
+        code: stdpred("uint_greater_than_by"),
+        params: vec![],
+      }),
+    });
+    let mut state = InMemoryStateStore::default();
+    state.apply(diff);
+    let codecache = InMemoryStateStore::default();
+
+    let tx = TransactionBuilder::new(&state, &codecache)
+      .update_state(&address, |balance: &mut u64| *balance -= 30)
+      .unwrap()
+      .update_state(&address, |balance: &mut u64| *balance -= 20)
+      .unwrap()
+      .build_unchecked();
+    assert_eq!(
+      tx.proposals.get(&address),
+      Some(&AccountChange::ReplaceState(
+        rmp_serde::to_vec(&50u64).unwrap()
+      ))
+    );
+
+    assert!(matches!(
+      TransactionBuilder::new(&state, &codecache)
+        .update_state(&missing, |balance: &mut u64| *balance += 1),
+      Err(Error::AccountNotFound(a)) if a == missing
+    ));
+
+    assert!(matches!(
+      TransactionBuilder::new(&state, &codecache)
+        .delete_account(address.clone())
+        .update_state(&address, |balance: &mut u64| *balance += 1),
+      Err(Error::ConflictingChange(a)) if a == address
+    ));
+
+    assert!(matches!(
+      TransactionBuilder::new(&state, &codecache)
+        .update_state(&address, |name: &mut String| name.push('!')),
+      Err(Error::InvalidState(a, _)) if a == address
+    ));
+
+    // the predicate code is not deployed so the vm rejects the transaction
+    assert!(matches!(
+      TransactionBuilder::new(&state, &codecache)
+        .update_state(&address, |balance: &mut u64| *balance -= 30)
+        .unwrap()
+        .build(BlockContext::default()),
+      Err(Error::Rejected(RuntimeError::State(_)))
+    ));
+  }
+}
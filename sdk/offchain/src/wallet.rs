@@ -0,0 +1,297 @@
+//! Hierarchical deterministic wallets of ed25519 keys.
+//!
+//! Keys are derived from a single seed following SLIP-0010, which only
+//! allows hardened derivation for ed25519. Public keys identify their
+//! owners on chain by their base58 encoding, both in addresses such as
+//! `/token/<token>/<wallet>` and in intent calldata, where signatures
+//! checked by `require_ed25519_signature` are looked up.
+
+use {
+  crate::token::stdpred,
+  anoma_primitives::{Address, Intent, Param, PredicateTree},
+  ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer},
+  hmac::{Hmac, Mac},
+  rand::RngCore,
+  sha2::Sha512,
+  std::{fmt::Display, str::FromStr},
+  thiserror::Error,
+  zeroize::Zeroize,
+};
+
+/// Offset of hardened indices in a derivation path.
+const HARDENED: u32 = 0x8000_0000;
+
+/// SLIP-0044 coin type reserved for testnets, used until the chain
+/// registers its own.
+const COIN_TYPE: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum Error {
+  #[error("Seed must be between 16 and 64 bytes long, got {0}")]
+  InvalidSeedLength(usize),
+
+  #[error("Invalid derivation path {0}, only hardened indices are allowed")]
+  InvalidPath(String),
+}
+
+/// A path of hardened indices from the wallet seed to a key,
+/// e.g. `m/44'/1'/0'/0'/0'`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+  /// The path of the key at a given index, `m/44'/1'/<index>'/0'/0'`.
+  pub fn account(index: u32) -> Result<Self, Error> {
+    if index >= HARDENED {
+      return Err(Error::InvalidPath(format!("m/44'/{COIN_TYPE}'/{index}'")));
+    }
+    Ok(Self(vec![44, COIN_TYPE, index, 0, 0]))
+  }
+}
+
+impl FromStr for DerivationPath {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = || Error::InvalidPath(s.into());
+    let mut segments = s.split('/');
+    if segments.next() != Some("m") {
+      return Err(invalid());
+    }
+
+    segments
+      .map(|segment| {
+        segment
+          .strip_suffix('\'')
+          .and_then(|index| index.parse::<u32>().ok())
+          .filter(|index| *index < HARDENED)
+          .ok_or_else(invalid)
+      })
+      .collect::<Result<_, _>>()
+      .map(Self)
+  }
+}
+
+impl Display for DerivationPath {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "m")?;
+    for index in &self.0 {
+      write!(f, "/{index}'")?;
+    }
+    Ok(())
+  }
+}
+
+/// A seed from which any number of keypairs can be derived.
+///
+/// The seed is wiped from memory when the wallet is dropped. To persist
+/// a wallet, store it encrypted in a [`crate::Keystore`].
+pub struct Wallet {
+  seed: Vec<u8>,
+}
+
+impl Wallet {
+  /// Creates a wallet from a new random 32 bytes seed.
+  pub fn generate() -> Self {
+    let mut seed = vec![0u8; 32];
+    rand::thread_rng().fill_bytes(&mut seed);
+    Self { seed }
+  }
+
+  pub fn from_seed(seed: &[u8]) -> Result<Self, Error> {
+    if !(16..=64).contains(&seed.len()) {
+      return Err(Error::InvalidSeedLength(seed.len()));
+    }
+    Ok(Self {
+      seed: seed.to_vec(),
+    })
+  }
+
+  pub fn seed(&self) -> &[u8] {
+    &self.seed
+  }
+
+  /// Derives the keypair at the given path.
+  pub fn derive(&self, path: &DerivationPath) -> Keypair {
+    let (mut key, mut chaincode) = hmac_sha512(b"ed25519 seed", &[&self.seed]);
+    for index in &path.0 {
+      let (child_key, child_chaincode) = hmac_sha512(&chaincode, &[
+        &[0],
+        &key,
+        &(index | HARDENED).to_be_bytes(),
+      ]);
+      key.zeroize();
+      key = child_key;
+      chaincode = child_chaincode;
+    }
+    chaincode.zeroize();
+
+    let secret = SecretKey::from_bytes(&key).expect("32 bytes secret key");
+    key.zeroize();
+    let public = PublicKey::from(&secret);
+    Keypair { secret, public }
+  }
+
+  /// Derives the keypair at the given index, see
+  /// [`DerivationPath::account`].
+  pub fn keypair(&self, index: u32) -> Result<Keypair, Error> {
+    Ok(self.derive(&DerivationPath::account(index)?))
+  }
+}
+
+impl Drop for Wallet {
+  fn drop(&mut self) {
+    self.seed.zeroize();
+  }
+}
+
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> ([u8; 32], [u8; 32]) {
+  let mut mac =
+    Hmac::<Sha512>::new_from_slice(key).expect("any key length is valid");
+  for chunk in data {
+    mac.update(chunk);
+  }
+  let output = mac.finalize().into_bytes();
+  let mut left = [0u8; 32];
+  let mut right = [0u8; 32];
+  left.copy_from_slice(&output[..32]);
+  right.copy_from_slice(&output[32..]);
+  (left, right)
+}
+
+/// Identifies the owner of a public key in addresses and calldata.
+pub fn wallet_id(owner: &PublicKey) -> String {
+  bs58::encode(owner.as_bytes()).into_string()
+}
+
+/// Address of the wallet of a key owner in a token,
+/// e.g. `/token/usdx/<wallet>`.
+pub fn token_wallet_address(token: &Address, owner: &PublicKey) -> Address {
+  token
+    .combine(wallet_id(owner))
+    .expect("base58 is a valid address segment")
+}
+
+/// Signs the signing hash of an intent and attaches the signature to its
+/// calldata, where `require_ed25519_signature` expects it.
+///
+/// Calldata is not covered by the signing hash, so multiple keys can sign
+/// the same intent in any order.
+pub fn sign_intent(intent: &mut Intent, keypair: &Keypair) {
+  let signature = keypair.sign(&intent.signing_hash().to_bytes());
+  intent
+    .calldata
+    .insert(wallet_id(&keypair.public), signature.to_bytes().to_vec());
+}
+
+/// A predicate that holds only if some intent in the transaction is
+/// signed by the key owner.
+pub fn require_signature(owner: &PublicKey) -> PredicateTree {
+  stdpred("require_ed25519_signature", vec![Param::Inline(
+    owner.as_bytes().to_vec(),
+  )])
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::{
+      sign_intent,
+      token_wallet_address,
+      wallet_id,
+      DerivationPath,
+      Error,
+      Wallet,
+    },
+    anoma_primitives::{Code, Intent, Predicate, PredicateTree},
+    ed25519_dalek::{Signature, Verifier},
+    multihash::{Code as HashCode, MultihashDigest},
+  };
+
+  fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+  }
+
+  #[test]
+  fn slip10_test_vector() {
+    // https://github.com/satoshilabs/slips/blob/master/slip-0010.md
+    // test vector 1 for ed25519
+    let seed: Vec<u8> = (0..16).collect();
+    let wallet = Wallet::from_seed(&seed).unwrap();
+
+    let master = wallet.derive(&"m".parse().unwrap());
+    assert_eq!(
+      hex(master.secret.as_bytes()),
+      "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+    );
+
+    let child = wallet.derive(&"m/0'".parse().unwrap());
+    assert_eq!(
+      hex(child.secret.as_bytes()),
+      "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"
+    );
+    assert_eq!(
+      hex(child.public.as_bytes()),
+      "8c8a13df77a28f3445213a0f432fde644acaa215fc72dcdf300d5efaa85d350c"
+    );
+
+    let path: DerivationPath = "m/0'/1'/2'/2'/1000000000'".parse().unwrap();
+    assert_eq!(path.to_string(), "m/0'/1'/2'/2'/1000000000'");
+    assert_eq!(
+      hex(wallet.derive(&path).secret.as_bytes()),
+      "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793"
+    );
+  }
+
+  #[test]
+  fn keypairs_and_addresses() {
+    assert!(matches!(
+      Wallet::from_seed(&[1; 8]),
+      Err(Error::InvalidSeedLength(8))
+    ));
+    for path in ["", "m/0", "m/1'/x'", "m/2147483648'", "0'/1'"] {
+      assert!(matches!(
+        path.parse::<DerivationPath>(),
+        Err(Error::InvalidPath(_))
+      ));
+    }
+
+    let wallet = Wallet::generate();
+    let first = wallet.keypair(0).unwrap();
+    let second = wallet.keypair(1).unwrap();
+    assert_ne!(first.public, second.public);
+    assert!(wallet.keypair(u32::MAX).is_err());
+
+    // the same seed always derives the same keys
+    let restored = Wallet::from_seed(wallet.seed()).unwrap();
+    assert_eq!(restored.keypair(1).unwrap().public, second.public);
+    assert_eq!(
+      wallet.derive(&"m/44'/1'/1'/0'/0'".parse().unwrap()).public,
+      second.public
+    );
+
+    let address =
+      token_wallet_address(&"/token/usdx".parse().unwrap(), &first.public);
+    assert_eq!(
+      address.to_string(),
+      format!("/token/usdx/{}", wallet_id(&first.public))
+    );
+
+    let mut intent = Intent::new(
+      HashCode::Sha3_256.digest(b"block"),
+      PredicateTree::Id(Predicate {
+        code: Code::Inline(b"some-code".to_vec()),
+        params: vec![],
+      }),
+    );
+    sign_intent(&mut intent, &first);
+    sign_intent(&mut intent, &second);
+
+    let message = intent.signing_hash().to_bytes();
+    for keypair in [&first, &second] {
+      let signature = &intent.calldata[&wallet_id(&keypair.public)];
+      let signature = Signature::from_bytes(signature).unwrap();
+      assert!(keypair.public.verify(&message, &signature).is_ok());
+    }
+  }
+}
@@ -1,15 +1,37 @@
 use {
-  crate::{builder, BlockStateBuilder},
+  crate::{builder, BlockRequest, BlockStateBuilder, Consumed},
   anoma_primitives::{Account, Address, Block, Transaction},
-  anoma_vm::State,
+  anoma_vm::{InMemoryStateStore, State},
   dashmap::DashMap,
-  futures::{Stream, StreamExt},
+  futures::{
+    channel::mpsc::{unbounded, UnboundedSender},
+    future::BoxFuture,
+    Future,
+    FutureExt,
+    Stream,
+    StreamExt,
+  },
   multihash::Multihash,
-  std::{num::NonZeroUsize, sync::Arc},
+  std::{
+    collections::HashSet,
+    num::NonZeroUsize,
+    sync::{
+      Arc,
+      Mutex,
+      PoisonError,
+      RwLock,
+      RwLockReadGuard,
+      RwLockWriteGuard,
+    },
+    time::Duration,
+  },
   thiserror::Error,
-  tokio::sync::{
-    oneshot::{self, error::RecvError, Sender},
-    RwLock,
+  tokio::{
+    sync::{
+      oneshot::{self, Receiver, Sender},
+      watch,
+    },
+    task::JoinHandle,
   },
   tracing::error,
 };
@@ -18,6 +40,12 @@ use {
 pub enum Error {
   #[error("The awaited block is already in distant history")]
   BlockInDistantHistory,
+
+  #[error("The blockchain watcher is no longer receiving blocks")]
+  Stopped,
+
+  #[error("Timed out after {0:?}")]
+  Timeout(Duration),
 }
 
 #[derive(Debug, Hash, PartialEq, Eq)]
@@ -25,33 +53,165 @@ enum WatchlistKey {
   Intent(Multihash),
   Transaction(Multihash),
   AccountChange(Address),
+  BlockHeight(u64),
 }
 
+#[derive(Clone)]
 enum WatchlistValue {
   Intent(Transaction),
   Transaction(Block),
   AccountChange(Transaction),
+  BlockHeight(Block),
 }
 
+/// All pending waiters of a key, each of them gets notified.
+type Watchlist = DashMap<WatchlistKey, Vec<Sender<WatchlistValue>>>;
+
+/// Long-lived account subscribers, notified on every change.
+type Subscriptions = DashMap<Address, Vec<UnboundedSender<Transaction>>>;
+
+/// Fetches blocks missed from gossip, see [`BlockchainWatcher::with_backfill`].
+type Backfill =
+  Arc<dyn Fn(BlockRequest) -> BoxFuture<'static, Vec<Block>> + Send + Sync>;
+
 /// This type monitors incoming blocks and accumulates state changes.
 /// It allows waiting for a specifc intent, transaction or an account change
 /// to be included in a block.
-pub struct BlockchainWatcher {
-  watchlist: Arc<DashMap<WatchlistKey, Sender<WatchlistValue>>>,
-  state_builder: Arc<RwLock<BlockStateBuilder<'static>>>,
+///
+/// Any number of callers may wait for the same key. They are all notified
+/// once it is observed.
+///
+/// Blocks are consumed by a background task that runs until the blocks
+/// stream ends or the watcher is stopped or dropped. Blocks are executed
+/// on a blocking thread, so they never stall the async runtime.
+/// Blocks may arrive out of order, those that can't be applied yet are
+/// kept aside until the blocks needed to fill the gaps are fetched with
+/// [`BlockchainWatcher::with_backfill`].
+///
+/// The watcher owns the accounts state and the code cache, and reading
+/// them never holds a lock across await points.
+pub struct BlockchainWatcher<S = InMemoryStateStore, C = InMemoryStateStore> {
+  watchlist: Arc<Watchlist>,
+  subscriptions: Arc<Subscriptions>,
+  orphans: Arc<Mutex<Vec<UnboundedSender<Transaction>>>>,
+  state_builder: Arc<RwLock<BlockStateBuilder<S, C>>>,
+  backfill: Arc<Mutex<Option<Backfill>>>,
+  timeout: Option<Duration>,
+  shutdown: watch::Sender<bool>,
+  worker: JoinHandle<()>,
+}
+
+/// Wakes up all waiters of a key. Waiters that have
+/// given up in the meantime are silently skipped.
+fn notify(watchlist: &Watchlist, key: &WatchlistKey, value: WatchlistValue) {
+  if let Some((_, waiters)) = watchlist.remove(key) {
+    for waiter in waiters {
+      let _ = waiter.send(value.clone());
+    }
+  }
+}
+
+/// Sends the transaction to all subscribers of an account
+/// and forgets subscribers that dropped their stream.
+fn publish(subscriptions: &Subscriptions, address: &Address, tx: &Transaction) {
+  if let Some(mut subscribers) = subscriptions.get_mut(address) {
+    subscribers.retain(|s| s.unbounded_send(tx.clone()).is_ok());
+  }
+  subscriptions.remove_if(address, |_, subscribers| subscribers.is_empty());
+}
+
+/// Notifies everyone interested in newly consumed blocks
+/// and in transactions of blocks they orphaned.
+fn signal(
+  watchlist: &Watchlist,
+  subscriptions: &Subscriptions,
+  orphans: &Mutex<Vec<UnboundedSender<Transaction>>>,
+  consumed: Consumed,
+) {
+  // transactions that made it into the new fork are not orphaned
+  let included: HashSet<_> = consumed
+    .applied
+    .iter()
+    .flat_map(|block| block.transactions.iter())
+    .map(|tx| *tx.hash())
+    .collect();
+  let mut orphans = orphans.lock().expect("poisoned");
+  for tx in consumed.orphaned.iter().flat_map(|b| b.transactions.iter()) {
+    if !included.contains(tx.hash()) {
+      orphans.retain(|s| s.unbounded_send(tx.clone()).is_ok());
+    }
+  }
+  drop(orphans);
+
+  for block in consumed.applied.iter() {
+    signal_block(watchlist, subscriptions, block);
+  }
 }
 
-impl BlockchainWatcher {
+fn signal_block(
+  watchlist: &Watchlist,
+  subscriptions: &Subscriptions,
+  block: &Block,
+) {
+  for tx in block.transactions.iter() {
+    notify(
+      watchlist,
+      &WatchlistKey::Transaction(*tx.hash()),
+      WatchlistValue::Transaction(block.clone()),
+    );
+
+    for intent in tx.intents.iter() {
+      notify(
+        watchlist,
+        &WatchlistKey::Intent(*intent.hash()),
+        WatchlistValue::Intent(tx.clone()),
+      );
+    }
+
+    for (address, _) in tx.proposals.iter() {
+      notify(
+        watchlist,
+        &WatchlistKey::AccountChange(address.clone()),
+        WatchlistValue::AccountChange(tx.clone()),
+      );
+      publish(subscriptions, address, tx);
+    }
+  }
+
+  notify(
+    watchlist,
+    &WatchlistKey::BlockHeight(block.height),
+    WatchlistValue::BlockHeight(block.clone()),
+  );
+}
+
+/// Lock poisoning only means that consuming a block panicked. Blocks are
+/// applied atomically to the state, so it is still consistent.
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+  lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+  lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<S, C> BlockchainWatcher<S, C>
+where
+  S: State + 'static,
+  C: State + 'static,
+{
   #[allow(clippy::result_large_err)]
   pub fn new(
     history_len: NonZeroUsize,
-    state: &'static mut dyn State,
-    codecache: &'static mut dyn State,
+    state: S,
+    codecache: C,
     recent: impl Iterator<Item = Block>,
     stream: impl Stream<Item = Block> + Unpin + Send + 'static,
   ) -> Result<Self, builder::Error> {
-    let watchlist =
-      Arc::new(DashMap::<WatchlistKey, Sender<WatchlistValue>>::new());
+    let watchlist = Arc::new(Watchlist::new());
+    let subscriptions = Arc::new(Subscriptions::new());
+    let orphans = Arc::new(Mutex::new(Vec::new()));
+    let backfill = Arc::new(Mutex::new(None::<Backfill>));
 
     let state_builder = Arc::new(RwLock::new(BlockStateBuilder::new(
       history_len,
@@ -61,135 +221,650 @@ impl BlockchainWatcher {
     )?));
 
     let watchlist_clone = watchlist.clone();
+    let subscriptions_clone = subscriptions.clone();
+    let orphans_clone = orphans.clone();
     let state_builder_clone = state_builder.clone();
+    let backfill_clone = backfill.clone();
+    let (shutdown, mut shutdown_rx) = watch::channel(false);
 
-    tokio::spawn(async move {
+    let worker = tokio::spawn(async move {
       let mut stream = stream;
       let watchlist = watchlist_clone;
+      let subscriptions = subscriptions_clone;
+      let orphans = orphans_clone;
       let state_builder = state_builder_clone;
-      while let Some(block) = stream.next().await {
-        for tx in block.transactions.iter() {
-          let txwatchkey = WatchlistKey::Transaction(*tx.hash());
-          if let Some((_, signal)) = watchlist.remove(&txwatchkey) {
-            if signal
-              .send(WatchlistValue::Transaction(block.clone()))
-              .is_err()
-            {
-              error!(
-                "Failed signalling awaited transaction {}",
-                bs58::encode(tx.hash().to_bytes()).into_string()
-              );
-            }
+      let backfill = backfill_clone;
+
+      // blocks fetched from peers to fill gaps, along with the request
+      // they answer. A request is sent again only after it is answered.
+      let (fetched_tx, mut fetched) = unbounded();
+      let mut inflight = HashSet::new();
+
+      loop {
+        let blocks = tokio::select! {
+          Ok(()) = shutdown_rx.changed() => break,
+          Some((request, blocks)) = fetched.next() => {
+            inflight.remove(&request);
+            blocks
           }
+          block = stream.next() => match block {
+            Some(block) => vec![block],
+            None => break,
+          },
+        };
 
-          for intent in tx.intents.iter() {
-            let intentwatchkey = WatchlistKey::Intent(*intent.hash());
-            if let Some((_, signal)) = watchlist.remove(&intentwatchkey) {
-              if signal.send(WatchlistValue::Intent(tx.clone())).is_err() {
-                error!(
-                  "Failed signalling awaited intent {}",
-                  bs58::encode(intent.hash().to_bytes()).into_string()
-                );
+        let watchlist = watchlist.clone();
+        let subscriptions = subscriptions.clone();
+        let orphans = orphans.clone();
+        let state_builder = state_builder.clone();
+        let missing = tokio::task::spawn_blocking(move || {
+          // waiters are signalled while still holding the write lock, so
+          // await methods never miss a block between checking the recent
+          // history and registering themselves on the watchlist.
+          let mut state_builder = write(&state_builder);
+          for block in blocks {
+            match state_builder.enqueue(block) {
+              Ok(consumed) => {
+                signal(&watchlist, &subscriptions, &orphans, consumed)
               }
+              Err(e) => error!("block rejected: {e:?}"),
             }
           }
+          state_builder.missing()
+        });
 
-          for account_change in tx.proposals.iter() {
-            let accwatchkey =
-              WatchlistKey::AccountChange(account_change.0.clone());
-            if let Some((_, signal)) = watchlist.remove(&accwatchkey) {
-              if signal
-                .send(WatchlistValue::AccountChange(tx.clone()))
-                .is_err()
-              {
-                error!(
-                  "Failed signalling awaited account change {}",
-                  account_change.0
-                );
-              }
-            }
+        let missing = match missing.await {
+          Ok(missing) => missing,
+          Err(e) => {
+            error!("consuming a block failed: {e:?}");
+            continue;
           }
-        }
+        };
 
-        if let Err(e) = state_builder.write().await.consume(block) {
-          error!("block rejected: {e:?}");
+        let Some(backfill) = backfill.lock().expect("poisoned").clone() else {
+          continue;
+        };
+        for request in missing {
+          if inflight.insert(request.clone()) {
+            let fetch = backfill(request.clone());
+            let fetched_tx = fetched_tx.clone();
+            tokio::spawn(async move {
+              let _ = fetched_tx.unbounded_send((request, fetch.await));
+            });
+          }
         }
       }
+
+      // no more blocks are coming, wake up everyone still waiting
+      // and end all subscription streams.
+      watchlist.clear();
+      subscriptions.clear();
+      orphans.lock().expect("poisoned").clear();
     });
 
     Ok(Self {
       watchlist,
+      subscriptions,
+      orphans,
       state_builder,
+      backfill,
+      timeout: None,
+      shutdown,
+      worker,
     })
   }
 
-  pub async fn get(&self, address: &Address) -> Option<Account> {
-    self.state_builder.read().await.get(address)
+  /// Makes all await methods of this watcher fail with [`Error::Timeout`]
+  /// if the awaited event is not observed within the given duration.
+  pub fn with_timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = Some(timeout);
+    self
   }
 
-  pub async fn most_recent_block(&self) -> Block {
-    self.state_builder.read().await.last().clone()
+  /// Fetches blocks that were missed from gossip from peers.
+  ///
+  /// Whenever blocks are kept aside because their parent is not known,
+  /// `fetch` is called with every request listed by
+  /// [`BlockStateBuilder::missing`] and the blocks it resolves to are
+  /// consumed like any other block. Failed requests should resolve to
+  /// no blocks, they are sent again once more blocks arrive.
+  pub fn with_backfill<F, Fut>(self, fetch: F) -> Self
+  where
+    F: Fn(BlockRequest) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Vec<Block>> + Send + 'static,
+  {
+    *self.backfill.lock().expect("poisoned") =
+      Some(Arc::new(move |request| fetch(request).boxed()));
+    self
+  }
+
+  pub fn get(&self, address: &Address) -> Option<Account> {
+    self.snapshot(|state| state.get(address))
+  }
+
+  pub fn most_recent_block(&self) -> Block {
+    self.snapshot(|state| state.last().clone())
+  }
+
+  /// Gives read access to the current state and recent blocks.
+  ///
+  /// Blocks are not consumed while the closure runs, so it should return
+  /// quickly, and it can't await anything.
+  pub fn snapshot<R>(
+    &self,
+    f: impl FnOnce(&BlockStateBuilder<S, C>) -> R,
+  ) -> R {
+    f(&read(&self.state_builder))
   }
 
+  /// Waits for a transaction that includes the intent.
+  ///
+  /// Resolves immediately if it is already in one of the recent blocks.
   pub async fn await_intent(
     &self,
     hash: Multihash,
-  ) -> Result<Transaction, RecvError> {
-    let key = WatchlistKey::Intent(hash);
-    let (tx, rx) = oneshot::channel();
-    self.watchlist.insert(key, tx);
-    rx.await.map(|v| match v {
-      WatchlistValue::Intent(tx) => tx,
-      WatchlistValue::Transaction(_) => {
-        panic!("bug in blockchain watcher. Incompatible signal type");
-      }
-      WatchlistValue::AccountChange(_) => {
-        panic!("bug in blockchain watcher. Incompatible signal type");
+  ) -> Result<Transaction, Error> {
+    let rx = {
+      let state_builder = read(&self.state_builder);
+      let included = state_builder
+        .recent()
+        .flat_map(|block| block.transactions.iter())
+        .find(|tx| tx.intents.iter().any(|intent| *intent.hash() == hash));
+      if let Some(tx) = included {
+        return Ok(tx.clone());
       }
+      self.register(WatchlistKey::Intent(hash))
+    };
+    self.wait(rx).await.map(|v| match v {
+      WatchlistValue::Intent(tx) => tx,
+      _ => panic!("bug in blockchain watcher. Incompatible signal type"),
     })
   }
 
+  /// Waits for a block that includes the transaction.
+  ///
+  /// Resolves immediately if it is already in one of the recent blocks.
   pub async fn await_transaction(
     &self,
     hash: Multihash,
-  ) -> Result<Block, RecvError> {
-    let key = WatchlistKey::Transaction(hash);
-    let (tx, rx) = oneshot::channel();
-    self.watchlist.insert(key, tx);
-    rx.await.map(|v| match v {
-      WatchlistValue::Intent(_) => {
-        panic!("bug in blockchain watcher. Incompatible signal type");
+  ) -> Result<Block, Error> {
+    let rx = {
+      let state_builder = read(&self.state_builder);
+      let included = state_builder
+        .recent()
+        .find(|block| block.transactions.iter().any(|tx| *tx.hash() == hash));
+      if let Some(block) = included {
+        return Ok(block.clone());
       }
+      self.register(WatchlistKey::Transaction(hash))
+    };
+    self.wait(rx).await.map(|v| match v {
       WatchlistValue::Transaction(block) => block,
-      WatchlistValue::AccountChange(_) => {
-        panic!("bug in blockchain watcher. Incompatible signal type");
-      }
+      _ => panic!("bug in blockchain watcher. Incompatible signal type"),
     })
   }
 
-  pub async fn await_account_change(
+  /// Waits for a block that includes the transaction and has at least
+  /// `confirmations` blocks built on top of it.
+  ///
+  /// If the block gets orphaned in the meantime, this keeps waiting until
+  /// the transaction is included and confirmed on the new fork. Blocks
+  /// older than the history of this watcher can't be rolled back anymore
+  /// and are considered final.
+  pub async fn await_confirmed_transaction(
     &self,
-    address: Address,
-  ) -> Result<Transaction, RecvError> {
-    let key = WatchlistKey::AccountChange(address);
-    let (tx, rx) = oneshot::channel();
-    self.watchlist.insert(key, tx);
-    rx.await.map(|v| match v {
-      WatchlistValue::Intent(_) => {
-        panic!("bug in blockchain watcher. Incompatible signal type");
+    hash: Multihash,
+    confirmations: u64,
+  ) -> Result<Block, Error> {
+    loop {
+      let block = self.await_transaction(hash).await?;
+      match self.await_block_height(block.height + confirmations).await {
+        Ok(_) | Err(Error::BlockInDistantHistory) => (),
+        Err(e) => return Err(e),
       }
-      WatchlistValue::Transaction(_) => {
-        panic!("bug in blockchain watcher. Incompatible signal type");
+
+      let canonical = self.snapshot(|state| {
+        state.recent().any(|b| b.hash() == block.hash())
+          || state.recent().all(|b| b.height > block.height)
+      });
+      if canonical {
+        return Ok(block);
       }
+    }
+  }
+
+  /// Waits for the next transaction that proposes a change to the account.
+  pub async fn await_account_change(
+    &self,
+    address: Address,
+  ) -> Result<Transaction, Error> {
+    let rx = self.register(WatchlistKey::AccountChange(address));
+    self.wait(rx).await.map(|v| match v {
       WatchlistValue::AccountChange(tx) => tx,
+      _ => panic!("bug in blockchain watcher. Incompatible signal type"),
     })
   }
 
+  /// Yields every transaction that proposes a change to the account from
+  /// now on. The stream ends when the watcher stops receiving blocks.
+  pub fn subscribe_account(
+    &self,
+    address: Address,
+  ) -> impl Stream<Item = Transaction> + Unpin + Send {
+    let (tx, rx) = unbounded();
+    self.subscriptions.entry(address).or_default().push(tx);
+    rx
+  }
+
+  /// Yields transactions of blocks that were rolled back because the chain
+  /// switched to a different fork, unless the new fork block includes them
+  /// as well. The stream ends when the watcher stops receiving blocks.
+  pub fn subscribe_orphaned(
+    &self,
+  ) -> impl Stream<Item = Transaction> + Unpin + Send {
+    let (tx, rx) = unbounded();
+    self.orphans.lock().expect("poisoned").push(tx);
+    rx
+  }
+
+  /// Waits until a block at the given height is consumed.
+  ///
+  /// Resolves immediately if the block is among the recent blocks kept
+  /// in history and fails if it is older than that.
   pub async fn await_block_height(&self, height: u64) -> Result<Block, Error> {
-    todo!();
+    let rx = {
+      let state_builder = read(&self.state_builder);
+      if height <= state_builder.last().height {
+        return state_builder
+          .recent()
+          .find(|block| block.height == height)
+          .cloned()
+          .ok_or(Error::BlockInDistantHistory);
+      }
+      self.register(WatchlistKey::BlockHeight(height))
+    };
+
+    self.wait(rx).await.map(|v| match v {
+      WatchlistValue::BlockHeight(block) => block,
+      _ => panic!("bug in blockchain watcher. Incompatible signal type"),
+    })
+  }
+
+  /// Stops consuming blocks and waits for the background task to finish.
+  ///
+  /// Pending waiters fail with [`Error::Stopped`] and subscription streams
+  /// end. Returns the state builder with all blocks consumed so far.
+  pub async fn stop(mut self) -> BlockStateBuilder<S, C> {
+    // fails only if the task has already exited on its own.
+    let _ = self.shutdown.send(true);
+    if let Err(e) = (&mut self.worker).await {
+      error!("blockchain watcher task failed: {e:?}");
+    }
+
+    // the task has exited and dropped its reference to the state builder
+    let state_builder = self.state_builder.clone();
+    drop(self);
+    match Arc::try_unwrap(state_builder) {
+      Ok(state_builder) => state_builder
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner),
+      Err(_) => unreachable!("the background task has finished"),
+    }
+  }
+
+  fn register(&self, key: WatchlistKey) -> Receiver<WatchlistValue> {
+    let (tx, rx) = oneshot::channel();
+    let mut waiters = self.watchlist.entry(key).or_default();
+    // drop waiters that timed out or were cancelled
+    waiters.retain(|waiter| !waiter.is_closed());
+    waiters.push(tx);
+    rx
+  }
+
+  async fn wait(
+    &self,
+    rx: Receiver<WatchlistValue>,
+  ) -> Result<WatchlistValue, Error> {
+    match self.timeout {
+      Some(timeout) => tokio::time::timeout(timeout, rx)
+        .await
+        .map_err(|_| Error::Timeout(timeout))?,
+      None => rx.await,
+    }
+    .map_err(|_| Error::Stopped)
+  }
+}
+
+impl<S, C> Drop for BlockchainWatcher<S, C> {
+  fn drop(&mut self) {
+    // the background task has nobody to report to anymore
+    let _ = self.shutdown.send(true);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::{BlockchainWatcher, Error},
+    crate::{BlockStateBuilder, InMemoryStateStore},
+    anoma_primitives::{
+      Account,
+      AccountChange,
+      Address,
+      Block,
+      Code,
+      Predicate,
+      PredicateTree,
+      Transaction,
+    },
+    futures::{
+      channel::mpsc::{unbounded, UnboundedSender},
+      future::ready,
+      StreamExt,
+    },
+    rmp_serde::{from_slice, to_vec},
+    std::{
+      num::NonZeroUsize,
+      sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+      },
+      time::Duration,
+    },
+  };
+
+  fn watcher(
+    history_len: usize,
+  ) -> (BlockchainWatcher, UnboundedSender<Block>) {
+    let (blocks, stream) = unbounded();
+    let watcher = BlockchainWatcher::new(
+      NonZeroUsize::new(history_len).unwrap(),
+      InMemoryStateStore::default(),
+      InMemoryStateStore::default(),
+      std::iter::once(Block::zero()),
+      stream,
+    )
+    .unwrap();
+    (watcher, blocks)
+  }
+
+  /// Produces `count` empty blocks on top of `parent`.
+  fn produce(parent: &Block, count: usize) -> Vec<Block> {
+    let mut blocks: Vec<Block> = vec![];
+    for _ in 0..count {
+      let parent = blocks.last().unwrap_or(parent);
+      blocks.push(Block::new(parent, parent.timestamp + 1, vec![]));
+    }
+    blocks
+  }
+
+  /// A transaction that creates an account with the given state.
+  fn create(address: &Address, state: u8) -> Transaction {
+    Transaction::new(
+      vec![],
+      [(
+        address.clone(),
+        AccountChange::CreateAccount(Account {
+          state: vec![state],
+          predicates: PredicateTree::Id(Predicate {
+            code: Code::Inline(vec![]),
+            params: vec![],
+          }),
+        }),
+      )]
+      .into(),
+    )
+  }
+
+  #[tokio::test]
+  async fn await_future_block_height() {
+    let (watcher, sender) = watcher(16);
+    let blocks = produce(&Block::zero(), 5);
+
+    let waiter = watcher.await_block_height(3);
+    let producer = async {
+      for block in blocks.iter().cloned() {
+        sender.unbounded_send(block).unwrap();
+        tokio::task::yield_now().await;
+      }
+    };
+
+    let (block, _) = futures::join!(waiter, producer);
+    assert_eq!(block.unwrap().hash(), blocks[2].hash());
+  }
+
+  #[tokio::test]
+  async fn await_past_block_height() {
+    let (watcher, sender) = watcher(2);
+    let blocks = produce(&Block::zero(), 5);
+    for block in blocks.iter().cloned() {
+      sender.unbounded_send(block).unwrap();
+    }
+
+    let last = watcher.await_block_height(5).await.unwrap();
+    assert_eq!(last.hash(), blocks[4].hash());
+    assert_eq!(watcher.most_recent_block().height, 5);
+
+    // still in recent history
+    let recent = watcher.await_block_height(4).await.unwrap();
+    assert_eq!(recent.hash(), blocks[3].hash());
+
+    // evicted from history
+    assert!(matches!(
+      watcher.await_block_height(1).await,
+      Err(Error::BlockInDistantHistory)
+    ));
+  }
+
+  #[tokio::test]
+  async fn stream_end_wakes_waiters() {
+    let (watcher, sender) = watcher(16);
+    for block in produce(&Block::zero(), 2) {
+      sender.unbounded_send(block).unwrap();
+    }
+    drop(sender);
+
+    assert!(matches!(
+      watcher.await_block_height(10).await,
+      Err(Error::Stopped)
+    ));
+    assert_eq!(watcher.most_recent_block().height, 2);
   }
 
-  pub async fn stop(self) {
-    todo!();
+  #[tokio::test]
+  async fn stop_while_stream_is_open() {
+    let (watcher, sender) = watcher(16);
+    let blocks = produce(&Block::zero(), 2);
+    sender.unbounded_send(blocks[0].clone()).unwrap();
+    watcher.await_block_height(1).await.unwrap();
+
+    // the consumed state is handed back
+    let state_builder = watcher.stop().await;
+    assert_eq!(state_builder.last().hash(), blocks[0].hash());
+
+    // the stream is dropped together with the background task
+    assert!(sender.unbounded_send(blocks[1].clone()).is_err());
+  }
+
+  #[tokio::test]
+  async fn backfill_missed_blocks() {
+    let blocks = produce(&Block::zero(), 4);
+    let mut peer = BlockStateBuilder::new(
+      NonZeroUsize::new(16).unwrap(),
+      InMemoryStateStore::default(),
+      InMemoryStateStore::default(),
+      std::iter::once(Block::zero()),
+    )
+    .unwrap();
+    for block in blocks.iter().cloned() {
+      peer.consume(block).unwrap();
+    }
+
+    // requests and responses go over the wire
+    let peer = Arc::new(peer);
+    let requests = Arc::new(AtomicUsize::new(0));
+    let (watcher, sender) = watcher(16);
+    let watcher = watcher.with_backfill({
+      let requests = requests.clone();
+      move |request| {
+        requests.fetch_add(1, Ordering::SeqCst);
+        let request = from_slice(&to_vec(&request).unwrap()).unwrap();
+        let response = to_vec(&peer.serve(&request)).unwrap();
+        ready(from_slice(&response).unwrap())
+      }
+    });
+
+    // blocks 1 and 2 are missed from gossip
+    sender.unbounded_send(blocks[2].clone()).unwrap();
+    sender.unbounded_send(blocks[3].clone()).unwrap();
+
+    let last = watcher.await_block_height(4).await.unwrap();
+    assert_eq!(last.hash(), blocks[3].hash());
+    assert_eq!(watcher.most_recent_block().hash(), blocks[3].hash());
+    assert!(watcher.snapshot(|state| state.missing().is_empty()));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+  }
+
+  #[tokio::test]
+  async fn drop_stops_the_worker() {
+    let (watcher, _sender) = watcher(16);
+    let changes = watcher.subscribe_account("/token/usdx".parse().unwrap());
+    drop(watcher);
+
+    // the stream is still open, but subscriptions end with the task
+    assert_eq!(changes.count().await, 0);
+  }
+
+  #[tokio::test]
+  async fn many_waiters_per_key() {
+    let (watcher, sender) = watcher(16);
+    let address: Address = "/token/usdx".parse().unwrap();
+    let tx = create(&address, 1);
+    let block = Block::new(&Block::zero(), 1, vec![tx.clone()]);
+
+    let waiters = futures::future::join4(
+      watcher.await_transaction(*tx.hash()),
+      watcher.await_transaction(*tx.hash()),
+      watcher.await_account_change(address.clone()),
+      watcher.await_account_change(address.clone()),
+    );
+    let producer = async {
+      tokio::task::yield_now().await;
+      sender.unbounded_send(block.clone()).unwrap();
+    };
+
+    let ((first, second, third, fourth), _) = futures::join!(waiters, producer);
+    assert_eq!(first.unwrap().hash(), block.hash());
+    assert_eq!(second.unwrap().hash(), block.hash());
+    assert_eq!(third.unwrap().hash(), tx.hash());
+    assert_eq!(fourth.unwrap().hash(), tx.hash());
+  }
+
+  #[tokio::test]
+  async fn await_with_timeout() {
+    let (watcher, sender) = watcher(16);
+    let watcher = watcher.with_timeout(Duration::from_millis(10));
+
+    assert!(matches!(
+      watcher.await_block_height(1).await,
+      Err(Error::Timeout(_))
+    ));
+
+    // timed out waiters don't affect later ones
+    sender
+      .unbounded_send(produce(&Block::zero(), 1).remove(0))
+      .unwrap();
+    assert_eq!(watcher.await_block_height(1).await.unwrap().height, 1);
+  }
+
+  #[tokio::test]
+  async fn subscribe_account_changes() {
+    let (watcher, sender) = watcher(16);
+    let address: Address = "/token/usdx".parse().unwrap();
+    let other: Address = "/token/eurx".parse().unwrap();
+    let changes = watcher.subscribe_account(address.clone());
+
+    let mut parent = Block::zero();
+    let mut expected = vec![];
+    for i in 0..3 {
+      let tx = create(&address, i);
+      expected.push(*tx.hash());
+      let block = Block::new(&parent, i.into(), vec![tx, create(&other, i)]);
+      sender.unbounded_send(block.clone()).unwrap();
+      parent = block;
+    }
+    drop(sender);
+
+    // the stream ends once the watcher stops receiving blocks
+    let observed: Vec<_> = changes.map(|tx| *tx.hash()).collect().await;
+    assert_eq!(observed, expected);
+  }
+
+  #[tokio::test]
+  async fn confirmations_survive_reorgs() {
+    let (watcher, sender) = watcher(16);
+    let address: Address = "/token/usdx".parse().unwrap();
+    let tx = create(&address, 1);
+    let zero = Block::zero();
+
+    let b1 = Block::new(&zero, 1, vec![tx.clone()]);
+    let b2 = Block::new(&b1, 2, vec![]);
+    sender.unbounded_send(b1.clone()).unwrap();
+    sender.unbounded_send(b2).unwrap();
+    assert_eq!(watcher.await_block_height(2).await.unwrap().height, 2);
+
+    let confirmed = || {
+      tokio::time::timeout(
+        Duration::from_millis(50),
+        watcher.await_confirmed_transaction(*tx.hash(), 2),
+      )
+    };
+    assert!(confirmed().await.is_err());
+    assert_eq!(
+      watcher.await_transaction(*tx.hash()).await.unwrap().hash(),
+      b1.hash()
+    );
+
+    // a fork at genesis without the transaction reaches the awaited height
+    let c1 = Block::new(&zero, 3, vec![]);
+    let c2 = Block::new(&c1, 4, vec![]);
+    let c3 = Block::new(&c2, 5, vec![]);
+    for block in [c1, c2, c3.clone()] {
+      sender.unbounded_send(block).unwrap();
+    }
+    assert!(confirmed().await.is_err());
+    assert_eq!(watcher.most_recent_block().hash(), c3.hash());
+
+    // the transaction is included again on the new fork
+    let c4 = Block::new(&c3, 6, vec![tx.clone()]);
+    let c5 = Block::new(&c4, 7, vec![]);
+    let c6 = Block::new(&c5, 8, vec![]);
+    for block in [c4.clone(), c5, c6] {
+      sender.unbounded_send(block).unwrap();
+    }
+    let block = confirmed().await.unwrap().unwrap();
+    assert_eq!(block.hash(), c4.hash());
+  }
+
+  #[tokio::test]
+  async fn orphaned_transactions() {
+    let (watcher, sender) = watcher(16);
+    let orphaned = watcher.subscribe_orphaned();
+    let usdx: Address = "/token/usdx".parse().unwrap();
+    let eurx: Address = "/token/eurx".parse().unwrap();
+    let (kept, dropped) = (create(&usdx, 1), create(&eurx, 1));
+    let zero = Block::zero();
+
+    let b1 = Block::new(&zero, 1, vec![kept.clone(), dropped.clone()]);
+    let c1 = Block::new(&zero, 2, vec![kept.clone()]);
+    let c2 = Block::new(&c1, 3, vec![]);
+    for block in [b1, c1, c2.clone()] {
+      sender.unbounded_send(block).unwrap();
+    }
+    drop(sender);
+
+    let orphaned: Vec<_> = orphaned.map(|tx| *tx.hash()).collect().await;
+    assert_eq!(orphaned, vec![*dropped.hash()]);
+    assert_eq!(watcher.most_recent_block().hash(), c2.hash());
   }
 }
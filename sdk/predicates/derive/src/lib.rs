@@ -1,123 +1,512 @@
 use {
   proc_macro::TokenStream,
-  quote::quote,
+  proc_macro2::{Literal, TokenStream as TokenStream2},
+  quote::{format_ident, quote},
   syn::{
     parse_macro_input,
-    parse_quote,
-    Abi,
+    spanned::Spanned,
+    Attribute,
+    Data,
+    DeriveInput,
+    Error,
+    Fields,
     FnArg,
     GenericArgument,
+    Ident,
     ItemFn,
+    Lit,
+    Meta,
+    MetaNameValue,
+    Pat,
     PathArguments,
     ReturnType,
     Type,
   },
 };
 
+/// Exports a function as a predicate entrypoint callable by the VM.
+///
+/// Predicate arguments can be either:
+///   1. `&PredicateContext`: the context of the evaluated transaction,
+///   2. `&Vec<ExpandedParam>`: all parameters undecoded, or
+///   3. any number of typed arguments, each decoded from the parameter at the
+///      same position. By default arguments are decoded from MessagePack,
+///      `#[param(raw)] arg: Vec<u8>` receives the raw parameter bytes and
+///      `#[param(expanded)] arg: ExpandedParam` receives the parameter itself.
+///
+/// Predicates return either `bool` or `Result<bool, E>` where `E: Display`.
+/// Parameters that fail to decode and returned errors are written to the
+/// debug log and reject the transaction.
 #[proc_macro_attribute]
 pub fn predicate(_: TokenStream, item: TokenStream) -> TokenStream {
-  let mut input_fn = parse_macro_input!(item as ItemFn);
-
-  if !verify_signature(&input_fn) {
-    panic!(
-      "Expecting predicates to be a function with the following signature: \
-       fn(&Vec<ExpandedParam>, &PredicateContext) -> bool"
-    );
+  let input_fn = parse_macro_input!(item as ItemFn);
+  match generate_entrypoint(input_fn) {
+    Ok(tokens) => TokenStream::from(tokens),
+    Err(e) => TokenStream::from(e.to_compile_error()),
   }
+}
 
-  decorate_entrypoint_abi(&mut input_fn);
+/// How a predicate function argument gets its value.
+enum Argument {
+  Context,
+  Params,
+  Typed {
+    name: String,
+    ty: Box<Type>,
+    encoding: Encoding,
+  },
+}
 
-  TokenStream::from(quote!(#input_fn))
+enum Encoding {
+  MessagePack,
+  Raw,
+  Expanded,
 }
 
-fn verify_signature(input_fn: &ItemFn) -> bool {
+impl Encoding {
+  fn decoder(&self) -> TokenStream2 {
+    match self {
+      Encoding::MessagePack => quote!(decode),
+      Encoding::Raw => quote!(raw),
+      Encoding::Expanded => quote!(expanded),
+    }
+  }
+
+  fn abi_name(&self) -> &'static str {
+    match self {
+      Encoding::MessagePack => "msgpack",
+      Encoding::Raw => "raw",
+      Encoding::Expanded => "expanded",
+    }
+  }
+}
+
+fn generate_entrypoint(mut input_fn: ItemFn) -> Result<TokenStream2, Error> {
   // those exported functions are implemented by the SDK and are
   // used by the VM to deliver data to predicates before invoking them.
   let reserved_names = ["__allocate", "__ingest_params", "__ingest_context"];
-  let name: String = input_fn.sig.ident.to_string();
-  if reserved_names.into_iter().any(|n| n == name) {
-    panic!("Predicate is using a reserved name: {name}");
+  let name = input_fn.sig.ident.clone();
+  if reserved_names.into_iter().any(|n| name == n) {
+    return Err(Error::new_spanned(
+      &name,
+      format!("Predicate is using a reserved name: {name}"),
+    ));
   }
 
-  let mut argiter = input_fn.sig.inputs.iter();
-  let first = argiter.next();
-  let second = argiter.next();
-  let ret = &input_fn.sig.output;
-
-  let args_ok = match (first, second, input_fn.sig.inputs.len()) {
-    (Some(FnArg::Typed(first)), Some(FnArg::Typed(second)), 2) => {
-      let mut args = (false, false);
-      if let Type::Reference(ref reftype) = *first.ty {
-        if reftype.mutability.is_none() {
-          if let Type::Path(ref vecpath) = *reftype.elem {
-            if let Some(ident) = vecpath.path.segments.last() {
-              if ident.ident == "Vec" {
-                if let PathArguments::AngleBracketed(ref generics) =
-                  ident.arguments
-                {
-                  if let Some(GenericArgument::Type(Type::Path(ty))) =
-                    generics.args.iter().next()
-                  {
-                    if let Some(seg) = ty.path.segments.last() {
-                      if seg.ident == "ExpandedParam" {
-                        args.0 = true;
-                      }
-                    }
-                  }
-                }
-              }
-            }
-          }
-        }
+  if let ReturnType::Default = input_fn.sig.output {
+    return Err(Error::new_spanned(
+      &input_fn.sig,
+      "Predicates must return either bool or Result<bool, E>",
+    ));
+  }
+
+  if !input_fn.sig.generics.params.is_empty() {
+    return Err(Error::new_spanned(
+      &input_fn.sig.generics,
+      "Predicates cannot be generic",
+    ));
+  }
+
+  let mut args = Vec::with_capacity(input_fn.sig.inputs.len());
+  for (index, input) in input_fn.sig.inputs.iter_mut().enumerate() {
+    let FnArg::Typed(input) = input else {
+      return Err(Error::new_spanned(input, "Predicates cannot be methods"));
+    };
+
+    let encoding = param_encoding(&input.attrs)?;
+    input.attrs.retain(|attr| !attr.path.is_ident("param"));
+
+    args.push(if is_reference_to(&input.ty, "PredicateContext") {
+      Argument::Context
+    } else if is_params_vec(&input.ty) {
+      Argument::Params
+    } else {
+      Argument::Typed {
+        name: match *input.pat {
+          Pat::Ident(ref pat) => pat.ident.to_string(),
+          _ => format!("_{index}"),
+        },
+        ty: input.ty.clone(),
+        encoding: encoding.unwrap_or(Encoding::MessagePack),
       }
-      if let Type::Reference(ref reftype) = *second.ty {
-        if reftype.mutability.is_none() {
-          if let Type::Path(ref path) = *reftype.elem {
-            if let Some(elem) = path.path.segments.last() {
-              if elem.ident == "PredicateContext" {
-                args.1 = true;
-              }
-            }
-          }
+    });
+  }
+
+  let typed: Vec<_> = args
+    .iter()
+    .filter_map(|arg| match arg {
+      Argument::Typed { name, ty, encoding } => {
+        Some((name, ty.as_ref(), encoding))
+      }
+      _ => None,
+    })
+    .collect();
+
+  let takes_params = args.iter().any(|arg| matches!(arg, Argument::Params));
+  if takes_params && !typed.is_empty() {
+    return Err(Error::new(
+      input_fn.sig.inputs.span(),
+      "Predicates can take either all params as &Vec<ExpandedParam> or typed \
+       arguments, but not both",
+    ));
+  }
+
+  // decode all typed arguments or bail out with the first decoding error
+  let count = typed.len();
+  let types = typed.iter().map(|(_, ty, _)| ty);
+  let decoders = typed.iter().enumerate().map(|(index, (name, _, enc))| {
+    let decoder = enc.decoder();
+    quote!(::anoma_predicates_sdk::params::#decoder(params, #index, #name)?)
+  });
+  let bindings: Vec<_> =
+    (0..count).map(|i| format_ident!("__arg{}", i)).collect();
+
+  // pass arguments to the predicate function in the order it declares them
+  let mut typed_bindings = bindings.iter();
+  let call_args = args.iter().map(|arg| match arg {
+    Argument::Context => quote!(context),
+    Argument::Params => quote!(params),
+    Argument::Typed { .. } => {
+      let binding = typed_bindings.next().expect("one per typed argument");
+      quote!(#binding)
+    }
+  });
+
+  let label = name.to_string();
+  let invocation = quote! {
+    ::anoma_predicates_sdk::PredicateOutput::verdict(
+      #name(#(#call_args),*),
+      #label,
+    )
+  };
+
+  let body = match takes_params {
+    true => invocation,
+    false => quote! {
+      fn __decode_params(
+        params: &[::anoma_predicates_sdk::ExpandedParam],
+      ) -> ::core::result::Result<
+        (#(#types,)*),
+        ::anoma_predicates_sdk::params::Error,
+      > {
+        ::anoma_predicates_sdk::params::expect_count(params, #count)?;
+        ::core::result::Result::Ok((#(#decoders,)*))
+      }
+
+      match __decode_params(params) {
+        ::core::result::Result::Ok((#(#bindings,)*)) => #invocation,
+        ::core::result::Result::Err(e) => {
+          ::anoma_predicates_sdk::reject(#label, e)
         }
       }
-      args.0 && args.1
+    },
+  };
+
+  // documentation goes on the exported entrypoint,
+  // everything else stays with the implementation.
+  let (docs, attrs): (Vec<_>, Vec<_>) = input_fn
+    .attrs
+    .drain(..)
+    .partition(|attr| attr.path.is_ident("doc"));
+  input_fn.attrs = attrs;
+
+  // describe the entrypoint in the ABI custom section of the WASM module
+  let abi = abi_entry(&label, &docs, (!takes_params).then_some(&typed[..]));
+  let abi_size = abi.len();
+  let abi = Literal::byte_string(abi.as_bytes());
+  let abi_static = format_ident!("__ANOMA_ABI_{}", label.to_uppercase());
+
+  Ok(quote! {
+    #[cfg(target_family = "wasm")]
+    #[used]
+    #[doc(hidden)]
+    #[link_section = "anoma_abi"]
+    static #abi_static: [u8; #abi_size] = *#abi;
+
+    #(#docs)*
+    #[no_mangle]
+    #[allow(clippy::ptr_arg)]
+    pub extern "C" fn #name(
+      params: &::anoma_predicates_sdk::__private::Vec<
+        ::anoma_predicates_sdk::ExpandedParam,
+      >,
+      context: &::anoma_predicates_sdk::PredicateContext,
+    ) -> bool {
+      #input_fn
+      #body
+    }
+  })
+}
+
+/// Describes a predicate entrypoint as one line of JSON.
+///
+/// Every predicate in a module contributes one line to the `anoma_abi`
+/// custom section, the linker concatenates them in the final WASM binary.
+/// Params are `null` for predicates that take all params undecoded.
+fn abi_entry(
+  name: &str,
+  docs: &[Attribute],
+  params: Option<&[(&String, &Type, &Encoding)]>,
+) -> String {
+  let docs: Vec<_> = docs
+    .iter()
+    .filter_map(|attr| match attr.parse_meta() {
+      Ok(Meta::NameValue(MetaNameValue {
+        lit: Lit::Str(doc), ..
+      })) => {
+        let line = doc.value();
+        Some(line.strip_prefix(' ').unwrap_or(&line).to_string())
+      }
+      _ => None,
+    })
+    .collect();
+
+  let params = match params {
+    None => "null".to_string(),
+    Some(params) => format!(
+      "[{}]",
+      params
+        .iter()
+        .map(|(name, ty, encoding)| {
+          format!(
+            r#"{{"name":{},"type":{},"encoding":"{}"}}"#,
+            json_string(name),
+            json_string(&type_descriptor(ty)),
+            encoding.abi_name()
+          )
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+    ),
+  };
+
+  format!(
+    "{{\"entrypoint\":{},\"docs\":{},\"params\":{params}}}\n",
+    json_string(name),
+    json_string(docs.join("\n").trim()),
+  )
+}
+
+/// Describes how serde sees a parameter type, e.g. `u64`, `string`,
+/// `seq<u8>` or `map<string,u64>`, so clients can check values they encode
+/// regardless of the paths and names of their own types.
+///
+/// Collections from std and alloc are described by their shape, other
+/// types by their name, which is what serde reports for structs and enums.
+fn type_descriptor(ty: &Type) -> String {
+  let args = |arguments: &PathArguments| -> Vec<String> {
+    match arguments {
+      PathArguments::AngleBracketed(generics) => generics
+        .args
+        .iter()
+        .filter_map(|arg| match arg {
+          GenericArgument::Type(ty) => Some(type_descriptor(ty)),
+          _ => None,
+        })
+        .collect(),
+      _ => vec![],
     }
-    _ => false,
   };
 
-  if !args_ok {
-    return false;
+  match ty {
+    Type::Reference(reference) => type_descriptor(&reference.elem),
+    Type::Paren(inner) => type_descriptor(&inner.elem),
+    Type::Group(inner) => type_descriptor(&inner.elem),
+    Type::Slice(slice) => format!("seq<{}>", type_descriptor(&slice.elem)),
+    Type::Array(array) => {
+      let elem = type_descriptor(&array.elem);
+      match &array.len {
+        syn::Expr::Lit(syn::ExprLit {
+          lit: Lit::Int(len), ..
+        }) => match len.base10_parse::<usize>() {
+          Ok(len) => format!("tuple<{}>", vec![elem; len].join(",")),
+          Err(_) => format!("seq<{elem}>"),
+        },
+        _ => format!("seq<{elem}>"),
+      }
+    }
+    Type::Tuple(tuple) if tuple.elems.is_empty() => "unit".into(),
+    Type::Tuple(tuple) => format!(
+      "tuple<{}>",
+      tuple
+        .elems
+        .iter()
+        .map(type_descriptor)
+        .collect::<Vec<_>>()
+        .join(",")
+    ),
+    Type::Path(path) => {
+      let Some(last) = path.path.segments.last() else {
+        return "_".into();
+      };
+      let name = last.ident.to_string();
+      let args = args(&last.arguments);
+      match (name.as_str(), &args[..]) {
+        ("String" | "str", _) => "string".into(),
+        // ed25519 public keys serialize as their compressed bytes and
+        // 256bit integers as their big-endian bytes, custom serialization
+        // is not visible to the macro so their shapes are spelled out.
+        ("PublicKey" | "U256", _) => "bytes".into(),
+        ("Box" | "Rc" | "Arc" | "Cow", [inner, ..]) => inner.clone(),
+        ("Option", [inner]) => format!("option<{inner}>"),
+        (
+          "Vec" | "VecDeque" | "LinkedList" | "BTreeSet" | "HashSet"
+          | "BinaryHeap",
+          [elem],
+        ) => format!("seq<{elem}>"),
+        ("BTreeMap" | "HashMap", [key, value, ..]) => {
+          format!("map<{key},{value}>")
+        }
+        _ => name,
+      }
+    }
+    other => quote!(#other).to_string().replace(' ', ""),
   }
+}
 
-  if let ReturnType::Type(_, ty) = ret {
-    if let Type::Path(ref path) = **ty {
-      if !path.path.is_ident("bool") {
-        return false;
+fn json_string(value: &str) -> String {
+  let mut output = String::with_capacity(value.len() + 2);
+  output.push('"');
+  for c in value.chars() {
+    match c {
+      '"' => output.push_str("\\\""),
+      '\\' => output.push_str("\\\\"),
+      '\n' => output.push_str("\\n"),
+      c if (c as u32) < 0x20 => {
+        output.push_str(&format!("\\u{:04x}", c as u32))
       }
+      c => output.push(c),
     }
   }
-
-  true
+  output.push('"');
+  output
 }
 
-/// Adds `no_mangle` attribute and pub extern "C"
-/// so the predicate is exported by WASM
-fn decorate_entrypoint_abi(input_fn: &mut ItemFn) {
-  input_fn.attrs.push(parse_quote! {
-    #[no_mangle]
-  });
-  input_fn.attrs.push(parse_quote! {
-    #[allow(clippy::ptr_arg)]
-  });
+/// Checks if a type is an immutable reference to a type with a given name.
+fn is_reference_to(ty: &Type, name: &str) -> bool {
+  if let Type::Reference(ref reftype) = *ty {
+    if reftype.mutability.is_none() {
+      if let Type::Path(ref path) = *reftype.elem {
+        if let Some(elem) = path.path.segments.last() {
+          return elem.ident == name;
+        }
+      }
+    }
+  }
+  false
+}
 
-  input_fn.sig.abi = Some(Abi {
-    extern_token: parse_quote!(extern),
-    name: None,
-  });
+/// Checks if a type is `&Vec<ExpandedParam>`
+fn is_params_vec(ty: &Type) -> bool {
+  if let Type::Reference(ref reftype) = *ty {
+    if reftype.mutability.is_none() {
+      if let Type::Path(ref vecpath) = *reftype.elem {
+        if let Some(ident) = vecpath.path.segments.last() {
+          if ident.ident == "Vec" {
+            if let PathArguments::AngleBracketed(ref generics) = ident.arguments
+            {
+              if let Some(GenericArgument::Type(Type::Path(ty))) =
+                generics.args.iter().next()
+              {
+                if let Some(seg) = ty.path.segments.last() {
+                  return seg.ident == "ExpandedParam";
+                }
+              }
+            }
+          }
+        }
+      }
+    }
+  }
+  false
 }
 
 #[proc_macro]
 pub fn initialize_library(_: TokenStream) -> TokenStream {
   TokenStream::from(quote! { extern crate alloc;})
 }
+
+/// Generates an implementation of `FromParams` for a struct where every
+/// field is decoded from the predicate parameter at the same position.
+#[proc_macro_derive(FromParams, attributes(param))]
+pub fn derive_from_params(item: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(item as DeriveInput);
+  match generate_from_params(input) {
+    Ok(tokens) => TokenStream::from(tokens),
+    Err(e) => TokenStream::from(e.to_compile_error()),
+  }
+}
+
+fn generate_from_params(input: DeriveInput) -> Result<TokenStream2, Error> {
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) =
+    input.generics.split_for_impl();
+
+  let fields = match input.data {
+    Data::Struct(ref data) => &data.fields,
+    _ => {
+      return Err(Error::new_spanned(
+        name,
+        "FromParams can only be derived for structs",
+      ))
+    }
+  };
+
+  let mut decoders = Vec::with_capacity(fields.len());
+  for (index, field) in fields.iter().enumerate() {
+    let label = match field.ident {
+      Some(ref ident) => ident.to_string(),
+      None => index.to_string(),
+    };
+    let decoder = param_encoding(&field.attrs)?
+      .unwrap_or(Encoding::MessagePack)
+      .decoder();
+    decoders.push(quote! {
+      ::anoma_predicates_sdk::params::#decoder(params, #index, #label)?
+    });
+  }
+
+  let count = fields.len();
+  let constructor = match fields {
+    Fields::Named(named) => {
+      let idents = named.named.iter().map(|f| &f.ident);
+      quote!(Self { #(#idents: #decoders),* })
+    }
+    Fields::Unnamed(_) => quote!(Self(#(#decoders),*)),
+    Fields::Unit => quote!(Self),
+  };
+
+  Ok(quote! {
+    impl #impl_generics ::anoma_predicates_sdk::params::FromParams
+      for #name #ty_generics #where_clause
+    {
+      fn from_params(
+        params: &[::anoma_predicates_sdk::ExpandedParam],
+      ) -> ::core::result::Result<
+        Self,
+        ::anoma_predicates_sdk::params::Error,
+      > {
+        ::anoma_predicates_sdk::params::expect_count(params, #count)?;
+        ::core::result::Result::Ok(#constructor)
+      }
+    }
+  })
+}
+
+/// Reads the encoding specified by the `#[param(...)]` attribute.
+fn param_encoding(attrs: &[Attribute]) -> Result<Option<Encoding>, Error> {
+  let Some(attr) = attrs.iter().find(|attr| attr.path.is_ident("param")) else {
+    return Ok(None);
+  };
+
+  let encoding = attr.parse_args::<Ident>()?;
+  match encoding.to_string().as_str() {
+    "raw" => Ok(Some(Encoding::Raw)),
+    "expanded" => Ok(Some(Encoding::Expanded)),
+    _ => Err(Error::new_spanned(
+      encoding,
+      "Unsupported parameter encoding. Expecting #[param(raw)] or \
+       #[param(expanded)]",
+    )),
+  }
+}
@@ -1,5 +1,3 @@
-extern crate alloc;
-
 use {
   alloc::{boxed::Box, vec::Vec},
   anoma_primitives::{ExpandedParam, PredicateContext},
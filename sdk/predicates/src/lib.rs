@@ -1,35 +1,66 @@
 #![cfg_attr(target_family = "wasm", no_std)]
 
+extern crate alloc;
+
+#[cfg(target_family = "wasm")]
 mod builtins;
+mod output;
+pub mod params;
+
+#[cfg(not(target_family = "wasm"))]
+pub mod testing;
 
+#[cfg(target_family = "wasm")]
 extern "C" {
   pub fn syscall_debug_log(ptr: *const u8, len: u32);
 }
 
 pub use {
-  anoma_predicates_sdk_macros::{initialize_library, predicate},
+  anoma_predicates_sdk_macros::{initialize_library, predicate, FromParams},
   anoma_primitives::{
+    Account,
     Address,
+    Basic,
+    BlockContext,
+    Code,
     Expanded,
     ExpandedAccountChange,
     ExpandedParam,
+    Param,
     Predicate,
     PredicateContext,
+    PredicateTree,
   },
+  output::{reject, PredicateOutput},
+  params::FromParams,
 };
 
+/// Used by code generated by the SDK macros.
+#[doc(hidden)]
+pub mod __private {
+  pub use alloc::vec::Vec;
+}
+
 #[cfg(not(target_family = "wasm"))]
 mod build;
 
 #[cfg(not(target_family = "wasm"))]
 pub use build::configure_build;
 
+#[cfg(target_family = "wasm")]
 pub fn debug_log(msg: &str) {
   let serialized = rmp_serde::to_vec(msg).unwrap();
   let ptr = serialized.as_ptr();
   unsafe { syscall_debug_log(ptr, serialized.len() as u32) };
 }
 
+/// Outside of the VM, when predicates are called directly from native
+/// unit tests, debug logs are printed to the test output.
+#[cfg(not(target_family = "wasm"))]
+pub fn debug_log(msg: &str) {
+  println!("{msg}");
+}
+
 #[macro_export]
 macro_rules! log {
     ($($arg:tt)*) => {
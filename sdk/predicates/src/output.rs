@@ -0,0 +1,32 @@
+use {crate::log, core::fmt::Display};
+
+/// Implemented by all types that predicate functions can return.
+///
+/// Predicates either return a plain `bool`, or a `Result<bool, E>`
+/// where errors are treated as a rejection of the transaction.
+pub trait PredicateOutput {
+  /// Converts the predicate output into the value returned to the VM.
+  fn verdict(self, predicate: &str) -> bool;
+}
+
+impl PredicateOutput for bool {
+  fn verdict(self, _: &str) -> bool {
+    self
+  }
+}
+
+impl<E: Display> PredicateOutput for Result<bool, E> {
+  fn verdict(self, predicate: &str) -> bool {
+    match self {
+      Ok(verdict) => verdict,
+      Err(e) => reject(predicate, e),
+    }
+  }
+}
+
+/// Writes the reason for rejecting a transaction to the
+/// debug log and returns false.
+pub fn reject(predicate: &str, reason: impl Display) -> bool {
+  log!("{predicate} rejected: {reason}");
+  false
+}
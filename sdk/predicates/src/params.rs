@@ -0,0 +1,137 @@
+//! Typed decoding of predicate parameters.
+//!
+//! By the time a predicate is invoked all its parameters are resolved
+//! to raw bytes. Types implementing [`FromParams`] describe the expected
+//! parameters list of a predicate, so instead of panicking on malformed
+//! input, predicates can report what went wrong and reject the
+//! transaction.
+
+use {
+  crate::log,
+  alloc::{
+    string::{String, ToString},
+    vec::Vec,
+  },
+  anoma_primitives::ExpandedParam,
+  core::fmt::{self, Display},
+  serde::de::DeserializeOwned,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+  /// The predicate was invoked with a different number of parameters
+  /// than it expects.
+  Count { expected: usize, actual: usize },
+
+  /// A parameter value could not be decoded into its expected type.
+  Decode {
+    index: usize,
+    name: &'static str,
+    reason: String,
+  },
+}
+
+impl Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Error::Count { expected, actual } => {
+        write!(f, "expected {expected} parameters, got {actual}")
+      }
+      Error::Decode {
+        index,
+        name,
+        reason,
+      } => {
+        write!(f, "invalid parameter '{name}' at index {index}: {reason}")
+      }
+    }
+  }
+}
+
+/// Implemented by types that can be decoded from the list of parameters
+/// a predicate was invoked with.
+///
+/// Usually this trait is derived using `#[derive(FromParams)]` on a struct
+/// where every field corresponds to a parameter at the same position.
+/// By default fields are decoded from MessagePack, fields annotated with
+/// `#[param(raw)]` receive the raw parameter bytes as `Vec<u8>` and fields
+/// annotated with `#[param(expanded)]` receive the `ExpandedParam` itself.
+pub trait FromParams: Sized {
+  fn from_params(params: &[ExpandedParam]) -> Result<Self, Error>;
+}
+
+/// Decodes predicate parameters into a typed value.
+///
+/// On failure the reason is written to the debug log and `None` is
+/// returned, so predicates can reject the transaction instead of
+/// trapping.
+pub fn parse<T: FromParams>(params: &[ExpandedParam]) -> Option<T> {
+  match T::from_params(params) {
+    Ok(value) => Some(value),
+    Err(e) => {
+      log!("failed to decode {}: {e}", core::any::type_name::<T>());
+      None
+    }
+  }
+}
+
+/// Verifies that the predicate received exactly the expected number
+/// of parameters.
+pub fn expect_count(
+  params: &[ExpandedParam],
+  expected: usize,
+) -> Result<(), Error> {
+  match params.len() == expected {
+    true => Ok(()),
+    false => Err(Error::Count {
+      expected,
+      actual: params.len(),
+    }),
+  }
+}
+
+/// Decodes a MessagePack encoded parameter at a given position.
+pub fn decode<T: DeserializeOwned>(
+  params: &[ExpandedParam],
+  index: usize,
+  name: &'static str,
+) -> Result<T, Error> {
+  rmp_serde::from_slice(param(params, index, name)?.data()).map_err(|e| {
+    Error::Decode {
+      index,
+      name,
+      reason: e.to_string(),
+    }
+  })
+}
+
+/// Returns the undecoded bytes of a parameter at a given position.
+pub fn raw(
+  params: &[ExpandedParam],
+  index: usize,
+  name: &'static str,
+) -> Result<Vec<u8>, Error> {
+  Ok(param(params, index, name)?.data().to_vec())
+}
+
+/// Returns the parameter at a given position along with the information
+/// about how it was resolved (inline, account, proposal or calldata).
+pub fn expanded(
+  params: &[ExpandedParam],
+  index: usize,
+  name: &'static str,
+) -> Result<ExpandedParam, Error> {
+  param(params, index, name).cloned()
+}
+
+fn param<'p>(
+  params: &'p [ExpandedParam],
+  index: usize,
+  name: &'static str,
+) -> Result<&'p ExpandedParam, Error> {
+  params.get(index).ok_or(Error::Decode {
+    index,
+    name,
+    reason: "missing parameter".to_string(),
+  })
+}
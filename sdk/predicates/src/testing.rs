@@ -0,0 +1,202 @@
+//! Host-side testing utilities for predicates.
+//!
+//! Predicates exported with `#[predicate]` are regular rust functions that
+//! can be called directly from native unit tests, without compiling them to
+//! WASM and running them through the VM. This module helps constructing
+//! their inputs: the parameters list and the transaction context with
+//! account proposals, calldata, signatures and block information.
+//!
+//! ```ignore
+//! let params = ParamsBuilder::new().inline(&5u64).inline(&5u64).build();
+//! let context = ContextBuilder::new().build();
+//! assert!(uint_equal(&params, &context));
+//! ```
+//!
+//! Messages written by predicates through `log!` are printed to stdout and
+//! show up in the test output.
+
+use {
+  anoma_primitives::{
+    Account,
+    Address,
+    BlockContext,
+    ExpandedAccountChange,
+    ExpandedParam,
+    PredicateContext,
+    PredicateTree,
+  },
+  ed25519_dalek::{Keypair, Signer},
+  multihash::{Multihash, MultihashDigest},
+  serde::Serialize,
+};
+
+/// Creates a deterministic intent hash for use in mock contexts.
+///
+/// In a real transaction calldata is grouped by the signing hash of the
+/// intent that carries it, and signatures are produced over that hash.
+pub fn intent_hash(seed: &[u8]) -> Multihash {
+  multihash::Code::Sha3_256.digest(seed)
+}
+
+/// Builds the list of parameters a predicate is invoked with, the way
+/// the VM would after resolving all references.
+#[derive(Debug, Default)]
+pub struct ParamsBuilder {
+  params: Vec<ExpandedParam>,
+}
+
+impl ParamsBuilder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Appends an inline MessagePack encoded value.
+  pub fn inline<T: Serialize + ?Sized>(self, value: &T) -> Self {
+    self.raw(encode(value))
+  }
+
+  /// Appends inline bytes as they are.
+  pub fn raw(mut self, bytes: impl Into<Vec<u8>>) -> Self {
+    self.params.push(ExpandedParam::Inline(bytes.into()));
+    self
+  }
+
+  /// Appends a value resolved from the current state of an account.
+  pub fn account<T: Serialize + ?Sized>(
+    mut self,
+    address: Address,
+    state: &T,
+  ) -> Self {
+    self
+      .params
+      .push(ExpandedParam::AccountRef(address, encode(state)));
+    self
+  }
+
+  /// Appends a value resolved from a proposed change to an account.
+  pub fn proposal(
+    mut self,
+    address: Address,
+    change: ExpandedAccountChange,
+  ) -> Self {
+    self
+      .params
+      .push(ExpandedParam::ProposalRef(address, change));
+    self
+  }
+
+  /// Appends a value resolved from intent calldata.
+  pub fn calldata(
+    mut self,
+    key: impl Into<String>,
+    value: impl Into<Vec<u8>>,
+  ) -> Self {
+    self
+      .params
+      .push(ExpandedParam::CalldataRef(key.into(), value.into()));
+    self
+  }
+
+  pub fn build(self) -> Vec<ExpandedParam> {
+    self.params
+  }
+}
+
+/// Builds a mock transaction context for predicates.
+#[derive(Debug, Default)]
+pub struct ContextBuilder {
+  context: PredicateContext,
+}
+
+impl ContextBuilder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Adds an arbitrary proposed change to an account.
+  pub fn proposal(
+    mut self,
+    address: Address,
+    change: ExpandedAccountChange,
+  ) -> Self {
+    self.context.proposals.insert(address, change);
+    self
+  }
+
+  /// Proposes a new account with the given state and predicates.
+  pub fn create_account<T: Serialize + ?Sized>(
+    self,
+    address: Address,
+    state: &T,
+    predicates: PredicateTree,
+  ) -> Self {
+    self.proposal(
+      address,
+      ExpandedAccountChange::CreateAccount(Account {
+        state: encode(state),
+        predicates,
+      }),
+    )
+  }
+
+  /// Proposes a change of state of an existing account.
+  pub fn replace_state<T: Serialize + ?Sized>(
+    self,
+    address: Address,
+    current: &T,
+    proposed: &T,
+  ) -> Self {
+    self.proposal(address, ExpandedAccountChange::ReplaceState {
+      current: encode(current),
+      proposed: encode(proposed),
+    })
+  }
+
+  /// Proposes the removal of an existing account.
+  pub fn delete_account(self, address: Address, current: Account) -> Self {
+    self.proposal(address, ExpandedAccountChange::DeleteAccount { current })
+  }
+
+  /// Attaches a calldata entry to an intent.
+  pub fn calldata(
+    mut self,
+    intent: Multihash,
+    key: impl Into<String>,
+    value: impl Into<Vec<u8>>,
+  ) -> Self {
+    self
+      .context
+      .calldata
+      .entry(intent)
+      .or_default()
+      .insert(key.into(), value.into());
+    self
+  }
+
+  /// Signs an intent with an ed25519 keypair and attaches the signature
+  /// to its calldata under the base58 encoded public key, the same way
+  /// wallets authorize intents.
+  pub fn signature(self, intent: Multihash, keypair: &Keypair) -> Self {
+    let signature = keypair.sign(&intent.to_bytes());
+    self.calldata(
+      intent,
+      bs58::encode(keypair.public.as_bytes()).into_string(),
+      signature.to_bytes(),
+    )
+  }
+
+  /// Sets the height and timestamp of the block in which the transaction
+  /// is executed.
+  pub fn block(mut self, height: u64, timestamp: u64) -> Self {
+    self.context.block = BlockContext { height, timestamp };
+    self
+  }
+
+  pub fn build(self) -> PredicateContext {
+    self.context
+  }
+}
+
+fn encode<T: Serialize + ?Sized>(value: &T) -> Vec<u8> {
+  rmp_serde::to_vec(value).expect("test values are always serializable")
+}
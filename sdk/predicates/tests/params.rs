@@ -0,0 +1,101 @@
+use anoma_predicates_sdk::{
+  params::{self, Error},
+  testing::ParamsBuilder,
+  Address,
+  ExpandedParam,
+  FromParams,
+};
+
+#[derive(Debug, PartialEq, FromParams)]
+struct Transfer {
+  amount: u64,
+  recipient: Address,
+  #[param(raw)]
+  memo: Vec<u8>,
+  #[param(expanded)]
+  source: ExpandedParam,
+}
+
+#[derive(Debug, PartialEq, FromParams)]
+struct Pair(u64, String);
+
+#[derive(Debug, PartialEq, FromParams)]
+struct Nothing;
+
+fn transfer_params() -> ParamsBuilder {
+  ParamsBuilder::new()
+    .inline(&100u64)
+    .inline(&"/token/usdx/bob".parse::<Address>().unwrap())
+    .raw(b"rent".to_vec())
+    .account("/token/usdx/alice".parse().unwrap(), &500u64)
+}
+
+#[test]
+fn decode_params_by_position() {
+  let params = transfer_params().build();
+  assert_eq!(
+    params::parse::<Transfer>(&params),
+    Some(Transfer {
+      amount: 100,
+      recipient: "/token/usdx/bob".parse().unwrap(),
+      memo: b"rent".to_vec(),
+      source: params[3].clone(),
+    })
+  );
+
+  let params = ParamsBuilder::new().inline(&7u64).inline("seven").build();
+  assert_eq!(Pair::from_params(&params), Ok(Pair(7, "seven".into())));
+  assert_eq!(Nothing::from_params(&[]), Ok(Nothing));
+}
+
+#[test]
+fn reject_missing_and_extra_params() {
+  let params = transfer_params().build();
+  assert_eq!(
+    Transfer::from_params(&params[..3]),
+    Err(Error::Count {
+      expected: 4,
+      actual: 3
+    })
+  );
+
+  let params = transfer_params().inline(&1u8).build();
+  assert_eq!(
+    Transfer::from_params(&params),
+    Err(Error::Count {
+      expected: 4,
+      actual: 5
+    })
+  );
+  assert_eq!(params::parse::<Transfer>(&params), None);
+}
+
+#[test]
+fn report_decode_errors() {
+  let params = ParamsBuilder::new().inline("seven").inline("7").build();
+  assert!(matches!(
+    Pair::from_params(&params),
+    Err(Error::Decode {
+      index: 0,
+      name: "0",
+      ..
+    })
+  ));
+
+  // raw fields take any bytes, other fields must decode
+  let params = ParamsBuilder::new()
+    .inline(&100u64)
+    .raw(vec![0xc1])
+    .raw(vec![0xc1])
+    .inline(&0u8)
+    .build();
+  assert!(matches!(
+    Transfer::from_params(&params),
+    Err(Error::Decode {
+      index: 1,
+      name: "recipient",
+      ..
+    })
+  ));
+  assert_eq!(params::parse::<Transfer>(&params), None);
+}
@@ -0,0 +1,323 @@
+//! Range, ratio and slippage predicates.
+//!
+//! Intents that behave like limit orders rarely know the exact amounts they
+//! will end up with. These predicates express bounds on values, deltas and
+//! exchange rates instead of exact equalities.
+//!
+//! Ratios are compared by cross multiplication in a wider integer type, so
+//! there is no rounding and no overflow.
+
+use {
+  anoma_predicates_sdk::predicate,
+  core::fmt::{self, Display},
+  primitive_types::U256,
+};
+
+/// Basis points in 100%.
+const BPS: u64 = 10_000;
+
+#[derive(Debug)]
+enum BoundsError {
+  ZeroDenominator,
+  InvalidBasisPoints(u64),
+}
+
+impl Display for BoundsError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      BoundsError::ZeroDenominator => write!(f, "zero denominator"),
+      BoundsError::InvalidBasisPoints(bps) => {
+        write!(f, "{bps} basis points is more than 100%")
+      }
+    }
+  }
+}
+
+fn nonzero<T: Default + PartialEq>(value: T) -> Result<T, BoundsError> {
+  match value == T::default() {
+    true => Err(BoundsError::ZeroDenominator),
+    false => Ok(value),
+  }
+}
+
+fn basis_points(bps: u64) -> Result<u64, BoundsError> {
+  match bps <= BPS {
+    true => Ok(bps),
+    false => Err(BoundsError::InvalidBasisPoints(bps)),
+  }
+}
+
+/// Takes three 64bit unsigned int arguments and verifies that the first one
+/// is within the inclusive range [second, third].
+#[predicate]
+fn uint_in_range(value: u64, min: u64, max: u64) -> bool {
+  min <= value && value <= max
+}
+
+/// Takes four 64bit unsigned int arguments a, b, c, d and verifies that
+/// a/b >= c/d. Rejects if any of the denominators is zero.
+#[predicate]
+fn uint_ratio_at_least(
+  a: u64,
+  b: u64,
+  c: u64,
+  d: u64,
+) -> Result<bool, BoundsError> {
+  let (b, d) = (nonzero(b)?, nonzero(d)?);
+  Ok(a as u128 * d as u128 >= c as u128 * b as u128)
+}
+
+/// Takes four 64bit unsigned int arguments a, b, c, d and verifies that
+/// a/b <= c/d. Rejects if any of the denominators is zero.
+#[predicate]
+fn uint_ratio_at_most(
+  a: u64,
+  b: u64,
+  c: u64,
+  d: u64,
+) -> Result<bool, BoundsError> {
+  let (b, d) = (nonzero(b)?, nonzero(d)?);
+  Ok(a as u128 * d as u128 <= c as u128 * b as u128)
+}
+
+/// Takes three 64bit unsigned int arguments: the actual amount, the quoted
+/// amount and the maximum slippage in basis points, and verifies that the
+/// actual amount is at least the quoted amount minus the slippage. For
+/// example a slippage of 50 accepts anything above 99.5% of the quote.
+#[predicate]
+fn uint_within_slippage(
+  actual: u64,
+  quoted: u64,
+  slippage_bps: u64,
+) -> Result<bool, BoundsError> {
+  let slippage_bps = basis_points(slippage_bps)?;
+  Ok(
+    actual as u128 * BPS as u128
+      >= quoted as u128 * (BPS - slippage_bps) as u128,
+  )
+}
+
+/// Takes three 64bit unsigned int arguments and verifies that the first one
+/// is greater than the second one by at least the third one.
+#[predicate]
+fn uint_greater_than_by_at_least(first: u64, second: u64, min: u64) -> bool {
+  matches!(first.checked_sub(second), Some(delta) if delta >= min)
+}
+
+/// Takes three 64bit unsigned int arguments and verifies that the first one
+/// is greater than or equal to the second one by at most the third one.
+#[predicate]
+fn uint_greater_than_by_at_most(first: u64, second: u64, max: u64) -> bool {
+  matches!(first.checked_sub(second), Some(delta) if delta <= max)
+}
+
+/// Takes three 64bit unsigned int arguments and verifies that the first one
+/// is less than the second one by at least the third one.
+#[predicate]
+fn uint_less_than_by_at_least(first: u64, second: u64, min: u64) -> bool {
+  matches!(second.checked_sub(first), Some(delta) if delta >= min)
+}
+
+/// Takes three 64bit unsigned int arguments and verifies that the first one
+/// is less than or equal to the second one by at most the third one.
+#[predicate]
+fn uint_less_than_by_at_most(first: u64, second: u64, max: u64) -> bool {
+  matches!(second.checked_sub(first), Some(delta) if delta <= max)
+}
+
+/// Takes three 128bit unsigned int arguments and verifies that the first
+/// one is within the inclusive range [second, third].
+#[predicate]
+fn uint128_in_range(value: u128, min: u128, max: u128) -> bool {
+  min <= value && value <= max
+}
+
+/// Takes four 128bit unsigned int arguments a, b, c, d and verifies that
+/// a/b >= c/d. Rejects if any of the denominators is zero.
+#[predicate]
+fn uint128_ratio_at_least(
+  a: u128,
+  b: u128,
+  c: u128,
+  d: u128,
+) -> Result<bool, BoundsError> {
+  let (b, d) = (nonzero(b)?, nonzero(d)?);
+  Ok(U256::from(a) * U256::from(d) >= U256::from(c) * U256::from(b))
+}
+
+/// Takes four 128bit unsigned int arguments a, b, c, d and verifies that
+/// a/b <= c/d. Rejects if any of the denominators is zero.
+#[predicate]
+fn uint128_ratio_at_most(
+  a: u128,
+  b: u128,
+  c: u128,
+  d: u128,
+) -> Result<bool, BoundsError> {
+  let (b, d) = (nonzero(b)?, nonzero(d)?);
+  Ok(U256::from(a) * U256::from(d) <= U256::from(c) * U256::from(b))
+}
+
+/// Takes the actual and quoted 128bit unsigned amounts and the maximum
+/// slippage as 64bit unsigned basis points, and verifies that the actual
+/// amount is at least the quoted amount minus the slippage.
+#[predicate]
+fn uint128_within_slippage(
+  actual: u128,
+  quoted: u128,
+  slippage_bps: u64,
+) -> Result<bool, BoundsError> {
+  let slippage_bps = basis_points(slippage_bps)?;
+  Ok(
+    U256::from(actual) * U256::from(BPS)
+      >= U256::from(quoted) * U256::from(BPS - slippage_bps),
+  )
+}
+
+/// Takes three 128bit unsigned int arguments and verifies that the first
+/// one is greater than the second one by at least the third one.
+#[predicate]
+fn uint128_greater_than_by_at_least(
+  first: u128,
+  second: u128,
+  min: u128,
+) -> bool {
+  matches!(first.checked_sub(second), Some(delta) if delta >= min)
+}
+
+/// Takes three 128bit unsigned int arguments and verifies that the first
+/// one is greater than or equal to the second one by at most the third one.
+#[predicate]
+fn uint128_greater_than_by_at_most(
+  first: u128,
+  second: u128,
+  max: u128,
+) -> bool {
+  matches!(first.checked_sub(second), Some(delta) if delta <= max)
+}
+
+/// Takes three 128bit unsigned int arguments and verifies that the first
+/// one is less than the second one by at least the third one.
+#[predicate]
+fn uint128_less_than_by_at_least(first: u128, second: u128, min: u128) -> bool {
+  matches!(second.checked_sub(first), Some(delta) if delta >= min)
+}
+
+/// Takes three 128bit unsigned int arguments and verifies that the first
+/// one is less than or equal to the second one by at most the third one.
+#[predicate]
+fn uint128_less_than_by_at_most(first: u128, second: u128, max: u128) -> bool {
+  matches!(second.checked_sub(first), Some(delta) if delta <= max)
+}
+
+#[cfg(test)]
+mod tests {
+  use anoma_predicates_sdk::testing::{ContextBuilder, ParamsBuilder};
+
+  fn params(values: &[u64]) -> Vec<anoma_predicates_sdk::ExpandedParam> {
+    values
+      .iter()
+      .fold(ParamsBuilder::new(), |builder, value| builder.inline(value))
+      .build()
+  }
+
+  #[test]
+  fn ranges() {
+    let context = ContextBuilder::new().build();
+    assert!(super::uint_in_range(&params(&[5, 5, 10]), &context));
+    assert!(super::uint_in_range(&params(&[10, 5, 10]), &context));
+    assert!(!super::uint_in_range(&params(&[11, 5, 10]), &context));
+    assert!(!super::uint_in_range(&params(&[4, 5, 10]), &context));
+  }
+
+  #[test]
+  fn ratios() {
+    let context = ContextBuilder::new().build();
+
+    // 3/4 >= 2/3 and not the other way around.
+    assert!(super::uint_ratio_at_least(&params(&[3, 4, 2, 3]), &context));
+    assert!(!super::uint_ratio_at_most(&params(&[3, 4, 2, 3]), &context));
+
+    // equal ratios satisfy both bounds.
+    assert!(super::uint_ratio_at_least(&params(&[2, 4, 1, 2]), &context));
+    assert!(super::uint_ratio_at_most(&params(&[2, 4, 1, 2]), &context));
+
+    // no overflow for large values.
+    let max = u64::MAX;
+    assert!(super::uint_ratio_at_least(
+      &params(&[max, max - 1, max - 1, max]),
+      &context
+    ));
+
+    assert!(!super::uint_ratio_at_least(
+      &params(&[1, 0, 1, 1]),
+      &context
+    ));
+  }
+
+  #[test]
+  fn slippage() {
+    let context = ContextBuilder::new().build();
+
+    // 0.5% slippage on a quote of 1000 accepts 995 and above.
+    assert!(super::uint_within_slippage(
+      &params(&[995, 1000, 50]),
+      &context
+    ));
+    assert!(!super::uint_within_slippage(
+      &params(&[994, 1000, 50]),
+      &context
+    ));
+    assert!(super::uint_within_slippage(
+      &params(&[0, 1000, 10000]),
+      &context
+    ));
+    assert!(!super::uint_within_slippage(
+      &params(&[0, 1000, 10001]),
+      &context
+    ));
+  }
+
+  #[test]
+  fn deltas() {
+    let context = ContextBuilder::new().build();
+
+    assert!(super::uint_greater_than_by_at_least(
+      &params(&[10, 5, 5]),
+      &context
+    ));
+    assert!(!super::uint_greater_than_by_at_least(
+      &params(&[10, 5, 6]),
+      &context
+    ));
+    assert!(super::uint_greater_than_by_at_most(
+      &params(&[10, 5, 5]),
+      &context
+    ));
+    assert!(!super::uint_greater_than_by_at_most(
+      &params(&[5, 10, 5]),
+      &context
+    ));
+    assert!(super::uint_less_than_by_at_least(
+      &params(&[5, 10, 3]),
+      &context
+    ));
+    assert!(!super::uint_less_than_by_at_most(
+      &params(&[5, 10, 3]),
+      &context
+    ));
+  }
+
+  #[test]
+  fn wide_ratios() {
+    let context = ContextBuilder::new().build();
+    let params = ParamsBuilder::new()
+      .inline(&u128::MAX)
+      .inline(&(u128::MAX - 1))
+      .inline(&(u128::MAX - 1))
+      .inline(&u128::MAX)
+      .build();
+    assert!(super::uint128_ratio_at_least(&params, &context));
+    assert!(!super::uint128_ratio_at_most(&params, &context));
+  }
+}
@@ -0,0 +1,110 @@
+use {super::Overflow, anoma_predicates_sdk::predicate};
+
+/// Takes two 128bit signed integer arguments and verifies that they are equal.
+#[predicate]
+fn int128_equal(first: i128, second: i128) -> bool {
+  first == second
+}
+
+/// Takes two 128bit signed integer arguments and verifies that first is > than
+/// second.
+#[predicate]
+fn int128_greater_than(first: i128, second: i128) -> bool {
+  first > second
+}
+
+/// Takes two 128bit signed integer arguments and verifies that first is >= than
+/// second.
+#[predicate]
+fn int128_greater_than_equal(first: i128, second: i128) -> bool {
+  first >= second
+}
+
+/// Takes two 128bit signed integer arguments and verifies that first is < than
+/// second.
+#[predicate]
+fn int128_less_than(first: i128, second: i128) -> bool {
+  first < second
+}
+
+/// Takes two 128bit signed integer arguments and verifies that first is <= than
+/// second.
+#[predicate]
+fn int128_less_than_equal(first: i128, second: i128) -> bool {
+  first <= second
+}
+
+/// Takes three 128bit signed integer arguments and verifies that argument at
+/// index 0 is greater than argument at index 1 by the value at index 2.
+#[predicate]
+fn int128_greater_than_by(
+  first: i128,
+  second: i128,
+  by: i128,
+) -> Result<bool, Overflow> {
+  Ok(first.checked_sub(second).ok_or(Overflow)? == by)
+}
+
+/// Takes three 128bit signed integer arguments and verifies that argument at
+/// index 0 is less than argument at index 1 by the value at index 2.
+#[predicate]
+fn int128_less_than_by(
+  first: i128,
+  second: i128,
+  by: i128,
+) -> Result<bool, Overflow> {
+  Ok(second.checked_sub(first).ok_or(Overflow)? == by)
+}
+
+/// Takes three 128bit signed integer arguments and verifies that the sum of the
+/// first two is equal to the third one. Rejects on overflow.
+#[predicate]
+fn int128_sum(first: i128, second: i128, sum: i128) -> Result<bool, Overflow> {
+  Ok(first.checked_add(second).ok_or(Overflow)? == sum)
+}
+
+/// Takes three 128bit signed integer arguments and verifies that the product of
+/// the first two is equal to the third one. Rejects on overflow.
+#[predicate]
+fn int128_product(
+  first: i128,
+  second: i128,
+  product: i128,
+) -> Result<bool, Overflow> {
+  Ok(first.checked_mul(second).ok_or(Overflow)? == product)
+}
+
+#[cfg(test)]
+mod tests {
+  use anoma_predicates_sdk::testing::{ContextBuilder, ParamsBuilder};
+
+  #[test]
+  fn wide_signed_values() {
+    let context = ContextBuilder::new().build();
+    let big = i128::from(i64::MAX) * 1000;
+    let params = ParamsBuilder::new()
+      .inline(&-big)
+      .inline(&big)
+      .inline(&(2 * big))
+      .build();
+
+    let pair = ParamsBuilder::new().inline(&-big).inline(&big).build();
+    assert!(super::int128_less_than(&pair, &context));
+    assert!(super::int128_less_than_by(&params, &context));
+
+    // small values may be encoded as native msgpack integers.
+    let params = ParamsBuilder::new().inline(&-7i64).inline(&-7i128).build();
+    assert!(super::int128_equal(&params, &context));
+  }
+
+  #[test]
+  fn overflow_rejects() {
+    let context = ContextBuilder::new().build();
+    let params = ParamsBuilder::new()
+      .inline(&i128::MAX)
+      .inline(&1i128)
+      .inline(&i128::MIN)
+      .build();
+    assert!(!super::int128_sum(&params, &context));
+  }
+}
@@ -0,0 +1,122 @@
+use {super::Overflow, anoma_predicates_sdk::predicate};
+
+/// Takes two 64bit signed integer arguments and verifies that they are equal.
+#[predicate]
+fn int64_equal(first: i64, second: i64) -> bool {
+  first == second
+}
+
+/// Takes two 64bit signed integer arguments and verifies that first is > than
+/// second.
+#[predicate]
+fn int64_greater_than(first: i64, second: i64) -> bool {
+  first > second
+}
+
+/// Takes two 64bit signed integer arguments and verifies that first is >= than
+/// second.
+#[predicate]
+fn int64_greater_than_equal(first: i64, second: i64) -> bool {
+  first >= second
+}
+
+/// Takes two 64bit signed integer arguments and verifies that first is < than
+/// second.
+#[predicate]
+fn int64_less_than(first: i64, second: i64) -> bool {
+  first < second
+}
+
+/// Takes two 64bit signed integer arguments and verifies that first is <= than
+/// second.
+#[predicate]
+fn int64_less_than_equal(first: i64, second: i64) -> bool {
+  first <= second
+}
+
+/// Takes three 64bit signed integer arguments and verifies that argument at
+/// index 0 is greater than argument at index 1 by the value at index 2.
+#[predicate]
+fn int64_greater_than_by(
+  first: i64,
+  second: i64,
+  by: i64,
+) -> Result<bool, Overflow> {
+  Ok(first.checked_sub(second).ok_or(Overflow)? == by)
+}
+
+/// Takes three 64bit signed integer arguments and verifies that argument at
+/// index 0 is less than argument at index 1 by the value at index 2.
+#[predicate]
+fn int64_less_than_by(
+  first: i64,
+  second: i64,
+  by: i64,
+) -> Result<bool, Overflow> {
+  Ok(second.checked_sub(first).ok_or(Overflow)? == by)
+}
+
+/// Takes three 64bit signed integer arguments and verifies that the sum of the
+/// first two is equal to the third one. Rejects on overflow.
+#[predicate]
+fn int64_sum(first: i64, second: i64, sum: i64) -> Result<bool, Overflow> {
+  Ok(first.checked_add(second).ok_or(Overflow)? == sum)
+}
+
+/// Takes three 64bit signed integer arguments and verifies that the product of
+/// the first two is equal to the third one. Rejects on overflow.
+#[predicate]
+fn int64_product(
+  first: i64,
+  second: i64,
+  product: i64,
+) -> Result<bool, Overflow> {
+  Ok(first.checked_mul(second).ok_or(Overflow)? == product)
+}
+
+#[cfg(test)]
+mod tests {
+  use anoma_predicates_sdk::testing::{ContextBuilder, ParamsBuilder};
+
+  #[test]
+  fn negative_values() {
+    let context = ContextBuilder::new().build();
+    let params = ParamsBuilder::new()
+      .inline(&-3i64)
+      .inline(&5i64)
+      .inline(&8i64)
+      .build();
+
+    let pair = ParamsBuilder::new().inline(&-3i64).inline(&5i64).build();
+    assert!(super::int64_less_than(&pair, &context));
+    assert!(super::int64_less_than_by(&params, &context));
+    assert!(!super::int64_greater_than_by(&params, &context));
+
+    let params = ParamsBuilder::new()
+      .inline(&-3i64)
+      .inline(&5i64)
+      .inline(&2i64)
+      .build();
+    assert!(super::int64_sum(&params, &context));
+  }
+
+  #[test]
+  fn overflow_rejects() {
+    let context = ContextBuilder::new().build();
+    let params = ParamsBuilder::new()
+      .inline(&i64::MIN)
+      .inline(&1i64)
+      .inline(&i64::MAX)
+      .build();
+
+    // i64::MIN - 1 would wrap around to i64::MAX.
+    assert!(!super::int64_greater_than_by(&params, &context));
+
+    let params = ParamsBuilder::new()
+      .inline(&i64::MIN)
+      .inline(&-1i64)
+      .inline(&i64::MIN)
+      .build();
+    assert!(!super::int64_product(&params, &context));
+  }
+}
@@ -0,0 +1,204 @@
+//! Integer comparison and arithmetic predicates.
+//!
+//! All integers are MessagePack encoded. 64bit integers use the native
+//! MessagePack integer types, 128bit integers are native integers when
+//! they fit in 64 bits or a 16 bytes big-endian binary otherwise (which is
+//! how `rmp_serde` serializes `u128` and `i128`), and 256bit integers are
+//! a big-endian binary of up to 32 bytes.
+//!
+//! Arithmetic never saturates or wraps around. Operations that overflow
+//! reject the transaction.
+
+use {
+  anoma_predicates_sdk::predicate,
+  core::fmt::{self, Display},
+};
+
+mod bounds;
+mod int128;
+mod int64;
+mod uint128;
+mod uint256;
+
+/// Returned when the result of an operation does not fit in its type.
+#[derive(Debug)]
+struct Overflow;
+
+impl Display for Overflow {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "arithmetic overflow")
+  }
+}
+
+/// Takes two arguments and varifies that they are equal 64bit unsigned
+/// integers.
+#[predicate]
+fn uint_equal(first: u64, second: u64) -> bool {
+  first == second
+}
+
+/// Takes two 64bit unsigned int arguments and varifies that first
+/// is > than second.
+#[predicate]
+fn uint_greater_than(first: u64, second: u64) -> bool {
+  first > second
+}
+
+/// Takes two 64bit unsigned ints arguments and varifies that first
+/// is >= than second.
+#[predicate]
+fn uint_greater_than_equal(first: u64, second: u64) -> bool {
+  first >= second
+}
+
+/// Takes two 64bit unsigned arguments and varifies that first
+/// is < than second.
+#[predicate]
+fn uint_less_than(first: u64, second: u64) -> bool {
+  first < second
+}
+
+/// Takes two 64bit unsigned ints arguments and varifies that first
+/// is < than second.
+#[predicate]
+fn uint_less_than_equal(first: u64, second: u64) -> bool {
+  first <= second
+}
+
+/// Takes three arguments and verifies that argument at index 0 is greater than
+/// arument at index 1 by a constant uint at argument index 2.
+#[predicate]
+fn uint_greater_than_by(first: u64, second: u64, by: u64) -> bool {
+  first.checked_sub(second) == Some(by)
+}
+
+/// Takes three arguments and verifies that argument at index 0 is less than
+/// arument at index 1 by a constant uint at argument index 2.
+#[predicate]
+fn uint_less_than_by(first: u64, second: u64, by: u64) -> bool {
+  second.checked_sub(first) == Some(by)
+}
+
+/// Takes three 64bit unsigned int arguments and verifies that the sum of
+/// the first two is equal to the third one. Rejects on overflow.
+#[predicate]
+fn uint_sum(first: u64, second: u64, sum: u64) -> Result<bool, Overflow> {
+  Ok(first.checked_add(second).ok_or(Overflow)? == sum)
+}
+
+/// Takes three 64bit unsigned int arguments and verifies that the product
+/// of the first two is equal to the third one. Rejects on overflow.
+#[predicate]
+fn uint_product(
+  first: u64,
+  second: u64,
+  product: u64,
+) -> Result<bool, Overflow> {
+  Ok(first.checked_mul(second).ok_or(Overflow)? == product)
+}
+
+#[cfg(test)]
+mod tests {
+  use anoma_predicates_sdk::testing::{ContextBuilder, ParamsBuilder};
+
+  #[test]
+  fn uint_comparisons() {
+    let context = ContextBuilder::new().build();
+    let params = ParamsBuilder::new().inline(&7u64).inline(&5u64).build();
+
+    assert!(!super::uint_equal(&params, &context));
+    assert!(super::uint_greater_than(&params, &context));
+    assert!(super::uint_greater_than_equal(&params, &context));
+    assert!(!super::uint_less_than(&params, &context));
+    assert!(!super::uint_less_than_equal(&params, &context));
+  }
+
+  #[test]
+  fn uint_by() {
+    let context = ContextBuilder::new().build();
+    let params = ParamsBuilder::new()
+      .inline(&7u64)
+      .inline(&5u64)
+      .inline(&2u64)
+      .build();
+
+    assert!(super::uint_greater_than_by(&params, &context));
+    assert!(!super::uint_less_than_by(&params, &context));
+
+    // first is less than second, must not saturate to zero.
+    let params = ParamsBuilder::new()
+      .inline(&5u64)
+      .inline(&7u64)
+      .inline(&0u64)
+      .build();
+    assert!(!super::uint_greater_than_by(&params, &context));
+  }
+
+  #[test]
+  fn uint_by_rejects_wrong_order() {
+    // these predicates used to saturate the difference, so a zero `by`
+    // accepted any pair in the wrong order. The difference is exact now.
+    let context = ContextBuilder::new().build();
+    let by = |first: u64, second: u64, by: u64| {
+      ParamsBuilder::new()
+        .inline(&first)
+        .inline(&second)
+        .inline(&by)
+        .build()
+    };
+
+    assert!(!super::uint_greater_than_by(&by(5, 7, 0), &context));
+    assert!(!super::uint_less_than_by(&by(7, 5, 0), &context));
+    assert!(!super::uint_greater_than_by(&by(0, u64::MAX, 0), &context));
+    assert!(!super::uint_less_than_by(&by(u64::MAX, 0, 0), &context));
+
+    // equal values still differ by zero either way
+    assert!(super::uint_greater_than_by(&by(5, 5, 0), &context));
+    assert!(super::uint_less_than_by(&by(5, 5, 0), &context));
+    assert!(super::uint_less_than_by(&by(5, 7, 2), &context));
+  }
+
+  #[test]
+  fn uint_checked_arithmetic() {
+    let context = ContextBuilder::new().build();
+    let params = ParamsBuilder::new()
+      .inline(&6u64)
+      .inline(&4u64)
+      .inline(&10u64)
+      .build();
+    assert!(super::uint_sum(&params, &context));
+
+    let params = ParamsBuilder::new()
+      .inline(&6u64)
+      .inline(&4u64)
+      .inline(&24u64)
+      .build();
+    assert!(super::uint_product(&params, &context));
+
+    let overflow = ParamsBuilder::new()
+      .inline(&u64::MAX)
+      .inline(&1u64)
+      .inline(&0u64)
+      .build();
+    assert!(!super::uint_sum(&overflow, &context));
+    assert!(!super::uint_product(
+      &ParamsBuilder::new()
+        .inline(&u64::MAX)
+        .inline(&2u64)
+        .inline(&(u64::MAX - 1))
+        .build(),
+      &context
+    ));
+  }
+
+  #[test]
+  fn malformed_params_reject() {
+    let context = ContextBuilder::new().build();
+
+    let missing = ParamsBuilder::new().inline(&7u64).build();
+    assert!(!super::uint_greater_than(&missing, &context));
+
+    let invalid = ParamsBuilder::new().inline(&7u64).inline("five").build();
+    assert!(!super::uint_greater_than(&invalid, &context));
+  }
+}
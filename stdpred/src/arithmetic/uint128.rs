@@ -0,0 +1,114 @@
+use {super::Overflow, anoma_predicates_sdk::predicate};
+
+/// Takes two 128bit unsigned integer arguments and verifies that they are
+/// equal.
+#[predicate]
+fn uint128_equal(first: u128, second: u128) -> bool {
+  first == second
+}
+
+/// Takes two 128bit unsigned integer arguments and verifies that first is >
+/// than second.
+#[predicate]
+fn uint128_greater_than(first: u128, second: u128) -> bool {
+  first > second
+}
+
+/// Takes two 128bit unsigned integer arguments and verifies that first is >=
+/// than second.
+#[predicate]
+fn uint128_greater_than_equal(first: u128, second: u128) -> bool {
+  first >= second
+}
+
+/// Takes two 128bit unsigned integer arguments and verifies that first is <
+/// than second.
+#[predicate]
+fn uint128_less_than(first: u128, second: u128) -> bool {
+  first < second
+}
+
+/// Takes two 128bit unsigned integer arguments and verifies that first is <=
+/// than second.
+#[predicate]
+fn uint128_less_than_equal(first: u128, second: u128) -> bool {
+  first <= second
+}
+
+/// Takes three 128bit unsigned integer arguments and verifies that argument at
+/// index 0 is greater than argument at index 1 by the value at index 2.
+#[predicate]
+fn uint128_greater_than_by(first: u128, second: u128, by: u128) -> bool {
+  first.checked_sub(second) == Some(by)
+}
+
+/// Takes three 128bit unsigned integer arguments and verifies that argument at
+/// index 0 is less than argument at index 1 by the value at index 2.
+#[predicate]
+fn uint128_less_than_by(first: u128, second: u128, by: u128) -> bool {
+  second.checked_sub(first) == Some(by)
+}
+
+/// Takes three 128bit unsigned integer arguments and verifies that the sum of
+/// the first two is equal to the third one. Rejects on overflow.
+#[predicate]
+fn uint128_sum(first: u128, second: u128, sum: u128) -> Result<bool, Overflow> {
+  Ok(first.checked_add(second).ok_or(Overflow)? == sum)
+}
+
+/// Takes three 128bit unsigned integer arguments and verifies that the product
+/// of the first two is equal to the third one. Rejects on overflow.
+#[predicate]
+fn uint128_product(
+  first: u128,
+  second: u128,
+  product: u128,
+) -> Result<bool, Overflow> {
+  Ok(first.checked_mul(second).ok_or(Overflow)? == product)
+}
+
+#[cfg(test)]
+mod tests {
+  use anoma_predicates_sdk::testing::{ContextBuilder, ParamsBuilder};
+
+  #[test]
+  fn eighteen_decimals() {
+    let context = ContextBuilder::new().build();
+    let one = 10u128.pow(18);
+    let params = ParamsBuilder::new()
+      .inline(&(25 * one))
+      .inline(&(5 * one))
+      .inline(&(20 * one))
+      .build();
+
+    let pair = ParamsBuilder::new()
+      .inline(&(25 * one))
+      .inline(&one)
+      .build();
+    assert!(super::uint128_greater_than(&pair, &context));
+    assert!(super::uint128_greater_than_by(&params, &context));
+    assert!(!super::uint128_less_than_by(&params, &context));
+
+    // small values may be encoded as native msgpack integers.
+    let params = ParamsBuilder::new().inline(&5u64).inline(&5u128).build();
+    assert!(super::uint128_equal(&params, &context));
+  }
+
+  #[test]
+  fn overflow_rejects() {
+    let context = ContextBuilder::new().build();
+    let params = ParamsBuilder::new()
+      .inline(&u128::MAX)
+      .inline(&2u128)
+      .inline(&(u128::MAX - 1))
+      .build();
+    assert!(!super::uint128_product(&params, &context));
+
+    let params = ParamsBuilder::new()
+      .inline(&1u128)
+      .inline(&2u128)
+      .inline(&0u128)
+      .build();
+    assert!(!super::uint128_greater_than_by(&params, &context));
+  }
+}
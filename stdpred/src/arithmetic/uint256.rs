@@ -0,0 +1,202 @@
+use {
+  super::Overflow,
+  anoma_predicates_sdk::predicate,
+  core::fmt,
+  serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer,
+  },
+};
+
+/// 256bit unsigned integer.
+///
+/// Encoded as a MessagePack binary of up to 32 big-endian bytes. Values
+/// that fit in 64 bits may also be encoded as native MessagePack integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct U256(primitive_types::U256);
+
+impl Serialize for U256 {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut bytes = [0u8; 32];
+    self.0.to_big_endian(&mut bytes);
+    serializer.serialize_bytes(&bytes)
+  }
+}
+
+impl<'de> Deserialize<'de> for U256 {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    deserializer.deserialize_any(U256Visitor)
+  }
+}
+
+struct U256Visitor;
+
+impl<'de> Visitor<'de> for U256Visitor {
+  type Value = U256;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "an unsigned integer or up to 32 big-endian bytes")
+  }
+
+  fn visit_u64<E: de::Error>(self, value: u64) -> Result<U256, E> {
+    Ok(U256(value.into()))
+  }
+
+  fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<U256, E> {
+    if bytes.len() > 32 {
+      return Err(E::invalid_length(bytes.len(), &self));
+    }
+    Ok(U256(primitive_types::U256::from_big_endian(bytes)))
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<U256, A::Error> {
+    let mut bytes = [0u8; 32];
+    let mut len = 0;
+    while let Some(byte) = seq.next_element::<u8>()? {
+      if len == bytes.len() {
+        return Err(de::Error::invalid_length(len + 1, &self));
+      }
+      bytes[len] = byte;
+      len += 1;
+    }
+    self.visit_bytes(&bytes[..len])
+  }
+}
+
+/// Takes two 256bit unsigned integer arguments and verifies that they are
+/// equal.
+#[predicate]
+fn uint256_equal(first: U256, second: U256) -> bool {
+  first == second
+}
+
+/// Takes two 256bit unsigned integer arguments and verifies that first is >
+/// than second.
+#[predicate]
+fn uint256_greater_than(first: U256, second: U256) -> bool {
+  first > second
+}
+
+/// Takes two 256bit unsigned integer arguments and verifies that first is >=
+/// than second.
+#[predicate]
+fn uint256_greater_than_equal(first: U256, second: U256) -> bool {
+  first >= second
+}
+
+/// Takes two 256bit unsigned integer arguments and verifies that first is <
+/// than second.
+#[predicate]
+fn uint256_less_than(first: U256, second: U256) -> bool {
+  first < second
+}
+
+/// Takes two 256bit unsigned integer arguments and verifies that first is <=
+/// than second.
+#[predicate]
+fn uint256_less_than_equal(first: U256, second: U256) -> bool {
+  first <= second
+}
+
+/// Takes three 256bit unsigned integer arguments and verifies that argument at
+/// index 0 is greater than argument at index 1 by the value at index 2.
+#[predicate]
+fn uint256_greater_than_by(first: U256, second: U256, by: U256) -> bool {
+  first.0.checked_sub(second.0) == Some(by.0)
+}
+
+/// Takes three 256bit unsigned integer arguments and verifies that argument at
+/// index 0 is less than argument at index 1 by the value at index 2.
+#[predicate]
+fn uint256_less_than_by(first: U256, second: U256, by: U256) -> bool {
+  second.0.checked_sub(first.0) == Some(by.0)
+}
+
+/// Takes three 256bit unsigned integer arguments and verifies that the sum of
+/// the first two is equal to the third one. Rejects on overflow.
+#[predicate]
+fn uint256_sum(first: U256, second: U256, sum: U256) -> Result<bool, Overflow> {
+  Ok(first.0.checked_add(second.0).ok_or(Overflow)? == sum.0)
+}
+
+/// Takes three 256bit unsigned integer arguments and verifies that the product
+/// of the first two is equal to the third one. Rejects on overflow.
+#[predicate]
+fn uint256_product(
+  first: U256,
+  second: U256,
+  product: U256,
+) -> Result<bool, Overflow> {
+  Ok(first.0.checked_mul(second.0).ok_or(Overflow)? == product.0)
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::U256,
+    anoma_predicates_sdk::testing::{ContextBuilder, ParamsBuilder},
+  };
+
+  fn u256(value: u128) -> U256 {
+    U256(value.into())
+  }
+
+  #[test]
+  fn wide_values() {
+    let context = ContextBuilder::new().build();
+    let one = u256(10u128.pow(18));
+    let big = U256(one.0 * one.0 * one.0);
+
+    let params = ParamsBuilder::new()
+      .inline(&U256(big.0 * 3))
+      .inline(&big)
+      .inline(&U256(big.0 * 2))
+      .build();
+    let pair = ParamsBuilder::new().inline(&big).inline(&one).build();
+    assert!(super::uint256_greater_than(&pair, &context));
+    assert!(super::uint256_greater_than_by(&params, &context));
+    assert!(!super::uint256_less_than_by(&params, &context));
+
+    // native msgpack integers and short big-endian binaries.
+    let params = ParamsBuilder::new()
+      .inline(&300u64)
+      .raw(vec![0xc4, 0x02, 0x01, 0x2c])
+      .build();
+    assert!(super::uint256_equal(&params, &context));
+  }
+
+  #[test]
+  fn overflow_rejects() {
+    let context = ContextBuilder::new().build();
+    let max = U256(primitive_types::U256::MAX);
+
+    let params = ParamsBuilder::new()
+      .inline(&max)
+      .inline(&u256(1))
+      .inline(&u256(0))
+      .build();
+    assert!(!super::uint256_sum(&params, &context));
+
+    let params = ParamsBuilder::new()
+      .inline(&u256(2))
+      .inline(&u256(3))
+      .inline(&u256(6))
+      .build();
+    assert!(super::uint256_product(&params, &context));
+  }
+
+  #[test]
+  fn oversized_rejects() {
+    let context = ContextBuilder::new().build();
+    let mut oversized = vec![0xc4, 33];
+    oversized.extend([0u8; 33]);
+
+    let params = ParamsBuilder::new().raw(oversized).inline(&u256(0)).build();
+    assert!(!super::uint256_equal(&params, &context));
+  }
+}
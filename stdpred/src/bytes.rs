@@ -1,16 +1,10 @@
-use {
-  alloc::vec::Vec,
-  anoma_predicates_sdk::{predicate, ExpandedParam, PredicateContext},
-};
+use {alloc::vec::Vec, anoma_predicates_sdk::predicate};
 
 /// Takes two arguments and varifies that they are equal bytestings
 #[predicate]
-fn bytes_equal(params: &Vec<ExpandedParam>, _: &PredicateContext) -> bool {
-  assert_eq!(params.len(), 2);
-
-  let mut it = params.iter();
-  let first = it.next().expect("asserted").data();
-  let second = it.next().expect("asserted").data();
-
+fn bytes_equal(
+  #[param(raw)] first: Vec<u8>,
+  #[param(raw)] second: Vec<u8>,
+) -> bool {
   first == second
 }
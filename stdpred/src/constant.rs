@@ -1,12 +1,8 @@
-use {
-  alloc::vec::Vec,
-  anoma_predicates_sdk::{
-    predicate,
-    Address,
-    ExpandedAccountChange,
-    ExpandedParam,
-    PredicateContext,
-  },
+use anoma_predicates_sdk::{
+  predicate,
+  Address,
+  ExpandedAccountChange,
+  PredicateContext,
 };
 
 /// Always return a const true or false.
@@ -14,10 +10,8 @@ use {
 /// Parameters:
 ///   0: Boolean value that is always returned by this predicate
 #[predicate]
-fn constant(params: &Vec<ExpandedParam>, _: &PredicateContext) -> bool {
-  assert_eq!(params.len(), 1);
-  rmp_serde::from_slice(params.iter().next().expect("asserted").data())
-    .expect("invalid argument format")
+fn constant(value: bool) -> bool {
+  value
 }
 
 /// Forbids any changes to the specified account state but allows changes to its
@@ -26,18 +20,9 @@ fn constant(params: &Vec<ExpandedParam>, _: &PredicateContext) -> bool {
 /// Parameters:
 ///   0. Address of the immutable account
 #[predicate]
-fn immutable_state(
-  params: &Vec<ExpandedParam>,
-  context: &PredicateContext,
-) -> bool {
-  assert_eq!(params.len(), 1);
-
+fn immutable_state(target: Address, context: &PredicateContext) -> bool {
   // make sure that the change is targetting this account not any of its
   // children
-  let target: Address =
-    rmp_serde::from_slice(params.first().expect("asserted").data())
-      .expect("invalid predicate param");
-
   if let Some(change) = context.proposals.get(&target) {
     if matches!(change, ExpandedAccountChange::ReplaceState { .. }) {
       return false;
@@ -53,18 +38,9 @@ fn immutable_state(
 /// Parameters:
 ///   0. Address of the immutable predicates account
 #[predicate]
-fn immutable_predicates(
-  params: &Vec<ExpandedParam>,
-  context: &PredicateContext,
-) -> bool {
-  assert_eq!(params.len(), 1);
-
+fn immutable_predicates(target: Address, context: &PredicateContext) -> bool {
   // make sure that the change is targetting this account not any of its
   // children
-  let target: Address =
-    rmp_serde::from_slice(params.first().expect("asserted").data())
-      .expect("invalid predicate param");
-
   if let Some(change) = context.proposals.get(&target) {
     if matches!(change, ExpandedAccountChange::ReplacePredicates { .. }) {
       return false;
@@ -0,0 +1,180 @@
+//! Hash-lock predicates.
+//!
+//! Those predicates are satisfied when any intent in the transaction reveals
+//! in its calldata a preimage of a given digest. Combined with the time-lock
+//! predicates they express hashed time-locked contracts (HTLC) used for
+//! atomic swaps:
+//!
+//! ```text
+//! Or(
+//!   And(sha256_preimage(H, "preimage"), before_height(T), signature(bob)),
+//!   And(after_height(T), signature(alice)),
+//! )
+//! ```
+//!
+//! The preimage is looked up in the calldata directly instead of using a
+//! `CalldataRef` parameter, because a missing calldata reference fails the
+//! whole transaction and would make the timeout branch unreachable.
+
+use {
+  alloc::{string::String, vec::Vec},
+  anoma_predicates_sdk::{predicate, PredicateContext},
+  blake2::{
+    digest::{Update, VariableOutput},
+    Blake2bVar,
+  },
+  sha2::{Digest, Sha256},
+  sha3::{Keccak256, Sha3_256},
+};
+
+/// Checks if any of the intents has a calldata entry under the given key,
+/// that hashes to the expected digest.
+fn reveals_preimage(
+  context: &PredicateContext,
+  key: &str,
+  digest: &[u8],
+  hash: impl Fn(&[u8]) -> Option<Vec<u8>>,
+) -> bool {
+  context
+    .calldata
+    .values()
+    .filter_map(|calldata| calldata.get(key))
+    .any(|preimage| hash(preimage).as_deref() == Some(digest))
+}
+
+/// Verifies that the transaction reveals a preimage of a SHA3-256 digest.
+///
+/// Parameters:
+///   0. Expected digest (raw bytes)
+///   1. Calldata key under which the preimage is revealed (string)
+#[predicate]
+fn sha3_256_preimage(
+  #[param(raw)] digest: Vec<u8>,
+  key: String,
+  context: &PredicateContext,
+) -> bool {
+  reveals_preimage(context, &key, &digest, |preimage| {
+    Some(Sha3_256::digest(preimage).to_vec())
+  })
+}
+
+/// Verifies that the transaction reveals a preimage of a SHA-256 digest.
+/// This is the hash function used by Bitcoin and Lightning HTLCs.
+///
+/// Parameters:
+///   0. Expected digest (raw bytes)
+///   1. Calldata key under which the preimage is revealed (string)
+#[predicate]
+fn sha256_preimage(
+  #[param(raw)] digest: Vec<u8>,
+  key: String,
+  context: &PredicateContext,
+) -> bool {
+  reveals_preimage(context, &key, &digest, |preimage| {
+    Some(Sha256::digest(preimage).to_vec())
+  })
+}
+
+/// Verifies that the transaction reveals a preimage of a Keccak-256 digest.
+/// This is the hash function used by Ethereum HTLCs.
+///
+/// Parameters:
+///   0. Expected digest (raw bytes)
+///   1. Calldata key under which the preimage is revealed (string)
+#[predicate]
+fn keccak256_preimage(
+  #[param(raw)] digest: Vec<u8>,
+  key: String,
+  context: &PredicateContext,
+) -> bool {
+  reveals_preimage(context, &key, &digest, |preimage| {
+    Some(Keccak256::digest(preimage).to_vec())
+  })
+}
+
+/// Verifies that the transaction reveals a preimage of a BLAKE2b digest.
+/// The output size of the hash function is the length of the expected
+/// digest, between 1 and 64 bytes.
+///
+/// Parameters:
+///   0. Expected digest (raw bytes)
+///   1. Calldata key under which the preimage is revealed (string)
+#[predicate]
+fn blake2b_preimage(
+  #[param(raw)] digest: Vec<u8>,
+  key: String,
+  context: &PredicateContext,
+) -> bool {
+  reveals_preimage(context, &key, &digest, |preimage| {
+    if digest.is_empty() {
+      return None;
+    }
+    let mut hasher = Blake2bVar::new(digest.len()).ok()?;
+    let mut output = alloc::vec![0u8; digest.len()];
+    hasher.update(preimage);
+    hasher.finalize_variable(&mut output).ok()?;
+    Some(output)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    anoma_predicates_sdk::testing::{
+      intent_hash,
+      ContextBuilder,
+      ParamsBuilder,
+    },
+    blake2::{
+      digest::{Update, VariableOutput},
+      Blake2bVar,
+    },
+    sha2::{Digest, Sha256},
+    sha3::{Keccak256, Sha3_256},
+  };
+
+  fn params(digest: Vec<u8>) -> Vec<anoma_predicates_sdk::ExpandedParam> {
+    ParamsBuilder::new().raw(digest).inline("preimage").build()
+  }
+
+  #[test]
+  fn preimages() {
+    let secret = b"correct horse battery staple";
+    let context = ContextBuilder::new()
+      .calldata(intent_hash(b"intent-1"), "preimage", secret.to_vec())
+      .build();
+
+    let sha256 = params(Sha256::digest(secret).to_vec());
+    assert!(super::sha256_preimage(&sha256, &context));
+    assert!(!super::sha3_256_preimage(&sha256, &context));
+
+    let sha3 = params(Sha3_256::digest(secret).to_vec());
+    assert!(super::sha3_256_preimage(&sha3, &context));
+
+    let keccak = params(Keccak256::digest(secret).to_vec());
+    assert!(super::keccak256_preimage(&keccak, &context));
+
+    let mut blake2b = Blake2bVar::new(32).unwrap();
+    let mut output = vec![0u8; 32];
+    blake2b.update(secret);
+    blake2b.finalize_variable(&mut output).unwrap();
+    let blake2b = params(output);
+    assert!(super::blake2b_preimage(&blake2b, &context));
+  }
+
+  #[test]
+  fn missing_or_wrong_preimage() {
+    let digest = params(Sha256::digest(b"secret").to_vec());
+
+    let context = ContextBuilder::new().build();
+    assert!(!super::sha256_preimage(&digest, &context));
+
+    let context = ContextBuilder::new()
+      .calldata(intent_hash(b"intent-1"), "preimage", b"guess".to_vec())
+      .build();
+    assert!(!super::sha256_preimage(&digest, &context));
+
+    // an empty digest is not a valid blake2b output size
+    assert!(!super::blake2b_preimage(&params(vec![]), &context));
+  }
+}
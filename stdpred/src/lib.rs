@@ -1,13 +1,34 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 mod arithmetic;
 mod bytes;
 mod constant;
+mod hashlock;
 mod map;
+mod nft;
+mod path;
+mod proposal;
 mod set;
 mod signature;
+mod state;
+mod time;
+mod token;
 
 use anoma_predicates_sdk::initialize_library;
-pub use {arithmetic::*, bytes::*, constant::*, map::*, set::*, signature::*};
+pub use {
+  arithmetic::*,
+  bytes::*,
+  constant::*,
+  hashlock::*,
+  map::*,
+  nft::*,
+  path::*,
+  proposal::*,
+  set::*,
+  signature::*,
+  state::*,
+  time::*,
+  token::*,
+};
 
 initialize_library!();
@@ -1,46 +1,68 @@
 use {
   alloc::{collections::BTreeMap, vec::Vec},
-  anoma_predicates_sdk::{predicate, ExpandedParam, PredicateContext},
+  anoma_predicates_sdk::predicate,
 };
 
+/// Verifies that a map of bytestrings has no entries.
 #[predicate]
-fn is_empty_map(params: &Vec<ExpandedParam>, _: &PredicateContext) -> bool {
-  assert_eq!(params.len(), 1);
-
-  let mut it = params.iter();
-  let first = it.next().expect("asserted").data();
-
-  let set: BTreeMap<Vec<u8>, Vec<u8>> = rmp_serde::from_slice(first)
-    .expect("Invalid collection format. Expecting BTreeMap<Vec<u8>, Vec<u8>>.");
-
-  set.is_empty()
+fn is_empty_map(map: BTreeMap<Vec<u8>, Vec<u8>>) -> bool {
+  map.is_empty()
 }
 
+/// Verifies that a map of bytestrings contains a given key.
 #[predicate]
-fn contains_key(params: &Vec<ExpandedParam>, _: &PredicateContext) -> bool {
-  assert_eq!(params.len(), 2);
+fn contains_key(
+  haystack: BTreeMap<Vec<u8>, Vec<u8>>,
+  #[param(raw)] needle: Vec<u8>,
+) -> bool {
+  haystack.contains_key(&needle)
+}
 
-  let mut it = params.iter();
-  let haystack = it.next().expect("asserted").data();
-  let needle = it.next().expect("asserted").data();
+/// Verifies that a map of bytestrings contains a given key and that
+/// the value stored under it is equal to a given value.
+#[predicate]
+fn key_equals(
+  haystack: BTreeMap<Vec<u8>, Vec<u8>>,
+  #[param(raw)] needle: Vec<u8>,
+  #[param(raw)] value: Vec<u8>,
+) -> bool {
+  haystack.get(&needle).map(|v| v == &value).unwrap_or(false)
+}
 
-  let map: BTreeMap<Vec<u8>, Vec<u8>> = rmp_serde::from_slice(haystack)
-    .expect("Invalid collection format. Expecting BTreeMap<Vec<u8>, Vec<u8>>.");
+#[cfg(test)]
+mod tests {
+  use {
+    anoma_predicates_sdk::testing::{ContextBuilder, ParamsBuilder},
+    std::collections::BTreeMap,
+  };
 
-  map.contains_key(needle)
-}
+  #[test]
+  fn key_lookup() {
+    let map: BTreeMap<Vec<u8>, Vec<u8>> =
+      [(b"owner".to_vec(), b"alice.eth".to_vec())]
+        .into_iter()
+        .collect();
+    let context = ContextBuilder::new().build();
 
-#[predicate]
-fn key_equals(params: &Vec<ExpandedParam>, _: &PredicateContext) -> bool {
-  assert_eq!(params.len(), 2);
+    let params = ParamsBuilder::new().inline(&map).raw(*b"owner").build();
+    assert!(super::contains_key(&params, &context));
 
-  let mut it = params.iter();
-  let haystack = it.next().expect("asserted").data();
-  let needle = it.next().expect("asserted").data();
-  let value = it.next().expect("asserted").data();
+    let params = ParamsBuilder::new()
+      .inline(&map)
+      .raw(*b"owner")
+      .raw(*b"alice.eth")
+      .build();
+    assert!(super::key_equals(&params, &context));
 
-  let map: BTreeMap<Vec<u8>, Vec<u8>> = rmp_serde::from_slice(haystack)
-    .expect("Invalid collection format. Expecting BTreeMap<Vec<u8>, Vec<u8>>.");
+    let params = ParamsBuilder::new()
+      .inline(&map)
+      .raw(*b"owner")
+      .raw(*b"mallory.eth")
+      .build();
+    assert!(!super::key_equals(&params, &context));
 
-  map.get(needle).map(|v| v == value).unwrap_or(false)
+    // the expected value is missing
+    let params = ParamsBuilder::new().inline(&map).raw(*b"owner").build();
+    assert!(!super::key_equals(&params, &context));
+  }
 }
@@ -0,0 +1,368 @@
+//! Non-fungible tokens.
+//!
+//! A collection is an account, e.g. `/nft/punks`, and every account directly
+//! under it, e.g. `/nft/punks/42`, is a unique item of that collection. All
+//! state is MessagePack with named fields:
+//!
+//! ```text
+//! collection account: { "authority": <base58 ed25519 pubkey>,
+//!                       "royalty_bps": u16,
+//!                       "royalty_recipient": <base58 ed25519 pubkey> }
+//! item account:       { "owner": <base58 ed25519 pubkey>, "uri": string }
+//! ```
+//!
+//! The collection account is guarded by `nft_collection(AccountRef(self))`.
+//! Because predicates of ancestors run for every change to an account, it
+//! governs all items of the collection regardless of their own predicates:
+//!   - minting items and changing the collection requires the signature of the
+//!     collection authority,
+//!   - transferring and burning an item requires the signature of its current
+//!     owner,
+//!   - the item uri and predicates can be changed only by the authority,
+//!   - the predicates of the collection account are immutable.
+//!
+//! Royalties are metadata for marketplaces and are not enforced on
+//! transfers. Intents express the expected owner of an item using
+//! `nft_owned_by`.
+
+use {
+  crate::{
+    signature::decode_pubkey,
+    state::{decode, signed_by, StateError},
+  },
+  alloc::{string::String, vec::Vec},
+  anoma_predicates_sdk::{
+    predicate,
+    Address,
+    ExpandedAccountChange,
+    ExpandedParam,
+    PredicateContext,
+  },
+  core::fmt::{self, Display},
+  serde::Deserialize,
+};
+
+/// Royalties are expressed in basis points of the sale price.
+const MAX_ROYALTY_BPS: u16 = 10_000;
+
+/// State of a collection account.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct Collection {
+  /// base58 encoded ed25519 public key allowed to mint items.
+  authority: String,
+  royalty_bps: u16,
+  royalty_recipient: String,
+}
+
+/// State of an item account.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct Item {
+  /// base58 encoded ed25519 public key of the owner.
+  owner: String,
+  uri: String,
+}
+
+#[derive(Debug)]
+enum NftError {
+  NotAnAccountRef,
+  NotAnItem(Address),
+  InvalidState(Address, String),
+  InvalidKey(String),
+  InvalidRoyalty(u16),
+  Unauthorized(Address, &'static str),
+  ImmutablePredicates,
+}
+
+impl Display for NftError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      NftError::NotAnAccountRef => {
+        write!(f, "expected an account reference to the collection")
+      }
+      NftError::NotAnItem(address) => {
+        write!(f, "{address} is not a direct child of the collection")
+      }
+      NftError::InvalidState(address, reason) => {
+        write!(f, "invalid nft state of {address}: {reason}")
+      }
+      NftError::InvalidKey(key) => {
+        write!(f, "'{key}' is not a base58 encoded ed25519 public key")
+      }
+      NftError::InvalidRoyalty(bps) => {
+        write!(f, "royalty of {bps} bps exceeds {MAX_ROYALTY_BPS} bps")
+      }
+      NftError::Unauthorized(address, action) => {
+        write!(f, "{action} {address} is not authorized")
+      }
+      NftError::ImmutablePredicates => {
+        write!(f, "collection predicates are immutable")
+      }
+    }
+  }
+}
+
+impl From<StateError> for NftError {
+  fn from(error: StateError) -> Self {
+    match error {
+      StateError::InvalidState(address, reason) => {
+        NftError::InvalidState(address, reason)
+      }
+      StateError::InvalidKey(key) => NftError::InvalidKey(key),
+    }
+  }
+}
+
+/// Fails with an authorization error unless the key signed the transaction.
+fn authorize(
+  key: &str,
+  address: &Address,
+  action: &'static str,
+  context: &PredicateContext,
+) -> Result<(), NftError> {
+  match signed_by(key, context)? {
+    true => Ok(()),
+    false => Err(NftError::Unauthorized(address.clone(), action)),
+  }
+}
+
+fn validate(
+  address: &Address,
+  collection: &Collection,
+) -> Result<(), NftError> {
+  if collection.royalty_bps > MAX_ROYALTY_BPS {
+    return Err(NftError::InvalidRoyalty(collection.royalty_bps));
+  }
+  decode_pubkey(&collection.authority)
+    .ok_or_else(|| NftError::InvalidKey(collection.authority.clone()))?;
+  decode_pubkey(&collection.royalty_recipient)
+    .ok_or_else(|| NftError::InvalidState(address.clone(), "royalty".into()))?;
+  Ok(())
+}
+
+/// Guards an NFT collection account and all items under it.
+///
+/// Parameters:
+///   0. AccountRef to the collection account itself
+#[predicate]
+fn nft_collection(
+  #[param(expanded)] collection: ExpandedParam,
+  context: &PredicateContext,
+) -> Result<bool, NftError> {
+  let ExpandedParam::AccountRef(address, state) = collection else {
+    return Err(NftError::NotAnAccountRef);
+  };
+
+  let current: Collection = decode(&address, &state)?;
+  let authority = current.authority.as_str();
+
+  match context.proposals.get(&address) {
+    None => (),
+    Some(ExpandedAccountChange::ReplaceState { proposed, .. }) => {
+      validate(&address, &decode(&address, proposed)?)?;
+      authorize(authority, &address, "updating collection", context)?;
+    }
+    Some(ExpandedAccountChange::DeleteAccount { .. }) => {
+      authorize(authority, &address, "deleting collection", context)?;
+    }
+    Some(_) => return Err(NftError::ImmutablePredicates),
+  }
+
+  for (item, change) in &context.proposals {
+    if !address.is_parent_of(item) {
+      continue;
+    }
+
+    if item.ancestors().next().as_ref() != Some(&address) {
+      return Err(NftError::NotAnItem(item.clone()));
+    }
+
+    match change {
+      ExpandedAccountChange::CreateAccount(account) => {
+        decode::<Item>(item, &account.state)?;
+        authorize(authority, item, "minting", context)?;
+      }
+      ExpandedAccountChange::ReplaceState { current, proposed } => {
+        let current: Item = decode(item, current)?;
+        let proposed: Item = decode(item, proposed)?;
+        if current.owner != proposed.owner {
+          authorize(&current.owner, item, "transferring", context)?;
+        }
+        if current.uri != proposed.uri {
+          authorize(authority, item, "changing uri of", context)?;
+        }
+      }
+      // the proposal does not carry the item state, so the owner
+      // is unknown here and only the authority can do that.
+      ExpandedAccountChange::ReplacePredicates { .. } => {
+        authorize(authority, item, "replacing predicates of", context)?;
+      }
+      ExpandedAccountChange::DeleteAccount { current } => {
+        let current: Item = decode(item, &current.state)?;
+        authorize(&current.owner, item, "burning", context)?;
+      }
+    }
+  }
+
+  Ok(true)
+}
+
+/// Verifies that an item is owned by the given key after the transaction,
+/// either minted to it or transferred to it.
+///
+/// Parameters:
+///   0. Item address
+///   1. ed25519 public key of the expected owner (raw bytes)
+#[predicate]
+fn nft_owned_by(
+  item: Address,
+  #[param(raw)] owner: Vec<u8>,
+  context: &PredicateContext,
+) -> Result<bool, NftError> {
+  let state = match context.proposals.get(&item) {
+    Some(ExpandedAccountChange::CreateAccount(account)) => &account.state,
+    Some(ExpandedAccountChange::ReplaceState { proposed, .. }) => proposed,
+    _ => return Ok(false),
+  };
+  let state: Item = decode(&item, state)?;
+  Ok(state.owner == bs58::encode(owner).into_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    anoma_predicates_sdk::{
+      testing::{intent_hash, ContextBuilder, ParamsBuilder},
+      Address,
+      ExpandedAccountChange,
+      ExpandedParam,
+    },
+    ed25519_dalek::Keypair,
+    serde::Serialize,
+  };
+
+  #[derive(Serialize)]
+  struct Collection {
+    authority: String,
+    royalty_bps: u16,
+    royalty_recipient: String,
+  }
+
+  #[derive(Serialize)]
+  struct Item {
+    owner: String,
+    uri: String,
+  }
+
+  fn b58(keypair: &Keypair) -> String {
+    bs58::encode(keypair.public.as_bytes()).into_string()
+  }
+
+  fn named<T: Serialize>(value: &T) -> Vec<u8> {
+    rmp_serde::to_vec_named(value).unwrap()
+  }
+
+  fn punks() -> Address {
+    "/nft/punks".parse().unwrap()
+  }
+
+  fn collection(authority: &Keypair, royalty_bps: u16) -> Collection {
+    Collection {
+      authority: b58(authority),
+      royalty_bps,
+      royalty_recipient: b58(authority),
+    }
+  }
+
+  fn item(owner: &Keypair, uri: &str) -> Item {
+    Item {
+      owner: b58(owner),
+      uri: uri.into(),
+    }
+  }
+
+  fn replace(
+    address: Address,
+    current: &impl Serialize,
+    proposed: &impl Serialize,
+    signer: &Keypair,
+  ) -> anoma_predicates_sdk::PredicateContext {
+    ContextBuilder::new()
+      .proposal(address, ExpandedAccountChange::ReplaceState {
+        current: named(current),
+        proposed: named(proposed),
+      })
+      .signature(intent_hash(b"intent-1"), signer)
+      .build()
+  }
+
+  #[test]
+  fn collection_rules() {
+    let authority = Keypair::generate(&mut rand::thread_rng());
+    let alice = Keypair::generate(&mut rand::thread_rng());
+    let params = vec![ExpandedParam::AccountRef(
+      punks(),
+      named(&collection(&authority, 500)),
+    )];
+    let item42: Address = "/nft/punks/42".parse().unwrap();
+
+    // royalties are capped at 100%
+    let royalties = |bps| {
+      let context = replace(
+        punks(),
+        &collection(&authority, 500),
+        &collection(&authority, bps),
+        &authority,
+      );
+      super::nft_collection(&params, &context)
+    };
+    assert!(royalties(250));
+    assert!(royalties(10_000));
+    assert!(!royalties(10_001));
+
+    // only the authority changes the uri
+    let uri = |signer| {
+      let context = replace(
+        item42.clone(),
+        &item(&alice, "ipfs://a"),
+        &item(&alice, "ipfs://b"),
+        signer,
+      );
+      super::nft_collection(&params, &context)
+    };
+    assert!(!uri(&alice));
+    assert!(uri(&authority));
+
+    // items are direct children of the collection
+    let nested = replace(
+      "/nft/punks/42/attributes".parse().unwrap(),
+      &item(&alice, "ipfs://a"),
+      &item(&alice, "ipfs://a"),
+      &authority,
+    );
+    assert!(!super::nft_collection(&params, &nested));
+  }
+
+  #[test]
+  fn owned_by() {
+    let alice = Keypair::generate(&mut rand::thread_rng());
+    let bob = Keypair::generate(&mut rand::thread_rng());
+    let item42: Address = "/nft/punks/42".parse().unwrap();
+    let context = replace(
+      item42.clone(),
+      &item(&alice, "ipfs://a"),
+      &item(&bob, "ipfs://a"),
+      &alice,
+    );
+
+    let owner = |keypair: &Keypair, item: &Address| {
+      let params = ParamsBuilder::new()
+        .inline(item)
+        .raw(keypair.public.as_bytes().to_vec())
+        .build();
+      super::nft_owned_by(&params, &context)
+    };
+    assert!(owner(&bob, &item42));
+    assert!(!owner(&alice, &item42));
+    assert!(!owner(&bob, &"/nft/punks/43".parse().unwrap()));
+  }
+}
@@ -0,0 +1,484 @@
+//! Predicates over structured account state.
+//!
+//! Account state is often more than a single number, e.g. a MessagePack map
+//! of balances or a struct serialized with named fields. Those predicates
+//! navigate such state using a path, a MessagePack array of segments where
+//! each segment is either a map key (strings for struct fields, or any other
+//! key type) or a non-negative array index. For example the path
+//! `["balances", "alice.eth"]` points at `100` in the state
+//! `{"balances": {"alice.eth": 100}, "frozen": false}`.
+//!
+//! Structs must be serialized with their field names (`to_vec_named` in
+//! `rmp_serde`) to be addressable by name, otherwise they are arrays and
+//! fields are addressed by their position.
+
+use {
+  alloc::{
+    string::{String, ToString},
+    vec::Vec,
+  },
+  anoma_predicates_sdk::{predicate, ExpandedAccountChange, ExpandedParam},
+  core::fmt::{self, Display},
+  serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    Deserialize,
+    Deserializer,
+  },
+};
+
+/// A dynamically typed MessagePack value.
+///
+/// Integers of all widths and signedness are stored as `i128`, so the same
+/// number compares equal regardless of how compactly it was encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+  Nil,
+  Bool(bool),
+  Int(i128),
+  Float(f64),
+  String(String),
+  Bytes(Vec<u8>),
+  Array(Vec<Value>),
+  Map(Vec<(Value, Value)>),
+}
+
+impl Value {
+  /// Returns the value stored under a path segment, either a map entry with
+  /// a matching key or an array element at a given index.
+  pub(crate) fn child(&self, segment: &Value) -> Option<&Value> {
+    match (self, segment) {
+      (Value::Map(entries), key) => {
+        entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+      }
+      (Value::Array(items), Value::Int(index)) => {
+        usize::try_from(*index).ok().and_then(|i| items.get(i))
+      }
+      _ => None,
+    }
+  }
+
+  /// Returns the value at a given path, the value itself for an empty path.
+  pub(crate) fn get(&self, path: &[Value]) -> Option<&Value> {
+    path
+      .iter()
+      .try_fold(self, |current, segment| current.child(segment))
+  }
+
+  pub(crate) fn as_u64(&self) -> Option<u64> {
+    match self {
+      Value::Int(value) => u64::try_from(*value).ok(),
+      _ => None,
+    }
+  }
+}
+
+impl<'de> Deserialize<'de> for Value {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    deserializer.deserialize_any(ValueVisitor)
+  }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+  type Value = Value;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("any MessagePack value")
+  }
+
+  fn visit_unit<E>(self) -> Result<Value, E> {
+    Ok(Value::Nil)
+  }
+
+  fn visit_none<E>(self) -> Result<Value, E> {
+    Ok(Value::Nil)
+  }
+
+  fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<Value, D::Error> {
+    Value::deserialize(d)
+  }
+
+  fn visit_newtype_struct<D: Deserializer<'de>>(
+    self,
+    d: D,
+  ) -> Result<Value, D::Error> {
+    Value::deserialize(d)
+  }
+
+  fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+    Ok(Value::Bool(v))
+  }
+
+  fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+    Ok(Value::Int(v.into()))
+  }
+
+  fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+    Ok(Value::Int(v.into()))
+  }
+
+  fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+    Ok(Value::Float(v))
+  }
+
+  fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+    Ok(Value::String(v.to_string()))
+  }
+
+  fn visit_string<E>(self, v: String) -> Result<Value, E> {
+    Ok(Value::String(v))
+  }
+
+  fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E> {
+    Ok(Value::Bytes(v.to_vec()))
+  }
+
+  fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Value, E> {
+    Ok(Value::Bytes(v))
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+    let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+    while let Some(item) = seq.next_element()? {
+      items.push(item);
+    }
+    Ok(Value::Array(items))
+  }
+
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+    let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+    while let Some(entry) = map.next_entry()? {
+      entries.push(entry);
+    }
+    Ok(Value::Map(entries))
+  }
+}
+
+#[derive(Debug)]
+pub(crate) enum PathError {
+  /// The parameter is not a proposed state change of an existing account.
+  NotAStateChange,
+
+  /// The current or proposed account state is not valid MessagePack.
+  InvalidState(String),
+}
+
+impl Display for PathError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      PathError::NotAStateChange => {
+        write!(f, "expected a proposed state change of an existing account")
+      }
+      PathError::InvalidState(reason) => {
+        write!(f, "account state is not valid msgpack: {reason}")
+      }
+    }
+  }
+}
+
+/// Decodes the current and proposed state of an account from a proposal
+/// parameter. Only `ReplaceState` proposals have both.
+pub(crate) fn state_change(
+  proposal: &ExpandedParam,
+) -> Result<(Value, Value), PathError> {
+  match proposal {
+    ExpandedParam::ProposalRef(
+      _,
+      ExpandedAccountChange::ReplaceState { current, proposed },
+    ) => Ok((decode(current)?, decode(proposed)?)),
+    _ => Err(PathError::NotAStateChange),
+  }
+}
+
+fn decode(state: &[u8]) -> Result<Value, PathError> {
+  rmp_serde::from_slice(state)
+    .map_err(|e| PathError::InvalidState(e.to_string()))
+}
+
+/// Checks that all differences between two values are within one of the
+/// allowed paths, where an allowed path permits any change in its subtree.
+/// `path` is the location of the compared values, `None` stands for
+/// entries that exist on one side only.
+fn changes_within(
+  current: Option<&Value>,
+  proposed: Option<&Value>,
+  path: &mut Vec<Value>,
+  allowed: &[Vec<Value>],
+) -> bool {
+  if allowed.iter().any(|prefix| path.starts_with(prefix)) {
+    return true;
+  }
+
+  if current == proposed {
+    return true;
+  }
+
+  let (current, proposed, keys) = match (current, proposed) {
+    (Some(c @ Value::Map(current)), Some(p @ Value::Map(proposed))) => {
+      let mut keys: Vec<Value> = Vec::new();
+      for (key, _) in current.iter().chain(proposed.iter()) {
+        if !keys.contains(key) {
+          keys.push(key.clone());
+        }
+      }
+      (c, p, keys)
+    }
+    (Some(c @ Value::Array(current)), Some(p @ Value::Array(proposed))) => {
+      let len = current.len().max(proposed.len()) as i128;
+      (c, p, (0..len).map(Value::Int).collect())
+    }
+    _ => return false,
+  };
+
+  keys.into_iter().all(|key| {
+    let (c, p) = (current.child(&key), proposed.child(&key));
+    path.push(key);
+    let within = changes_within(c, p, path, allowed);
+    path.pop();
+    within
+  })
+}
+
+/// Verifies that a value exists at a given path.
+///
+/// Parameters:
+///   0. MessagePack state
+///   1. Path (array of segments)
+#[predicate]
+fn path_exists(state: Value, path: Vec<Value>) -> bool {
+  state.get(&path).is_some()
+}
+
+/// Verifies that the value at a given path is equal to a given value.
+///
+/// Parameters:
+///   0. MessagePack state
+///   1. Path (array of segments)
+///   2. Expected value
+#[predicate]
+fn path_equals(state: Value, path: Vec<Value>, value: Value) -> bool {
+  state.get(&path) == Some(&value)
+}
+
+/// Verifies that a proposed state change leaves the value at a given path
+/// untouched. A value missing on both sides counts as unchanged.
+///
+/// Parameters:
+///   0. Proposal of an account state change
+///   1. Path (array of segments)
+#[predicate]
+fn path_unchanged(
+  #[param(expanded)] proposal: ExpandedParam,
+  path: Vec<Value>,
+) -> Result<bool, PathError> {
+  let (current, proposed) = state_change(&proposal)?;
+  Ok(current.get(&path) == proposed.get(&path))
+}
+
+/// Verifies that a proposed state change increases the 64bit unsigned
+/// integer at a given path by exactly a given amount.
+///
+/// Parameters:
+///   0. Proposal of an account state change
+///   1. Path (array of segments)
+///   2. Amount (u64)
+#[predicate]
+fn path_uint_greater_than_by(
+  #[param(expanded)] proposal: ExpandedParam,
+  path: Vec<Value>,
+  by: u64,
+) -> Result<bool, PathError> {
+  let (current, proposed) = state_change(&proposal)?;
+  let current = current.get(&path).and_then(Value::as_u64);
+  let proposed = proposed.get(&path).and_then(Value::as_u64);
+  Ok(match (current, proposed) {
+    (Some(current), Some(proposed)) => {
+      proposed.checked_sub(current) == Some(by)
+    }
+    _ => false,
+  })
+}
+
+/// Verifies that a proposed state change decreases the 64bit unsigned
+/// integer at a given path by exactly a given amount.
+///
+/// Parameters:
+///   0. Proposal of an account state change
+///   1. Path (array of segments)
+///   2. Amount (u64)
+#[predicate]
+fn path_uint_less_than_by(
+  #[param(expanded)] proposal: ExpandedParam,
+  path: Vec<Value>,
+  by: u64,
+) -> Result<bool, PathError> {
+  let (current, proposed) = state_change(&proposal)?;
+  let current = current.get(&path).and_then(Value::as_u64);
+  let proposed = proposed.get(&path).and_then(Value::as_u64);
+  Ok(match (current, proposed) {
+    (Some(current), Some(proposed)) => {
+      current.checked_sub(proposed) == Some(by)
+    }
+    _ => false,
+  })
+}
+
+/// Verifies that a proposed state change modifies only values within a
+/// whitelist of paths. A whitelisted path permits any change in its subtree,
+/// including adding and removing entries.
+///
+/// Parameters:
+///   0. Proposal of an account state change
+///   1. Allowed paths (array of paths)
+#[predicate]
+fn only_paths_changed(
+  #[param(expanded)] proposal: ExpandedParam,
+  allowed: Vec<Vec<Value>>,
+) -> Result<bool, PathError> {
+  let (current, proposed) = state_change(&proposal)?;
+  Ok(changes_within(
+    Some(&current),
+    Some(&proposed),
+    &mut Vec::new(),
+    &allowed,
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    anoma_predicates_sdk::{
+      testing::{ContextBuilder, ParamsBuilder},
+      ExpandedAccountChange,
+    },
+    serde::Serialize,
+    std::collections::BTreeMap,
+  };
+
+  #[derive(Serialize)]
+  struct Pool {
+    owner: String,
+    fee_bps: u16,
+    reserves: BTreeMap<String, u64>,
+    history: Vec<u64>,
+  }
+
+  fn pool(usdx: u64, eth: u64, history: Vec<u64>) -> Pool {
+    Pool {
+      owner: "alice.eth".into(),
+      fee_bps: 30,
+      reserves: [("usdx".into(), usdx), ("eth".into(), eth)]
+        .into_iter()
+        .collect(),
+      history,
+    }
+  }
+
+  fn at(state: &Pool, path: &impl Serialize) -> ParamsBuilder {
+    ParamsBuilder::new()
+      .raw(rmp_serde::to_vec_named(state).unwrap())
+      .inline(path)
+  }
+
+  fn change(current: &Pool, proposed: &Pool) -> ParamsBuilder {
+    ParamsBuilder::new().proposal(
+      "/amm/pool".parse().unwrap(),
+      ExpandedAccountChange::ReplaceState {
+        current: rmp_serde::to_vec_named(current).unwrap(),
+        proposed: rmp_serde::to_vec_named(proposed).unwrap(),
+      },
+    )
+  }
+
+  #[test]
+  fn navigate_paths() {
+    let state = pool(100, 2, vec![7, 8]);
+    let context = ContextBuilder::new().build();
+    let exists =
+      |params: ParamsBuilder| super::path_exists(&params.build(), &context);
+
+    assert!(exists(at(&state, &("reserves", "usdx"))));
+    assert!(exists(at(&state, &("history", 1))));
+    assert!(exists(at(&state, &[0u8; 0])));
+    assert!(!exists(at(&state, &("history", 2))));
+    assert!(!exists(at(&state, &("reserves", "btc"))));
+    assert!(!exists(at(&state, &("owner", 0))));
+
+    let params = at(&state, &("reserves", "usdx")).inline(&100u64).build();
+    assert!(super::path_equals(&params, &context));
+
+    let params = at(&state, &("owner",)).inline("alice.eth").build();
+    assert!(super::path_equals(&params, &context));
+
+    // integers compare by value, not by their encoding
+    let params = at(&state, &("history", 0u64)).inline(&7u8).build();
+    assert!(super::path_equals(&params, &context));
+
+    let params = at(&state, &("fee_bps",)).inline(&31u16).build();
+    assert!(!super::path_equals(&params, &context));
+  }
+
+  #[test]
+  fn compare_current_and_proposed() {
+    let context = ContextBuilder::new().build();
+    let swap = || change(&pool(100, 2, vec![]), &pool(150, 1, vec![]));
+
+    let params = swap().inline(&("reserves", "usdx")).build();
+    assert!(!super::path_unchanged(&params, &context));
+
+    let params = swap().inline(&("owner",)).build();
+    assert!(super::path_unchanged(&params, &context));
+
+    let params = swap().inline(&("reserves", "usdx")).inline(&50u64).build();
+    assert!(super::path_uint_greater_than_by(&params, &context));
+    assert!(!super::path_uint_less_than_by(&params, &context));
+
+    let params = swap().inline(&("reserves", "eth")).inline(&1u64).build();
+    assert!(super::path_uint_less_than_by(&params, &context));
+    assert!(!super::path_uint_greater_than_by(&params, &context));
+
+    // not a number
+    let params = swap().inline(&("owner",)).inline(&0u64).build();
+    assert!(!super::path_uint_greater_than_by(&params, &context));
+
+    // only proposals with both current and proposed state
+    let state = rmp_serde::to_vec_named(&pool(1, 1, vec![])).unwrap();
+    let params = ParamsBuilder::new().raw(state).inline(&("owner",)).build();
+    assert!(!super::path_unchanged(&params, &context));
+  }
+
+  #[test]
+  fn whitelisted_changes() {
+    let context = ContextBuilder::new().build();
+    let only = |params: ParamsBuilder| {
+      super::only_paths_changed(&params.build(), &context)
+    };
+
+    let reserves = [["reserves"]];
+    let usdx = [("reserves", "usdx")];
+
+    let swap = || change(&pool(100, 2, vec![]), &pool(150, 1, vec![]));
+    assert!(only(swap().inline(&reserves)));
+    assert!(!only(swap().inline(&usdx)));
+
+    let deposit = change(&pool(100, 2, vec![]), &pool(150, 2, vec![]));
+    assert!(only(deposit.inline(&usdx)));
+
+    // appending to an array that is not whitelisted
+    let log = || change(&pool(100, 2, vec![1]), &pool(150, 2, vec![1, 2]));
+    assert!(!only(log().inline(&usdx)));
+    assert!(only(log().inline(&(("reserves",), ("history", 1)))));
+
+    let mut proposed = pool(100, 2, vec![]);
+    proposed.owner = "mallory.eth".into();
+    let takeover = change(&pool(100, 2, vec![]), &proposed);
+    assert!(!only(takeover.inline(&reserves)));
+
+    // nothing changed, nothing whitelisted
+    let noop = change(&pool(1, 1, vec![]), &pool(1, 1, vec![]));
+    assert!(only(noop.inline(&[[0u8; 0]; 0])));
+  }
+}
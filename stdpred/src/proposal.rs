@@ -0,0 +1,234 @@
+//! Predicates over proposed account changes.
+//!
+//! Instead of receiving the before and after values of an account as two
+//! separate `AccountRef` and `ProposalRef` params, those predicates take
+//! an account address and inspect the whole proposed change in
+//! `PredicateContext::proposals`. They reject if the transaction does not
+//! propose the expected kind of change for that account.
+
+use {
+  alloc::{
+    string::{String, ToString},
+    vec::Vec,
+  },
+  anoma_predicates_sdk::{
+    predicate,
+    Address,
+    ExpandedAccountChange,
+    PredicateContext,
+  },
+  core::fmt::{self, Display},
+};
+
+#[derive(Debug)]
+enum ProposalError {
+  /// The current or proposed state of an account is not a u64.
+  InvalidState(Address, String),
+}
+
+impl Display for ProposalError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ProposalError::InvalidState(address, reason) => {
+        write!(f, "state of {address} is not a valid u64: {reason}")
+      }
+    }
+  }
+}
+
+fn decode_u64(address: &Address, state: &[u8]) -> Result<u64, ProposalError> {
+  rmp_serde::from_slice(state)
+    .map_err(|e| ProposalError::InvalidState(address.clone(), e.to_string()))
+}
+
+/// Verifies that the transaction replaces the state of an account holding a
+/// 64bit unsigned integer so that it changes by exactly the expected delta,
+/// e.g. `-100` for a withdrawal of 100 tokens from a wallet.
+///
+/// Parameters:
+///   0. Account address
+///   1. Expected delta (i64)
+#[predicate]
+fn state_delta_u64(
+  address: Address,
+  delta: i64,
+  context: &PredicateContext,
+) -> Result<bool, ProposalError> {
+  match context.proposals.get(&address) {
+    Some(ExpandedAccountChange::ReplaceState { current, proposed }) => {
+      let current = decode_u64(&address, current)?;
+      let proposed = decode_u64(&address, proposed)?;
+      Ok(i128::from(proposed) - i128::from(current) == i128::from(delta))
+    }
+    _ => Ok(false),
+  }
+}
+
+/// Verifies that the transaction creates an account with the exact given
+/// state, regardless of its predicates.
+///
+/// Parameters:
+///   0. Account address
+///   1. Expected state (raw bytes)
+#[predicate]
+fn account_created_with(
+  address: Address,
+  #[param(raw)] state: Vec<u8>,
+  context: &PredicateContext,
+) -> bool {
+  match context.proposals.get(&address) {
+    Some(ExpandedAccountChange::CreateAccount(account)) => {
+      account.state == state
+    }
+    _ => false,
+  }
+}
+
+/// Verifies that the transaction deletes an account.
+///
+/// Parameters:
+///   0. Account address
+#[predicate]
+fn account_deleted(address: Address, context: &PredicateContext) -> bool {
+  matches!(
+    context.proposals.get(&address),
+    Some(ExpandedAccountChange::DeleteAccount { .. })
+  )
+}
+
+/// Verifies that the transaction leaves the predicates of an existing
+/// account as they are. Passes if the account is not changed at all or
+/// only its state is replaced, rejects accounts being created or deleted.
+///
+/// Parameters:
+///   0. Account address
+#[predicate]
+fn predicates_unchanged(address: Address, context: &PredicateContext) -> bool {
+  match context.proposals.get(&address) {
+    None | Some(ExpandedAccountChange::ReplaceState { .. }) => true,
+    Some(ExpandedAccountChange::ReplacePredicates { current, proposed }) => {
+      current == proposed
+    }
+    Some(ExpandedAccountChange::CreateAccount(_))
+    | Some(ExpandedAccountChange::DeleteAccount { .. }) => false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use anoma_predicates_sdk::{
+    testing::{ContextBuilder, ParamsBuilder},
+    Account,
+    Address,
+    Code,
+    ExpandedAccountChange,
+    Param,
+    Predicate,
+    PredicateTree,
+  };
+
+  fn wallet() -> Address {
+    "/token/usdx/alice.eth".parse().unwrap()
+  }
+
+  fn predicates(entrypoint: &str) -> PredicateTree {
+    PredicateTree::Id(Predicate {
+      code: Code::AccountRef("/stdpred/v1".parse().unwrap(), entrypoint.into()),
+      params: vec![Param::Inline(vec![1, 2, 3])],
+    })
+  }
+
+  #[test]
+  fn state_delta() {
+    let context = ContextBuilder::new()
+      .replace_state(wallet(), &500u64, &400u64)
+      .build();
+    let delta = |delta: i64| {
+      let params = ParamsBuilder::new().inline(&wallet()).inline(&delta);
+      super::state_delta_u64(&params.build(), &context)
+    };
+
+    assert!(delta(-100));
+    assert!(!delta(100));
+    assert!(!delta(0));
+
+    let context = ContextBuilder::new()
+      .replace_state(wallet(), &0u64, &u64::MAX)
+      .build();
+    let params = ParamsBuilder::new().inline(&wallet()).inline(&i64::MAX);
+    assert!(!super::state_delta_u64(&params.build(), &context));
+
+    // not a number
+    let context = ContextBuilder::new()
+      .replace_state(wallet(), "alice", "bob")
+      .build();
+    let params = ParamsBuilder::new().inline(&wallet()).inline(&0i64);
+    assert!(!super::state_delta_u64(&params.build(), &context));
+
+    // the account is not changed by the transaction
+    let context = ContextBuilder::new().build();
+    let params = ParamsBuilder::new().inline(&wallet()).inline(&0i64);
+    assert!(!super::state_delta_u64(&params.build(), &context));
+  }
+
+  #[test]
+  fn created_and_deleted() {
+    let created = ContextBuilder::new()
+      .create_account(wallet(), &100u64, predicates("uint_equal"))
+      .build();
+    let deleted = ContextBuilder::new()
+      .delete_account(wallet(), Account {
+        state: rmp_serde::to_vec(&100u64).unwrap(),
+        predicates: predicates("uint_equal"),
+      })
+      .build();
+
+    let with = |state: u64| {
+      ParamsBuilder::new()
+        .inline(&wallet())
+        .inline(&state)
+        .build()
+    };
+    assert!(super::account_created_with(&with(100), &created));
+    assert!(!super::account_created_with(&with(101), &created));
+    assert!(!super::account_created_with(&with(100), &deleted));
+
+    let params = ParamsBuilder::new().inline(&wallet()).build();
+    assert!(super::account_deleted(&params, &deleted));
+    assert!(!super::account_deleted(&params, &created));
+  }
+
+  #[test]
+  fn unchanged_predicates() {
+    let params = ParamsBuilder::new().inline(&wallet()).build();
+    let unchanged = |context| super::predicates_unchanged(&params, &context);
+
+    assert!(unchanged(ContextBuilder::new().build()));
+    assert!(unchanged(
+      ContextBuilder::new()
+        .replace_state(wallet(), &1u64, &2u64)
+        .build()
+    ));
+    assert!(unchanged(
+      ContextBuilder::new()
+        .proposal(wallet(), ExpandedAccountChange::ReplacePredicates {
+          current: predicates("uint_equal"),
+          proposed: predicates("uint_equal"),
+        })
+        .build()
+    ));
+    assert!(!unchanged(
+      ContextBuilder::new()
+        .proposal(wallet(), ExpandedAccountChange::ReplacePredicates {
+          current: predicates("uint_equal"),
+          proposed: predicates("constant_true"),
+        })
+        .build()
+    ));
+    assert!(!unchanged(
+      ContextBuilder::new()
+        .create_account(wallet(), &1u64, predicates("uint_equal"))
+        .build()
+    ));
+  }
+}
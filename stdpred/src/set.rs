@@ -1,31 +1,19 @@
 use {
   alloc::{collections::BTreeSet, vec::Vec},
-  anoma_predicates_sdk::{predicate, ExpandedParam, PredicateContext},
+  anoma_predicates_sdk::predicate,
 };
 
+/// Verifies that a set of bytestrings has no elements.
 #[predicate]
-fn is_empty_set(params: &Vec<ExpandedParam>, _: &PredicateContext) -> bool {
-  assert_eq!(params.len(), 1);
-
-  let mut it = params.iter();
-  let first = it.next().expect("asserted").data();
-
-  let set: BTreeSet<Vec<u8>> = rmp_serde::from_slice(first)
-    .expect("Invalid collection format. Expecting BTreeSet<Vec<u8>>.");
-
+fn is_empty_set(set: BTreeSet<Vec<u8>>) -> bool {
   set.is_empty()
 }
 
+/// Verifies that a set of bytestrings contains a given element.
 #[predicate]
-fn contains_element(params: &Vec<ExpandedParam>, _: &PredicateContext) -> bool {
-  assert_eq!(params.len(), 2);
-
-  let mut it = params.iter();
-  let haystack = it.next().expect("asserted").data();
-  let needle = it.next().expect("asserted").data();
-
-  let set: BTreeSet<Vec<u8>> = rmp_serde::from_slice(haystack)
-    .expect("Invalid collection format. Expecting BTreeSet<Vec<u8>>.");
-
-  set.contains(needle)
+fn contains_element(
+  haystack: BTreeSet<Vec<u8>>,
+  #[param(raw)] needle: Vec<u8>,
+) -> bool {
+  haystack.contains(&needle)
 }
@@ -0,0 +1,239 @@
+use {
+  alloc::{collections::BTreeSet, string::String, vec::Vec},
+  anoma_predicates_sdk::{predicate, PredicateContext},
+  core::fmt::{self, Display},
+  ed25519_dalek::{PublicKey, Signature, SignatureError, Verifier},
+};
+
+mod secp256k1;
+mod webauthn;
+
+#[derive(Debug)]
+enum ThresholdError {
+  DuplicateSigner(String),
+  WeightOverflow,
+}
+
+impl Display for ThresholdError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ThresholdError::DuplicateSigner(pubkey) => {
+        write!(f, "signer {pubkey} is listed more than once")
+      }
+      ThresholdError::WeightOverflow => write!(f, "signers weight overflow"),
+    }
+  }
+}
+
+/// Verifies that the transaction includes an intent that contains a signature
+/// for a given public key. The signature should be in calldata under a string
+/// key that is the base58 representation of the required signing pubkey.
+#[predicate]
+fn require_ed25519_signature(
+  #[param(raw)] pubkey: Vec<u8>,
+  context: &PredicateContext,
+) -> Result<bool, SignatureError> {
+  let pubkey = PublicKey::from_bytes(&pubkey)?;
+  let expected_calldata_key = bs58::encode(&pubkey.as_bytes()).into_string();
+  for (hash, calldata) in &context.calldata {
+    if let Some(signature) = calldata.get(&expected_calldata_key) {
+      if let Ok(signature) = Signature::from_bytes(signature) {
+        return Ok(pubkey.verify(&hash.to_bytes(), &signature).is_ok());
+      }
+    }
+  }
+  Ok(false)
+}
+
+/// Checks if any intent in the transaction carries a valid signature of its
+/// hash by the given public key, under the base58 representation of the key.
+pub(crate) fn has_valid_signature(
+  pubkey: &PublicKey,
+  context: &PredicateContext,
+) -> bool {
+  let calldata_key = bs58::encode(pubkey.as_bytes()).into_string();
+  context.calldata.iter().any(|(hash, calldata)| {
+    calldata
+      .get(&calldata_key)
+      .and_then(|signature| Signature::from_bytes(signature).ok())
+      .map(|signature| pubkey.verify(&hash.to_bytes(), &signature).is_ok())
+      .unwrap_or(false)
+  })
+}
+
+/// Decodes an ed25519 public key from its base58 representation, the form
+/// in which keys are stored in account state and used as calldata keys.
+pub(crate) fn decode_pubkey(pubkey: &str) -> Option<PublicKey> {
+  let bytes = bs58::decode(pubkey).into_vec().ok()?;
+  PublicKey::from_bytes(&bytes).ok()
+}
+
+/// Verifies that at least `threshold` of the listed public keys have signed
+/// any of the intents in the transaction (k-of-n multisig).
+///
+/// Signatures are expected in the calldata of intents under the base58
+/// representation of the signing pubkey, same as in
+/// `require_ed25519_signature`. A key that signed several intents is counted
+/// only once.
+///
+/// Parameters:
+///   0. Minimum number of distinct signers
+///   1. List of ed25519 public keys
+#[predicate]
+fn require_threshold_signatures(
+  threshold: u64,
+  pubkeys: Vec<PublicKey>,
+  context: &PredicateContext,
+) -> Result<bool, ThresholdError> {
+  let signers = pubkeys.into_iter().map(|pubkey| (pubkey, 1)).collect();
+  weighted_signatures(threshold, signers, context)
+}
+
+/// Verifies that the total weight of the listed public keys that have signed
+/// any of the intents in the transaction is at least `threshold`.
+///
+/// Signatures are expected in the calldata of intents under the base58
+/// representation of the signing pubkey. A key that signed several intents
+/// contributes its weight only once.
+///
+/// Parameters:
+///   0. Minimum total weight of signers
+///   1. List of (ed25519 public key, weight) pairs
+#[predicate]
+fn require_weighted_signatures(
+  threshold: u64,
+  signers: Vec<(PublicKey, u64)>,
+  context: &PredicateContext,
+) -> Result<bool, ThresholdError> {
+  weighted_signatures(threshold, signers, context)
+}
+
+fn weighted_signatures(
+  threshold: u64,
+  signers: Vec<(PublicKey, u64)>,
+  context: &PredicateContext,
+) -> Result<bool, ThresholdError> {
+  let mut seen = BTreeSet::new();
+  let mut weight = 0u64;
+  for (pubkey, signer_weight) in signers {
+    if !seen.insert(pubkey.to_bytes()) {
+      return Err(ThresholdError::DuplicateSigner(
+        bs58::encode(pubkey.as_bytes()).into_string(),
+      ));
+    }
+    if has_valid_signature(&pubkey, context) {
+      weight = weight
+        .checked_add(signer_weight)
+        .ok_or(ThresholdError::WeightOverflow)?;
+    }
+  }
+  Ok(weight >= threshold)
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    anoma_predicates_sdk::testing::{
+      intent_hash,
+      ContextBuilder,
+      ParamsBuilder,
+    },
+    ed25519_dalek::{Keypair, Signer},
+    rand::rngs::OsRng,
+  };
+
+  #[test]
+  fn ed25519_signature() {
+    let signer = Keypair::generate(&mut OsRng);
+    let other = Keypair::generate(&mut OsRng);
+
+    let context = ContextBuilder::new()
+      .signature(intent_hash(b"intent-1"), &signer)
+      .build();
+
+    let params = ParamsBuilder::new().raw(signer.public.to_bytes()).build();
+    assert!(super::require_ed25519_signature(&params, &context));
+
+    let params = ParamsBuilder::new().raw(other.public.to_bytes()).build();
+    assert!(!super::require_ed25519_signature(&params, &context));
+  }
+
+  #[test]
+  fn forged_signature_rejects() {
+    let signer = Keypair::generate(&mut OsRng);
+    let forger = Keypair::generate(&mut OsRng);
+    let intent = intent_hash(b"intent-1");
+
+    // signature made by a different key stored under the signer's pubkey.
+    let forged = forger.sign(&intent.to_bytes());
+    let context = ContextBuilder::new()
+      .calldata(
+        intent,
+        bs58::encode(signer.public.as_bytes()).into_string(),
+        forged.to_bytes(),
+      )
+      .build();
+
+    let params = ParamsBuilder::new().raw(signer.public.to_bytes()).build();
+    assert!(!super::require_ed25519_signature(&params, &context));
+  }
+
+  #[test]
+  fn threshold_signatures() {
+    let keys: Vec<_> = (0..3).map(|_| Keypair::generate(&mut OsRng)).collect();
+    let pubkeys: Vec<_> = keys.iter().map(|k| k.public).collect();
+
+    // the first signer signs two intents, it is counted once.
+    let context = ContextBuilder::new()
+      .signature(intent_hash(b"intent-1"), &keys[0])
+      .signature(intent_hash(b"intent-2"), &keys[0])
+      .build();
+
+    let two_of_three = ParamsBuilder::new().inline(&2u64).inline(&pubkeys);
+    assert!(!super::require_threshold_signatures(
+      &two_of_three.build(),
+      &context
+    ));
+
+    let context = ContextBuilder::new()
+      .signature(intent_hash(b"intent-1"), &keys[0])
+      .signature(intent_hash(b"intent-2"), &keys[2])
+      .build();
+
+    let two_of_three = ParamsBuilder::new().inline(&2u64).inline(&pubkeys);
+    assert!(super::require_threshold_signatures(
+      &two_of_three.build(),
+      &context
+    ));
+
+    // the same key listed twice must not count twice.
+    let duplicates = vec![pubkeys[0], pubkeys[0]];
+    let params = ParamsBuilder::new().inline(&2u64).inline(&duplicates);
+    assert!(!super::require_threshold_signatures(
+      &params.build(),
+      &context
+    ));
+  }
+
+  #[test]
+  fn weighted_signatures() {
+    let keys: Vec<_> = (0..3).map(|_| Keypair::generate(&mut OsRng)).collect();
+    let signers = vec![
+      (keys[0].public, 3u64),
+      (keys[1].public, 1u64),
+      (keys[2].public, 1u64),
+    ];
+
+    let context = ContextBuilder::new()
+      .signature(intent_hash(b"intent-1"), &keys[1])
+      .signature(intent_hash(b"intent-2"), &keys[2])
+      .build();
+    let params = ParamsBuilder::new().inline(&3u64).inline(&signers).build();
+    assert!(!super::require_weighted_signatures(&params, &context));
+
+    let context = ContextBuilder::new()
+      .signature(intent_hash(b"intent-1"), &keys[0])
+      .build();
+    assert!(super::require_weighted_signatures(&params, &context));
+  }
+}
@@ -0,0 +1,221 @@
+use {
+  alloc::{format, string::String, vec::Vec},
+  anoma_predicates_sdk::{predicate, Address, PredicateContext},
+  core::fmt::{self, Display},
+  k256::ecdsa::{signature::Verifier, RecoveryId, Signature, VerifyingKey},
+  sha3::{Digest, Keccak256},
+};
+
+/// Verifies that the transaction includes an intent that contains a
+/// secp256k1 ECDSA signature of its hash for a given public key.
+///
+/// The signature is expected in calldata under the base58 representation of
+/// the public key bytes, the same way as in `require_ed25519_signature`. It
+/// is a 64 bytes `r || s` signature of the intent hash bytes with SHA-256 as
+/// the message digest.
+///
+/// Parameters:
+///   0. SEC1 encoded public key, compressed or uncompressed (raw bytes)
+#[predicate]
+fn require_secp256k1_signature(
+  #[param(raw)] pubkey: Vec<u8>,
+  context: &PredicateContext,
+) -> Result<bool, k256::ecdsa::Error> {
+  let key = VerifyingKey::from_sec1_bytes(&pubkey)?;
+  let calldata_key = bs58::encode(&pubkey).into_string();
+  Ok(context.calldata.iter().any(|(hash, calldata)| {
+    calldata
+      .get(&calldata_key)
+      .and_then(|signature| Signature::from_slice(signature).ok())
+      .map(|signature| key.verify(&hash.to_bytes(), &signature).is_ok())
+      .unwrap_or(false)
+  }))
+}
+
+#[derive(Debug)]
+enum EthereumError {
+  InvalidAddress(String),
+}
+
+impl Display for EthereumError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      EthereumError::InvalidAddress(segment) => {
+        write!(f, "'{segment}' is not an ethereum address")
+      }
+    }
+  }
+}
+
+/// Verifies that the transaction includes an intent signed by the owner of
+/// an Ethereum address.
+///
+/// The Ethereum address is taken from the last segment of the given account
+/// address, e.g. `/token/usdx/0x0239d39F0c3F9b26cF728bC6b09872C090935E9f`,
+/// so wallet accounts can be guarded by the same key that controls the
+/// Ethereum account.
+///
+/// The signature is expected in calldata under the base58 representation of
+/// the 20 address bytes. It is a 65 bytes `r || s || v` signature produced
+/// by `personal_sign` (EIP-191) over the intent hash bytes, which is what
+/// Ethereum wallets produce. The signer public key is recovered from the
+/// signature and its address is compared against the expected one.
+///
+/// Parameters:
+///   0. Account address with an ethereum address as its last segment
+#[predicate]
+fn require_ethereum_signature(
+  address: Address,
+  context: &PredicateContext,
+) -> Result<bool, EthereumError> {
+  let expected = ethereum_address(&address)?;
+  let calldata_key = bs58::encode(&expected).into_string();
+  Ok(context.calldata.iter().any(|(hash, calldata)| {
+    calldata
+      .get(&calldata_key)
+      .and_then(|signature| recover_signer(&hash.to_bytes(), signature))
+      .map(|signer| signer == expected)
+      .unwrap_or(false)
+  }))
+}
+
+/// Parses the last segment of an address as a 0x prefixed hex ethereum
+/// address. Checksum casing is not enforced.
+fn ethereum_address(address: &Address) -> Result<[u8; 20], EthereumError> {
+  let address = format!("{address}");
+  let segment = address.rsplit('/').next().unwrap_or_default();
+  let invalid = || EthereumError::InvalidAddress(segment.into());
+  let hex = segment.strip_prefix("0x").ok_or_else(invalid)?.as_bytes();
+
+  if hex.len() != 40 {
+    return Err(invalid());
+  }
+
+  let mut output = [0u8; 20];
+  for (byte, pair) in output.iter_mut().zip(hex.chunks(2)) {
+    let high = hex_digit(pair[0]).ok_or_else(invalid)?;
+    let low = hex_digit(pair[1]).ok_or_else(invalid)?;
+    *byte = high << 4 | low;
+  }
+  Ok(output)
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+  (c as char).to_digit(16).map(|d| d as u8)
+}
+
+/// Recovers the ethereum address of the signer of an EIP-191 personal
+/// message signature.
+fn recover_signer(message: &[u8], signature: &[u8]) -> Option<[u8; 20]> {
+  if signature.len() != 65 {
+    return None;
+  }
+
+  let recid = match signature[64] {
+    v @ (0 | 1) => v,
+    v @ (27 | 28) => v - 27,
+    _ => return None,
+  };
+
+  let recid = RecoveryId::from_byte(recid)?;
+  let signature = Signature::from_slice(&signature[..64]).ok()?;
+  let prehash = personal_message_hash(message);
+  let key =
+    VerifyingKey::recover_from_prehash(&prehash, &signature, recid).ok()?;
+  Some(key_address(&key))
+}
+
+/// keccak256("\x19Ethereum Signed Message:\n" || len(message) || message)
+fn personal_message_hash(message: &[u8]) -> [u8; 32] {
+  let mut hasher = Keccak256::new();
+  hasher.update(b"\x19Ethereum Signed Message:\n");
+  hasher.update(format!("{}", message.len()).as_bytes());
+  hasher.update(message);
+  hasher.finalize().into()
+}
+
+/// Last 20 bytes of keccak256 of the uncompressed public key.
+fn key_address(key: &VerifyingKey) -> [u8; 20] {
+  let point = key.to_encoded_point(false);
+  let hash: [u8; 32] = Keccak256::digest(&point.as_bytes()[1..]).into();
+  let mut address = [0u8; 20];
+  address.copy_from_slice(&hash[12..]);
+  address
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::{key_address, personal_message_hash},
+    anoma_predicates_sdk::{
+      testing::{intent_hash, ContextBuilder, ParamsBuilder},
+      Address,
+    },
+    k256::ecdsa::{signature::Signer, Signature, SigningKey},
+  };
+
+  fn signing_key(seed: u8) -> SigningKey {
+    SigningKey::from_slice(&[seed; 32]).unwrap()
+  }
+
+  #[test]
+  fn secp256k1_signature() {
+    let key = signing_key(7);
+    let pubkey = key
+      .verifying_key()
+      .to_encoded_point(true)
+      .as_bytes()
+      .to_vec();
+    let intent = intent_hash(b"intent-1");
+    let signature: Signature = key.sign(&intent.to_bytes());
+
+    let context = ContextBuilder::new()
+      .calldata(
+        intent,
+        bs58::encode(&pubkey).into_string(),
+        signature.to_bytes().to_vec(),
+      )
+      .build();
+
+    let params = ParamsBuilder::new().raw(pubkey).build();
+    assert!(super::require_secp256k1_signature(&params, &context));
+
+    let other = signing_key(8)
+      .verifying_key()
+      .to_encoded_point(true)
+      .as_bytes()
+      .to_vec();
+    let params = ParamsBuilder::new().raw(other).build();
+    assert!(!super::require_secp256k1_signature(&params, &context));
+  }
+
+  #[test]
+  fn ethereum_signature() {
+    let key = signing_key(7);
+    let eth_address = key_address(key.verifying_key());
+    let intent = intent_hash(b"intent-1");
+
+    let prehash = personal_message_hash(&intent.to_bytes());
+    let (signature, recid) = key.sign_prehash_recoverable(&prehash).unwrap();
+    let mut signature = signature.to_bytes().to_vec();
+    signature.push(recid.to_byte() + 27);
+
+    let context = ContextBuilder::new()
+      .calldata(intent, bs58::encode(eth_address).into_string(), signature)
+      .build();
+
+    let hex: String = eth_address.iter().map(|b| format!("{b:02X}")).collect();
+    let wallet: Address = format!("/token/usdx/0x{hex}").parse().unwrap();
+    let params = ParamsBuilder::new().inline(&wallet).build();
+    assert!(super::require_ethereum_signature(&params, &context));
+
+    let other = "0x0239d39F0c3F9b26cF728bC6b09872C090935E9f";
+    let other: Address = format!("/token/usdx/{other}").parse().unwrap();
+    let params = ParamsBuilder::new().inline(&other).build();
+    assert!(!super::require_ethereum_signature(&params, &context));
+
+    let invalid: Address = "/token/usdx/example.eth".parse().unwrap();
+    let params = ParamsBuilder::new().inline(&invalid).build();
+    assert!(!super::require_ethereum_signature(&params, &context));
+  }
+}
@@ -0,0 +1,216 @@
+use {
+  alloc::{string::String, vec::Vec},
+  anoma_predicates_sdk::{predicate, PredicateContext},
+  base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine},
+  p256::ecdsa::{signature::Verifier, Signature, VerifyingKey},
+  serde::Deserialize,
+  sha2::{Digest, Sha256},
+};
+
+/// WebAuthn assertion produced by an authenticator (e.g. a passkey) in
+/// response to `navigator.credentials.get()`.
+#[derive(Debug, Deserialize)]
+struct Assertion {
+  authenticator_data: Vec<u8>,
+  client_data_json: Vec<u8>,
+  signature: Vec<u8>,
+}
+
+/// The subset of the client data fields that are verified.
+#[derive(Debug, Deserialize)]
+struct ClientData {
+  #[serde(rename = "type")]
+  ty: String,
+  challenge: String,
+  origin: String,
+}
+
+/// The user presence bit in authenticator data flags.
+const USER_PRESENT: u8 = 0x01;
+
+/// Verifies that the transaction includes an intent that was authorized by a
+/// WebAuthn authenticator holding a given P-256 key.
+///
+/// The assertion is expected in calldata under the base58 representation of
+/// the public key bytes, the same way as in `require_ed25519_signature`. It
+/// is a MessagePack encoded array of `[authenticator_data, client_data_json,
+/// signature]`, where the signature is DER encoded. The challenge of the
+/// assertion must be the base64url encoded intent signing hash bytes, the
+/// assertion must be scoped to the expected relying party and origin, and
+/// the authenticator must have verified the user presence.
+///
+/// Parameters:
+///   0. SEC1 encoded P-256 public key (raw bytes)
+///   1. Relying party id the credential is scoped to, e.g. `example.com`
+///   2. Origin of the page that requested the assertion, e.g. `https://example.com`
+#[predicate]
+fn require_webauthn_signature(
+  #[param(raw)] pubkey: Vec<u8>,
+  rp_id: String,
+  origin: String,
+  context: &PredicateContext,
+) -> Result<bool, p256::ecdsa::Error> {
+  let key = VerifyingKey::from_sec1_bytes(&pubkey)?;
+  let calldata_key = bs58::encode(&pubkey).into_string();
+  let relying_party = RelyingParty {
+    id_hash: Sha256::digest(rp_id.as_bytes()).into(),
+    origin,
+  };
+  Ok(context.calldata.iter().any(|(hash, calldata)| {
+    calldata
+      .get(&calldata_key)
+      .and_then(|assertion| rmp_serde::from_slice(assertion).ok())
+      .map(|assertion| {
+        let challenge = URL_SAFE_NO_PAD.encode(hash.to_bytes());
+        verify(&key, &relying_party, &challenge, &assertion)
+      })
+      .unwrap_or(false)
+  }))
+}
+
+/// The party a credential is scoped to.
+struct RelyingParty {
+  id_hash: [u8; 32],
+  origin: String,
+}
+
+fn verify(
+  key: &VerifyingKey,
+  relying_party: &RelyingParty,
+  challenge: &str,
+  assertion: &Assertion,
+) -> bool {
+  // authenticator data starts with sha256(rpId) followed by flags
+  let data = &assertion.authenticator_data;
+  if data.len() < 33 || data[..32] != relying_party.id_hash {
+    return false;
+  }
+
+  if data[32] & USER_PRESENT == 0 {
+    return false;
+  }
+
+  let client_data: ClientData =
+    match serde_json::from_slice(&assertion.client_data_json) {
+      Ok(client_data) => client_data,
+      Err(_) => return false,
+    };
+
+  if client_data.ty != "webauthn.get"
+    || client_data.challenge != challenge
+    || client_data.origin != relying_party.origin
+  {
+    return false;
+  }
+
+  let signature = match Signature::from_der(&assertion.signature) {
+    Ok(signature) => signature,
+    Err(_) => return false,
+  };
+
+  // authenticators sign authenticator_data || sha256(client_data_json)
+  let mut message = assertion.authenticator_data.clone();
+  message.extend_from_slice(&Sha256::digest(&assertion.client_data_json));
+  key.verify(&message, &signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::{Engine, URL_SAFE_NO_PAD},
+    anoma_predicates_sdk::testing::{
+      intent_hash,
+      ContextBuilder,
+      ParamsBuilder,
+    },
+    p256::ecdsa::{signature::Signer, Signature, SigningKey},
+    sha2::{Digest, Sha256},
+  };
+
+  fn assertion(
+    key: &SigningKey,
+    challenge: &[u8],
+    flags: u8,
+    rp_id: &str,
+    origin: &str,
+  ) -> Vec<u8> {
+    let mut authenticator_data = Sha256::digest(rp_id).to_vec();
+    authenticator_data.push(flags);
+    authenticator_data.extend_from_slice(&1u32.to_be_bytes());
+
+    let client_data_json = format!(
+      r#"{{"type":"webauthn.get","challenge":"{}","origin":"{origin}"}}"#,
+      URL_SAFE_NO_PAD.encode(challenge)
+    )
+    .into_bytes();
+
+    let mut message = authenticator_data.clone();
+    message.extend_from_slice(&Sha256::digest(&client_data_json));
+    let signature: Signature = key.sign(&message);
+
+    rmp_serde::to_vec(&(
+      authenticator_data,
+      client_data_json,
+      signature.to_der().as_bytes().to_vec(),
+    ))
+    .unwrap()
+  }
+
+  #[test]
+  fn webauthn_assertion() {
+    let key = SigningKey::from_slice(&[3u8; 32]).unwrap();
+    let pubkey = key
+      .verifying_key()
+      .to_encoded_point(true)
+      .as_bytes()
+      .to_vec();
+    let calldata_key = bs58::encode(&pubkey).into_string();
+    let intent = intent_hash(b"intent-1");
+    let params = ParamsBuilder::new()
+      .raw(pubkey)
+      .inline("example.com")
+      .inline("https://example.com")
+      .build();
+
+    let verify = |challenge: &[u8], flags: u8, rp_id: &str, origin: &str| {
+      let context = ContextBuilder::new()
+        .calldata(
+          intent,
+          calldata_key.clone(),
+          assertion(&key, challenge, flags, rp_id, origin),
+        )
+        .build();
+      super::require_webauthn_signature(&params, &context)
+    };
+
+    let challenge = intent.to_bytes();
+    assert!(verify(
+      &challenge,
+      0x05,
+      "example.com",
+      "https://example.com"
+    ));
+
+    // assertion for a different challenge
+    assert!(!verify(
+      &intent_hash(b"other").to_bytes(),
+      0x05,
+      "example.com",
+      "https://example.com"
+    ));
+
+    // user presence not verified by the authenticator
+    assert!(!verify(
+      &challenge,
+      0x04,
+      "example.com",
+      "https://example.com"
+    ));
+
+    // credential scoped to a different relying party
+    assert!(!verify(&challenge, 0x05, "evil.com", "https://example.com"));
+
+    // assertion requested by a different origin
+    assert!(!verify(&challenge, 0x05, "example.com", "https://evil.com"));
+  }
+}
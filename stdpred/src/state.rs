@@ -0,0 +1,37 @@
+//! Helpers for predicates that keep MessagePack encoded state in accounts
+//! and authorize changes with base58 encoded ed25519 keys stored there.
+
+use {
+  crate::signature::{decode_pubkey, has_valid_signature},
+  alloc::string::{String, ToString},
+  anoma_predicates_sdk::{Address, PredicateContext},
+  serde::de::DeserializeOwned,
+};
+
+/// Failures shared by those predicates, each of them converts it into
+/// its own error type.
+pub enum StateError {
+  /// Account state could not be decoded, with the reason.
+  InvalidState(Address, String),
+
+  /// A key is not a base58 encoded ed25519 public key.
+  InvalidKey(String),
+}
+
+pub fn decode<T: DeserializeOwned>(
+  address: &Address,
+  state: &[u8],
+) -> Result<T, StateError> {
+  rmp_serde::from_slice(state)
+    .map_err(|e| StateError::InvalidState(address.clone(), e.to_string()))
+}
+
+/// Checks if the transaction is signed by a key in its base58 form.
+pub fn signed_by(
+  key: &str,
+  context: &PredicateContext,
+) -> Result<bool, StateError> {
+  let pubkey = decode_pubkey(key)
+    .ok_or_else(|| StateError::InvalidKey(key.to_string()))?;
+  Ok(has_valid_signature(&pubkey, context))
+}
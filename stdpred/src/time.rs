@@ -0,0 +1,207 @@
+//! Time-lock predicates.
+//!
+//! Those predicates look at the block in which the transaction is executed.
+//! Heights are block numbers and timestamps are seconds since unix epoch as
+//! recorded by the block producer.
+
+use {
+  anoma_predicates_sdk::{predicate, PredicateContext},
+  core::fmt::{self, Display},
+};
+
+/// Verifies that the transaction is executed in a block with a height
+/// greater than the given height.
+///
+/// Parameters:
+///   0. Block height (u64)
+#[predicate]
+fn after_height(height: u64, context: &PredicateContext) -> bool {
+  context.block.height > height
+}
+
+/// Verifies that the transaction is executed in a block with a height
+/// less than the given height, e.g. "valid until block N".
+///
+/// Parameters:
+///   0. Block height (u64)
+#[predicate]
+fn before_height(height: u64, context: &PredicateContext) -> bool {
+  context.block.height < height
+}
+
+/// Verifies that the transaction is executed in a block with a height
+/// within the inclusive range [start, end].
+///
+/// Parameters:
+///   0. First allowed block height (u64)
+///   1. Last allowed block height (u64)
+#[predicate]
+fn between_heights(start: u64, end: u64, context: &PredicateContext) -> bool {
+  start <= context.block.height && context.block.height <= end
+}
+
+/// Verifies that the transaction is executed in a block produced after the
+/// given timestamp.
+///
+/// Parameters:
+///   0. Unix timestamp in seconds (u64)
+#[predicate]
+fn after_timestamp(timestamp: u64, context: &PredicateContext) -> bool {
+  context.block.timestamp > timestamp
+}
+
+/// Verifies that the transaction is executed in a block produced before the
+/// given timestamp.
+///
+/// Parameters:
+///   0. Unix timestamp in seconds (u64)
+#[predicate]
+fn before_timestamp(timestamp: u64, context: &PredicateContext) -> bool {
+  context.block.timestamp < timestamp
+}
+
+/// Verifies that the transaction is executed in a block produced within the
+/// inclusive time window [start, end], e.g. a campaign duration.
+///
+/// Parameters:
+///   0. Window start as unix timestamp in seconds (u64)
+///   1. Window end as unix timestamp in seconds (u64)
+#[predicate]
+fn between_timestamps(
+  start: u64,
+  end: u64,
+  context: &PredicateContext,
+) -> bool {
+  start <= context.block.timestamp && context.block.timestamp <= end
+}
+
+#[derive(Debug)]
+enum VestingError {
+  InvalidSchedule { start: u64, cliff: u64, end: u64 },
+}
+
+impl Display for VestingError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      VestingError::InvalidSchedule { start, cliff, end } => write!(
+        f,
+        "invalid vesting schedule start: {start}, cliff: {cliff}, end: {end}"
+      ),
+    }
+  }
+}
+
+/// Verifies that a balance does not go below the amount that is still
+/// locked by a linear vesting schedule.
+///
+/// Nothing is vested before the cliff, after the cliff the vested amount
+/// grows linearly from the start of the schedule and everything is vested
+/// at the end of it. The balance must always be at least `total - vested`
+/// at the timestamp of the block in which the transaction is executed.
+///
+/// Parameters:
+///   0. Total amount subject to vesting (u64)
+///   1. Vesting start as unix timestamp in seconds (u64)
+///   2. Cliff as unix timestamp in seconds (u64)
+///   3. Vesting end as unix timestamp in seconds (u64)
+///   4. Balance to check, usually the proposed balance of a wallet (u64)
+#[predicate]
+fn vesting_schedule(
+  total: u64,
+  start: u64,
+  cliff: u64,
+  end: u64,
+  balance: u64,
+  context: &PredicateContext,
+) -> Result<bool, VestingError> {
+  if !(start <= cliff && cliff <= end && start < end) {
+    return Err(VestingError::InvalidSchedule { start, cliff, end });
+  }
+
+  let now = context.block.timestamp;
+  let vested = if now < cliff {
+    0
+  } else if now >= end {
+    total
+  } else {
+    // never overflows, because elapsed < duration
+    let elapsed = (now - start) as u128;
+    let duration = (end - start) as u128;
+    (total as u128 * elapsed / duration) as u64
+  };
+
+  Ok(balance >= total - vested)
+}
+
+#[cfg(test)]
+mod tests {
+  use anoma_predicates_sdk::testing::{ContextBuilder, ParamsBuilder};
+
+  #[test]
+  fn heights() {
+    let context = ContextBuilder::new().block(100, 0).build();
+    let at = |h: u64| ParamsBuilder::new().inline(&h).build();
+
+    assert!(super::after_height(&at(99), &context));
+    assert!(!super::after_height(&at(100), &context));
+    assert!(super::before_height(&at(101), &context));
+    assert!(!super::before_height(&at(100), &context));
+
+    let window =
+      |a: u64, b: u64| ParamsBuilder::new().inline(&a).inline(&b).build();
+    assert!(super::between_heights(&window(100, 100), &context));
+    assert!(super::between_heights(&window(90, 110), &context));
+    assert!(!super::between_heights(&window(101, 110), &context));
+  }
+
+  #[test]
+  fn timestamps() {
+    let context = ContextBuilder::new().block(1, 1_700_000_000).build();
+    let at = |t: u64| ParamsBuilder::new().inline(&t).build();
+
+    assert!(super::after_timestamp(&at(1_699_999_999), &context));
+    assert!(!super::before_timestamp(&at(1_700_000_000), &context));
+
+    let window = ParamsBuilder::new()
+      .inline(&1_690_000_000u64)
+      .inline(&1_710_000_000u64)
+      .build();
+    assert!(super::between_timestamps(&window, &context));
+  }
+
+  #[test]
+  fn vesting() {
+    // 1000 tokens vesting over 100 seconds with a cliff at 25 seconds.
+    let schedule = |balance: u64| {
+      ParamsBuilder::new()
+        .inline(&1000u64)
+        .inline(&100u64)
+        .inline(&125u64)
+        .inline(&200u64)
+        .inline(&balance)
+        .build()
+    };
+    let at = |t: u64| ContextBuilder::new().block(1, t).build();
+
+    // before the cliff everything is locked
+    assert!(super::vesting_schedule(&schedule(1000), &at(120)));
+    assert!(!super::vesting_schedule(&schedule(999), &at(120)));
+
+    // half way through half is vested
+    assert!(super::vesting_schedule(&schedule(500), &at(150)));
+    assert!(!super::vesting_schedule(&schedule(499), &at(150)));
+
+    // after the end everything is vested
+    assert!(super::vesting_schedule(&schedule(0), &at(200)));
+
+    // cliff after the end
+    let invalid = ParamsBuilder::new()
+      .inline(&1000u64)
+      .inline(&100u64)
+      .inline(&300u64)
+      .inline(&200u64)
+      .inline(&1000u64)
+      .build();
+    assert!(!super::vesting_schedule(&invalid, &at(150)));
+  }
+}
@@ -0,0 +1,640 @@
+//! Fungible tokens.
+//!
+//! A token instance is an account, e.g. `/token/usdx`, and every account
+//! under it, e.g. `/token/usdx/alice.eth`, is a wallet holding a balance of
+//! that token. All state is MessagePack with named fields:
+//!
+//! ```text
+//! token account:  { "authority": <base58 ed25519 pubkey>, "supply": u64 }
+//! wallet account: { "balance": u64, "frozen": bool,
+//!                   "allowances": { <base58 spender pubkey>: u64 } }
+//! ```
+//!
+//! The token account is guarded by `token(AccountRef(token))`, which runs
+//! for every transaction touching the token or any of its wallets and
+//! enforces the supply invariants:
+//!   - the supply always changes by the difference between the sum of all
+//!     proposed and current balances, so minting and burning is only possible
+//!     along with an update of the supply,
+//!   - minting, burning, freezing and unfreezing wallets and rotating the
+//!     authority must be signed by the current authority,
+//!   - frozen wallets can't spend unless the authority signs,
+//!   - the predicates of the token account are immutable.
+//!
+//! Wallets are guarded by `token_wallet(AccountRef(wallet), owner)`, or any
+//! other predicate that suits their owner. It lets anyone credit the wallet
+//! and requires the owner signature for everything else, except spending
+//! within an allowance, which must be signed by the spender instead.
+//!
+//! Intents express expected outcomes using `token_sent` and
+//! `token_received` on wallet addresses.
+
+use {
+  crate::state::{decode, signed_by, StateError},
+  alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+    vec::Vec,
+  },
+  anoma_predicates_sdk::{
+    predicate,
+    Address,
+    ExpandedAccountChange,
+    ExpandedParam,
+    PredicateContext,
+  },
+  core::fmt::{self, Display},
+  serde::Deserialize,
+};
+
+/// State of a token account.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct Token {
+  /// base58 encoded ed25519 public key of the mint authority.
+  authority: String,
+
+  /// Sum of balances of all wallets of this token.
+  supply: u64,
+}
+
+/// State of a wallet account.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+struct Wallet {
+  balance: u64,
+
+  #[serde(default)]
+  frozen: bool,
+
+  /// Amounts that spenders, identified by their base58 encoded ed25519
+  /// public key, are allowed to spend from this wallet.
+  #[serde(default)]
+  allowances: BTreeMap<String, u64>,
+}
+
+#[derive(Debug)]
+enum TokenError {
+  NotAnAccountRef,
+  InvalidState(Address, String),
+  InvalidKey(String),
+  SupplyMismatch { expected: i128, actual: i128 },
+  Unauthorized(&'static str),
+  Frozen(Address),
+  ImmutablePredicates,
+}
+
+impl Display for TokenError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TokenError::NotAnAccountRef => {
+        write!(f, "expected an account reference to the token or wallet")
+      }
+      TokenError::InvalidState(address, reason) => {
+        write!(f, "invalid token state of {address}: {reason}")
+      }
+      TokenError::InvalidKey(key) => {
+        write!(f, "'{key}' is not a base58 encoded ed25519 public key")
+      }
+      TokenError::SupplyMismatch { expected, actual } => write!(
+        f,
+        "supply must change by {expected} to reflect balances, got {actual}"
+      ),
+      TokenError::Unauthorized(action) => {
+        write!(f, "{action} requires a signature of the mint authority")
+      }
+      TokenError::Frozen(address) => {
+        write!(f, "wallet {address} is frozen")
+      }
+      TokenError::ImmutablePredicates => {
+        write!(f, "token predicates are immutable")
+      }
+    }
+  }
+}
+
+impl From<StateError> for TokenError {
+  fn from(error: StateError) -> Self {
+    match error {
+      StateError::InvalidState(address, reason) => {
+        TokenError::InvalidState(address, reason)
+      }
+      StateError::InvalidKey(key) => TokenError::InvalidKey(key),
+    }
+  }
+}
+
+/// The current and proposed state of a wallet, `None` for wallets that
+/// don't exist before or after the transaction.
+fn wallet_change(
+  address: &Address,
+  change: &ExpandedAccountChange,
+) -> Result<(Option<Wallet>, Option<Wallet>), TokenError> {
+  Ok(match change {
+    ExpandedAccountChange::CreateAccount(account) => {
+      (None, Some(decode(address, &account.state)?))
+    }
+    ExpandedAccountChange::ReplaceState { current, proposed } => (
+      Some(decode(address, current)?),
+      Some(decode(address, proposed)?),
+    ),
+    ExpandedAccountChange::ReplacePredicates { .. } => (None, None),
+    ExpandedAccountChange::DeleteAccount { current } => {
+      (Some(decode(address, &current.state)?), None)
+    }
+  })
+}
+
+/// Guards a token account and the supply invariants of all its wallets.
+///
+/// Parameters:
+///   0. AccountRef to the token account itself
+#[predicate]
+fn token(
+  #[param(expanded)] token: ExpandedParam,
+  context: &PredicateContext,
+) -> Result<bool, TokenError> {
+  let ExpandedParam::AccountRef(address, state) = token else {
+    return Err(TokenError::NotAnAccountRef);
+  };
+
+  let current: Token = decode(&address, &state)?;
+  let authorized = signed_by(&current.authority, context)?;
+
+  let proposed = match context.proposals.get(&address) {
+    None => current.clone(),
+    Some(ExpandedAccountChange::ReplaceState { proposed, .. }) => {
+      decode(&address, proposed)?
+    }
+    // a token can be removed once all its tokens are burnt
+    Some(ExpandedAccountChange::DeleteAccount { .. }) => Token {
+      supply: 0,
+      ..current.clone()
+    },
+    Some(_) => return Err(TokenError::ImmutablePredicates),
+  };
+
+  if proposed.authority != current.authority && !authorized {
+    return Err(TokenError::Unauthorized("changing the authority"));
+  }
+
+  let mut delta: i128 = 0;
+  for (wallet, change) in &context.proposals {
+    if !address.is_parent_of(wallet) {
+      continue;
+    }
+
+    let (before, after) = wallet_change(wallet, change)?;
+    let before_balance = before.as_ref().map(|w| w.balance).unwrap_or(0);
+    let after_balance = after.as_ref().map(|w| w.balance).unwrap_or(0);
+    delta += i128::from(after_balance) - i128::from(before_balance);
+
+    let was_frozen = before.as_ref().map(|w| w.frozen).unwrap_or(false);
+    let is_frozen = after.as_ref().map(|w| w.frozen).unwrap_or(false);
+    if was_frozen != is_frozen && !authorized {
+      return Err(TokenError::Unauthorized("freezing or unfreezing a wallet"));
+    }
+
+    if was_frozen && after_balance < before_balance && !authorized {
+      return Err(TokenError::Frozen(wallet.clone()));
+    }
+  }
+
+  let supply_delta = i128::from(proposed.supply) - i128::from(current.supply);
+  if supply_delta != delta {
+    return Err(TokenError::SupplyMismatch {
+      expected: delta,
+      actual: supply_delta,
+    });
+  }
+
+  if delta != 0 && !authorized {
+    return Err(TokenError::Unauthorized("minting or burning tokens"));
+  }
+
+  Ok(true)
+}
+
+/// Guards a token wallet. Anyone may credit the wallet and the token
+/// authority may freeze it, spending, approving allowances, replacing
+/// predicates or deleting the wallet requires the owner signature.
+/// Spenders may decrease the balance by at most their allowance if they
+/// decrease their allowance by the same amount.
+///
+/// Parameters:
+///   0. AccountRef to the wallet account itself
+///   1. ed25519 public key of the owner (raw bytes)
+#[predicate]
+fn token_wallet(
+  #[param(expanded)] wallet: ExpandedParam,
+  #[param(raw)] owner: Vec<u8>,
+  context: &PredicateContext,
+) -> Result<bool, TokenError> {
+  let ExpandedParam::AccountRef(address, _) = wallet else {
+    return Err(TokenError::NotAnAccountRef);
+  };
+
+  let owner = bs58::encode(&owner).into_string();
+  let (current, proposed) = match context.proposals.get(&address) {
+    None => return Ok(true),
+    Some(ExpandedAccountChange::ReplaceState { current, proposed }) => (
+      decode::<Wallet>(&address, current)?,
+      decode::<Wallet>(&address, proposed)?,
+    ),
+    Some(_) => return Ok(signed_by(&owner, context)?),
+  };
+
+  // receiving tokens or being frozen by the authority
+  if proposed.balance >= current.balance
+    && proposed.allowances == current.allowances
+  {
+    return Ok(true);
+  }
+
+  if signed_by(&owner, context)? {
+    return Ok(true);
+  }
+
+  // spending within an allowance, exactly one spender allowance
+  // is decreased by the spent amount and the rest is unchanged.
+  let spent = current.balance.saturating_sub(proposed.balance);
+  let changed: BTreeSet<_> = current
+    .allowances
+    .keys()
+    .chain(proposed.allowances.keys())
+    .filter(|spender| {
+      current.allowances.get(*spender) != proposed.allowances.get(*spender)
+    })
+    .collect();
+
+  let mut spenders = changed.into_iter();
+
+  let (Some(spender), None) = (spenders.next(), spenders.next()) else {
+    return Ok(false);
+  };
+
+  let allowance = |wallet: &Wallet| -> u64 {
+    wallet.allowances.get(spender).copied().unwrap_or(0)
+  };
+
+  Ok(
+    spent != 0
+      && allowance(&current).checked_sub(allowance(&proposed)) == Some(spent)
+      && signed_by(spender, context)?,
+  )
+}
+
+/// Reads the balance of a wallet after a proposed change and the change of
+/// balance relative to its current state, wallets created by the transaction
+/// start from zero.
+fn balance_delta(
+  address: &Address,
+  context: &PredicateContext,
+) -> Result<Option<i128>, TokenError> {
+  Ok(match context.proposals.get(address) {
+    Some(change) => match wallet_change(address, change)? {
+      (before, Some(after)) => Some(
+        i128::from(after.balance)
+          - i128::from(before.map(|w| w.balance).unwrap_or(0)),
+      ),
+      _ => None,
+    },
+    None => None,
+  })
+}
+
+/// Verifies that the transaction debits exactly the given amount of tokens
+/// from a wallet.
+///
+/// Parameters:
+///   0. Wallet address
+///   1. Amount (u64)
+#[predicate]
+fn token_sent(
+  wallet: Address,
+  amount: u64,
+  context: &PredicateContext,
+) -> Result<bool, TokenError> {
+  Ok(balance_delta(&wallet, context)? == Some(-i128::from(amount)))
+}
+
+/// Verifies that the transaction credits exactly the given amount of tokens
+/// to a wallet, either existing or created by the transaction.
+///
+/// Parameters:
+///   0. Wallet address
+///   1. Amount (u64)
+#[predicate]
+fn token_received(
+  wallet: Address,
+  amount: u64,
+  context: &PredicateContext,
+) -> Result<bool, TokenError> {
+  Ok(balance_delta(&wallet, context)? == Some(i128::from(amount)))
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    anoma_predicates_sdk::{
+      testing::{intent_hash, ContextBuilder, ParamsBuilder},
+      Account,
+      Address,
+      Code,
+      ExpandedAccountChange,
+      ExpandedParam,
+      Predicate,
+      PredicateContext,
+      PredicateTree,
+    },
+    ed25519_dalek::Keypair,
+    serde::Serialize,
+    std::collections::BTreeMap,
+  };
+
+  #[derive(Serialize)]
+  struct Token {
+    authority: String,
+    supply: u64,
+  }
+
+  #[derive(Clone, Default, Serialize)]
+  struct Wallet {
+    balance: u64,
+    frozen: bool,
+    allowances: BTreeMap<String, u64>,
+  }
+
+  fn wallet(balance: u64) -> Wallet {
+    Wallet {
+      balance,
+      ..Default::default()
+    }
+  }
+
+  fn frozen(balance: u64) -> Wallet {
+    Wallet {
+      balance,
+      frozen: true,
+      ..Default::default()
+    }
+  }
+
+  fn approved(balance: u64, spender: &Keypair, allowance: u64) -> Wallet {
+    Wallet {
+      balance,
+      allowances: [(b58(spender), allowance)].into_iter().collect(),
+      ..Default::default()
+    }
+  }
+
+  fn keypair() -> Keypair {
+    Keypair::generate(&mut rand::thread_rng())
+  }
+
+  fn b58(keypair: &Keypair) -> String {
+    bs58::encode(keypair.public.as_bytes()).into_string()
+  }
+
+  fn named<T: Serialize>(value: &T) -> Vec<u8> {
+    rmp_serde::to_vec_named(value).unwrap()
+  }
+
+  fn usdx() -> Address {
+    "/token/usdx".parse().unwrap()
+  }
+
+  fn alice() -> Address {
+    "/token/usdx/alice.eth".parse().unwrap()
+  }
+
+  fn bob() -> Address {
+    "/token/usdx/bob.eth".parse().unwrap()
+  }
+
+  /// Builds a transaction context with a list of proposals.
+  struct Tx(ContextBuilder);
+
+  impl Tx {
+    fn new() -> Self {
+      Self(ContextBuilder::new())
+    }
+
+    fn create(self, address: Address, wallet: &Wallet) -> Self {
+      Self(self.0.proposal(
+        address,
+        ExpandedAccountChange::CreateAccount(Account {
+          state: named(wallet),
+          predicates: PredicateTree::Id(Predicate {
+            code: Code::Inline(vec![]),
+            params: vec![],
+          }),
+        }),
+      ))
+    }
+
+    fn replace(
+      self,
+      address: Address,
+      current: &impl Serialize,
+      proposed: &impl Serialize,
+    ) -> Self {
+      Self(
+        self
+          .0
+          .proposal(address, ExpandedAccountChange::ReplaceState {
+            current: named(current),
+            proposed: named(proposed),
+          }),
+      )
+    }
+
+    fn supply(self, authority: &Keypair, current: u64, proposed: u64) -> Self {
+      let token = |supply| Token {
+        authority: b58(authority),
+        supply,
+      };
+      self.replace(usdx(), &token(current), &token(proposed))
+    }
+
+    fn signed(self, keypair: &Keypair) -> Self {
+      Self(self.0.signature(intent_hash(b"intent-1"), keypair))
+    }
+
+    fn build(self) -> PredicateContext {
+      self.0.build()
+    }
+  }
+
+  /// Evaluates the token predicate for a token with the given supply
+  fn token(authority: &Keypair, supply: u64, tx: Tx) -> bool {
+    let params = vec![ExpandedParam::AccountRef(
+      usdx(),
+      named(&Token {
+        authority: b58(authority),
+        supply,
+      }),
+    )];
+    super::token(&params, &tx.build())
+  }
+
+  /// Evaluates the wallet predicate of alice's wallet
+  fn alice_wallet(owner: &Keypair, tx: Tx) -> bool {
+    let params = ParamsBuilder::new()
+      .account(alice(), &())
+      .raw(owner.public.as_bytes().to_vec())
+      .build();
+    super::token_wallet(&params, &tx.build())
+  }
+
+  #[test]
+  fn supply_invariants() {
+    let authority = keypair();
+    let transfer = || {
+      Tx::new()
+        .replace(alice(), &wallet(100), &wallet(60))
+        .create(bob(), &wallet(40))
+    };
+
+    // balanced transfers need no authority
+    assert!(token(&authority, 100, transfer()));
+
+    // minting and burning
+    let mint = || Tx::new().replace(alice(), &wallet(100), &wallet(150));
+    assert!(!token(&authority, 100, mint()));
+    assert!(!token(&authority, 100, mint().signed(&authority)));
+    assert!(!token(&authority, 100, mint().supply(&authority, 100, 150)));
+    assert!(token(
+      &authority,
+      100,
+      mint().supply(&authority, 100, 150).signed(&authority)
+    ));
+    assert!(!token(
+      &authority,
+      100,
+      mint().supply(&authority, 100, 140).signed(&authority)
+    ));
+
+    let burn = Tx::new()
+      .replace(alice(), &wallet(100), &wallet(30))
+      .supply(&authority, 100, 30)
+      .signed(&authority);
+    assert!(token(&authority, 100, burn));
+
+    // supply changes only along with balances
+    let inflate = Tx::new().supply(&authority, 100, 200).signed(&authority);
+    assert!(!token(&authority, 100, inflate));
+
+    // rotating the authority
+    let rotate = |signer: &Keypair| {
+      Tx::new()
+        .replace(
+          usdx(),
+          &Token {
+            authority: b58(&authority),
+            supply: 100,
+          },
+          &Token {
+            authority: b58(signer),
+            supply: 100,
+          },
+        )
+        .signed(signer)
+    };
+    assert!(!token(&authority, 100, rotate(&keypair())));
+    assert!(token(
+      &authority,
+      100,
+      rotate(&authority).signed(&authority)
+    ));
+  }
+
+  #[test]
+  fn freezing() {
+    let authority = keypair();
+    let freeze = || Tx::new().replace(alice(), &wallet(100), &frozen(100));
+    assert!(!token(&authority, 100, freeze()));
+    assert!(token(&authority, 100, freeze().signed(&authority)));
+
+    // frozen wallets can receive but can't spend
+    let spend = || {
+      Tx::new()
+        .replace(alice(), &frozen(100), &frozen(50))
+        .replace(bob(), &wallet(0), &wallet(50))
+    };
+    assert!(!token(&authority, 100, spend()));
+    assert!(token(&authority, 100, spend().signed(&authority)));
+
+    let receive = Tx::new()
+      .replace(alice(), &frozen(100), &frozen(150))
+      .replace(bob(), &wallet(50), &wallet(0));
+    assert!(token(&authority, 150, receive));
+
+    // the owner can't unfreeze
+    let unfreeze = Tx::new().replace(alice(), &frozen(100), &wallet(100));
+    assert!(!token(&authority, 100, unfreeze));
+  }
+
+  #[test]
+  fn wallet_authorization() {
+    let owner = keypair();
+    let spender = keypair();
+
+    let receive = Tx::new().replace(alice(), &wallet(100), &wallet(150));
+    assert!(alice_wallet(&owner, receive));
+
+    let freeze = Tx::new().replace(alice(), &wallet(100), &frozen(100));
+    assert!(alice_wallet(&owner, freeze));
+
+    let spend = || Tx::new().replace(alice(), &wallet(100), &wallet(60));
+    assert!(!alice_wallet(&owner, spend()));
+    assert!(alice_wallet(&owner, spend().signed(&owner)));
+
+    let approve =
+      || Tx::new().replace(alice(), &wallet(100), &approved(100, &spender, 50));
+    assert!(!alice_wallet(&owner, approve()));
+    assert!(!alice_wallet(&owner, approve().signed(&spender)));
+    assert!(alice_wallet(&owner, approve().signed(&owner)));
+
+    // spending from an allowance
+    let spend = |amount: u64, allowance: u64| {
+      Tx::new().replace(
+        alice(),
+        &approved(100, &spender, 50),
+        &approved(100 - amount, &spender, allowance),
+      )
+    };
+    assert!(alice_wallet(&owner, spend(30, 20).signed(&spender)));
+    assert!(alice_wallet(&owner, spend(50, 0).signed(&spender)));
+    assert!(!alice_wallet(&owner, spend(30, 20)));
+    assert!(!alice_wallet(&owner, spend(30, 30).signed(&spender)));
+    assert!(!alice_wallet(&owner, spend(0, 10).signed(&spender)));
+    assert!(!alice_wallet(&owner, spend(30, 20).signed(&keypair())));
+
+    let exhausted = Tx::new()
+      .replace(alice(), &approved(100, &spender, 50), &wallet(50))
+      .signed(&spender);
+    assert!(alice_wallet(&owner, exhausted));
+
+    let overspend = Tx::new()
+      .replace(alice(), &approved(100, &spender, 50), &wallet(40))
+      .signed(&spender);
+    assert!(!alice_wallet(&owner, overspend));
+  }
+
+  #[test]
+  fn sent_and_received() {
+    let context = Tx::new()
+      .replace(alice(), &wallet(100), &wallet(60))
+      .create(bob(), &wallet(40))
+      .build();
+    let params = |wallet: Address, amount: u64| {
+      ParamsBuilder::new().inline(&wallet).inline(&amount).build()
+    };
+
+    assert!(super::token_sent(&params(alice(), 40), &context));
+    assert!(!super::token_sent(&params(alice(), 30), &context));
+    assert!(!super::token_sent(&params(bob(), 40), &context));
+    assert!(super::token_received(&params(bob(), 40), &context));
+    assert!(!super::token_received(&params(alice(), 40), &context));
+    assert!(!super::token_received(&params(usdx(), 0), &context));
+  }
+}
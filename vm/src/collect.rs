@@ -4,6 +4,7 @@ use {
     Account,
     AccountChange,
     Address,
+    BlockContext,
     Calldata,
     Code,
     Expanded,
@@ -16,6 +17,7 @@ use {
     PredicateTree,
     Transaction,
   },
+  multihash::Multihash,
   std::collections::{BTreeMap, HashMap},
   thiserror::Error,
 };
@@ -50,6 +52,12 @@ pub enum Error {
      the transaction in {1:?}"
   )]
   CalldataNotFound(String, Predicate),
+
+  #[error(
+    "Intents with signing hash {1:?} carry different calldata values under \
+     key '{0}'"
+  )]
+  ConflictingCalldata(String, Multihash),
 }
 
 /// in case all predicates evaluate successfully on mutated
@@ -108,13 +116,34 @@ pub fn outputs(
 pub fn predicate_context(
   state: &dyn State,
   transaction: &Transaction,
+  block: BlockContext,
 ) -> Result<PredicateContext, Error> {
   Ok(PredicateContext {
-    calldata: transaction
-      .intents
-      .iter()
-      .map(|intent| (*intent.hash(), intent.calldata.clone()))
-      .collect(),
+    block,
+    calldata: {
+      // signatures in calldata sign the signing hash of their intent,
+      // intents with the same signing hash may only add new keys or
+      // repeat existing values, never replace them.
+      let mut calldata = BTreeMap::<_, Calldata>::new();
+      for intent in &transaction.intents {
+        let merged = calldata.entry(*intent.signing_hash()).or_default();
+        for (key, value) in &intent.calldata {
+          match merged.get(key) {
+            Some(existing) if existing != value => {
+              return Err(Error::ConflictingCalldata(
+                key.clone(),
+                *intent.signing_hash(),
+              ));
+            }
+            Some(_) => {}
+            None => {
+              merged.insert(key.clone(), value.clone());
+            }
+          }
+        }
+      }
+      calldata
+    },
     proposals: {
       let mut proposals = BTreeMap::new();
       for (addr, change) in &transaction.proposals {
@@ -4,6 +4,7 @@ use {
   crate::{collect, State, StateDiff},
   anoma_primitives::{
     Address,
+    BlockContext,
     Expanded,
     Predicate,
     PredicateContext,
@@ -84,8 +85,12 @@ pub enum Error {
 /// current blockchain state and the proposed values and returns
 /// a StateDiff object that can be applied to global blockchain
 /// state if all predicates evaluate to true.
+///
+/// The block context describes the block in which the transaction
+/// is included and is visible to all predicates.
 pub fn execute(
   tx: Transaction,
+  block: BlockContext,
   state: &dyn State,
   cache: &dyn State,
 ) -> Result<StateDiff, Error> {
@@ -98,8 +103,9 @@ pub fn execute(
 
   // This context object is passed to every account and intent predicate
   // during evaluation stage. It contains all account mutations proposed
-  // by the transaction and all calldata attached to intents.
-  let context = collect::predicate_context(state, &tx)?;
+  // by the transaction, all calldata attached to intents and the
+  // block in which the transaction is executed.
+  let context = collect::predicate_context(state, &tx, block)?;
 
   // Those are predicates of accounts that are mutated by this
   // transaction. They include immediate predicates of the mutated
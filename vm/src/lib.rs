@@ -1,11 +1,19 @@
 mod collect;
 mod execution;
 mod schedule;
+mod snapshot;
 mod state;
 mod syncell;
 
 pub use {
   execution::{execute, Error as RuntimeError},
-  schedule::execute_many,
-  state::{InMemoryStateStore, State, StateDiff},
+  schedule::{execute_many, TransactionRefs},
+  snapshot::{
+    Error as SnapshotError,
+    SnapshotChunk,
+    SnapshotReader,
+    SnapshotWriter,
+    StateSnapshot,
+  },
+  state::{InMemoryStateStore, Overlayed, State, StateDiff},
 };
@@ -7,7 +7,7 @@ use {
     State,
     StateDiff,
   },
-  anoma_primitives::{Address, Code, Param, Transaction},
+  anoma_primitives::{Address, BlockContext, Code, Param, Transaction},
   petgraph::{
     dot,
     prelude::DiGraph,
@@ -35,11 +35,12 @@ use {
 /// transaction execution or an error explaining why a tx failed. The resulting
 /// collection of results is in the same order as the input txs.
 pub fn execute_many(
+  block: BlockContext,
   state: &dyn State,
   cache: &dyn State,
   txs: impl Iterator<Item = Transaction>,
 ) -> Vec<Result<StateDiff, execution::Error>> {
-  Schedule::new(state, txs).run(state, cache).collect()
+  Schedule::new(state, txs).run(block, state, cache).collect()
 }
 
 type NodeType = SynCell<Option<(Transaction, usize)>>;
@@ -66,6 +67,7 @@ impl<'s> std::fmt::Debug for Tree<'s> {
 impl<'s> Tree<'s> {
   pub fn run(
     self,
+    block: BlockContext,
     state: &dyn State,
     cache: &dyn State,
   ) -> impl Iterator<Item = (Result<StateDiff, execution::Error>, usize)> {
@@ -97,7 +99,7 @@ impl<'s> Tree<'s> {
       let state = Overlayed::new(state, &acc_state);
       let results: Vec<_> = row_txs
         .into_par_iter()
-        .map(|(tx, ix)| (execute(tx, &state, cache), ix))
+        .map(|(tx, ix)| (execute(tx, block, &state, cache), ix))
         .collect();
 
       // accumulate state changes within one tx dependency tree row,
@@ -150,13 +152,14 @@ impl Schedule {
 
   pub fn run(
     self,
+    block: BlockContext,
     state: &dyn State,
     cache: &dyn State,
   ) -> impl Iterator<Item = Result<StateDiff, execution::Error>> {
     let mut trees: Vec<(Result<StateDiff, execution::Error>, usize)> = self
       .trees()
       .into_par_iter()
-      .map(|tree: Tree| tree.run(state, cache).collect::<Vec<_>>())
+      .map(|tree: Tree| tree.run(block, state, cache).collect::<Vec<_>>())
       .flatten()
       .collect();
 
@@ -228,14 +231,26 @@ impl std::fmt::Debug for Schedule {
 }
 
 /// Specifies the list of all accounts that a transaction will read or write to.
-/// This is used when scheduling transactions for execution in parallel.
+/// This is used when scheduling transactions for execution in parallel and
+/// by mempools that need to know which pending transactions conflict with
+/// each other.
 #[derive(Debug, PartialEq, Eq)]
-struct TransactionRefs {
+pub struct TransactionRefs {
   reads: HashSet<Address>,
   writes: HashSet<Address>,
 }
 
 impl TransactionRefs {
+  /// Accounts that are read by predicates but not mutated by the transaction.
+  pub fn reads(&self) -> impl Iterator<Item = &Address> {
+    self.reads.iter()
+  }
+
+  /// Accounts that are mutated by the transaction.
+  pub fn writes(&self) -> impl Iterator<Item = &Address> {
+    self.writes.iter()
+  }
+
   pub fn depends_on(&self, other: &Self) -> bool {
     self.reads.iter().any(|addr| other.writes.contains(addr))
       || self.writes.iter().any(|addr| other.writes.contains(addr))
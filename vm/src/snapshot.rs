@@ -0,0 +1,260 @@
+use {
+  crate::StateDiff,
+  anoma_primitives::{Account, Address, Block},
+  multihash::{Code, Multihash, MultihashDigest},
+  serde::{Deserialize, Serialize},
+  std::{collections::HashSet, num::NonZeroUsize},
+  thiserror::Error,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+  #[error("Chunk {0:?} is not part of the snapshot or was already imported")]
+  UnexpectedChunk(Multihash),
+
+  #[error("Malformed snapshot chunk: {0}")]
+  MalformedChunk(#[from] rmp_serde::decode::Error),
+
+  #[error("Snapshot is incomplete, {0} chunks are missing")]
+  Incomplete(usize),
+}
+
+/// Describes the entire state of the chain right after a given block.
+///
+/// Accounts are not stored in the snapshot itself, they are split into
+/// chunks that can be transferred independently and are identified by
+/// the hash of their contents. Once the snapshot is obtained from a
+/// trusted source, any chunk can be fetched from untrusted peers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+  /// The most recent block whose state changes are included.
+  pub block: Block,
+
+  /// Hashes of all chunks of this snapshot.
+  pub chunks: Vec<Multihash>,
+}
+
+impl StateSnapshot {
+  pub fn hash(&self) -> Multihash {
+    Code::Sha3_256.digest(&rmp_serde::to_vec(self).expect("infallible"))
+  }
+}
+
+/// A part of the accounts state of a snapshot.
+///
+/// Chunks are transferred as raw bytes, their hash is the hash of
+/// those bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotChunk(Vec<u8>);
+
+impl SnapshotChunk {
+  fn new(accounts: &[(Address, Account)]) -> Self {
+    Self(rmp_serde::to_vec(accounts).expect("infallible"))
+  }
+
+  pub fn hash(&self) -> Multihash {
+    Code::Sha3_256.digest(&self.0)
+  }
+
+  pub fn as_bytes(&self) -> &[u8] {
+    &self.0
+  }
+}
+
+impl From<Vec<u8>> for SnapshotChunk {
+  fn from(bytes: Vec<u8>) -> Self {
+    Self(bytes)
+  }
+}
+
+/// Splits accounts into chunks of a snapshot as they are iterated.
+///
+/// The writer is an iterator of chunks, once all of them are produced
+/// [`SnapshotWriter::finish`] returns the snapshot that refers to them.
+pub struct SnapshotWriter<I> {
+  block: Block,
+  accounts: I,
+  chunk_size: usize,
+  chunks: Vec<Multihash>,
+}
+
+impl<I> SnapshotWriter<I>
+where
+  I: Iterator<Item = (Address, Account)>,
+{
+  /// Creates a writer for the state after `block`, putting at most
+  /// `chunk_size` accounts in every chunk.
+  pub fn new(block: Block, accounts: I, chunk_size: NonZeroUsize) -> Self {
+    Self {
+      block,
+      accounts,
+      chunk_size: chunk_size.get(),
+      chunks: vec![],
+    }
+  }
+
+  /// Produces the snapshot, including any chunks not iterated yet.
+  pub fn finish(mut self) -> StateSnapshot {
+    while self.next().is_some() {}
+    StateSnapshot {
+      block: self.block,
+      chunks: self.chunks,
+    }
+  }
+}
+
+impl<I> Iterator for SnapshotWriter<I>
+where
+  I: Iterator<Item = (Address, Account)>,
+{
+  type Item = SnapshotChunk;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let accounts: Vec<_> =
+      self.accounts.by_ref().take(self.chunk_size).collect();
+    if accounts.is_empty() {
+      return None;
+    }
+    let chunk = SnapshotChunk::new(&accounts);
+    self.chunks.push(chunk.hash());
+    Some(chunk)
+  }
+}
+
+/// Verifies chunks of a snapshot and turns them into state diffs.
+///
+/// Chunks may be read in any order, but each of them only once.
+pub struct SnapshotReader {
+  snapshot: StateSnapshot,
+  pending: HashSet<Multihash>,
+}
+
+impl SnapshotReader {
+  pub fn new(snapshot: StateSnapshot) -> Self {
+    Self {
+      pending: snapshot.chunks.iter().cloned().collect(),
+      snapshot,
+    }
+  }
+
+  /// Returns the accounts stored in a chunk if it belongs to the snapshot.
+  pub fn read(&mut self, chunk: &SnapshotChunk) -> Result<StateDiff, Error> {
+    let hash = chunk.hash();
+    if !self.pending.contains(&hash) {
+      return Err(Error::UnexpectedChunk(hash));
+    }
+
+    let accounts: Vec<(Address, Account)> = rmp_serde::from_slice(&chunk.0)?;
+    self.pending.remove(&hash);
+
+    let mut diff = StateDiff::default();
+    for (address, account) in accounts {
+      diff.set(address, account);
+    }
+    Ok(diff)
+  }
+
+  /// Hashes of chunks that were not read yet.
+  pub fn missing(&self) -> impl Iterator<Item = &Multihash> {
+    self.pending.iter()
+  }
+
+  /// Returns the block of the snapshot once all its chunks were read.
+  pub fn finish(self) -> Result<Block, Error> {
+    match self.pending.len() {
+      0 => Ok(self.snapshot.block),
+      missing => Err(Error::Incomplete(missing)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::{Error, SnapshotReader, SnapshotWriter},
+    crate::{InMemoryStateStore, State},
+    anoma_primitives::{
+      Account,
+      Address,
+      Block,
+      Code,
+      Predicate,
+      PredicateTree,
+    },
+    std::num::NonZeroUsize,
+  };
+
+  fn accounts(count: usize) -> impl Iterator<Item = (Address, Account)> {
+    (0..count).map(|i| {
+      (format!("/test/addr{i}").parse().unwrap(), Account {
+        state: vec![i as u8; i],
+        predicates: PredicateTree::Id(Predicate {
+          code: Code::Inline(b"some-code".to_vec()),
+          params: vec![],
+        }),
+      })
+    })
+  }
+
+  #[test]
+  fn snapshot_roundtrip() {
+    let block = Block::new(&Block::zero(), 1, vec![]);
+    let mut writer = SnapshotWriter::new(
+      block.clone(),
+      accounts(10),
+      NonZeroUsize::new(4).unwrap(),
+    );
+    let mut chunks: Vec<_> = writer.by_ref().collect();
+    let snapshot = writer.finish();
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(snapshot.chunks.len(), 3);
+    assert_eq!(snapshot.block.hash(), block.hash());
+
+    let mut store = InMemoryStateStore::default();
+    let mut reader = SnapshotReader::new(snapshot.clone());
+
+    // chunks can arrive in any order
+    chunks.reverse();
+    let last = chunks.pop().unwrap();
+    for chunk in &chunks {
+      store.apply(reader.read(chunk).unwrap());
+    }
+
+    // each chunk is accepted only once
+    assert!(matches!(
+      reader.read(&chunks[0]),
+      Err(Error::UnexpectedChunk(_))
+    ));
+    assert_eq!(reader.missing().collect::<Vec<_>>(), vec![&last.hash()]);
+
+    // tampered chunks are rejected
+    let mut tampered = last.as_bytes().to_vec();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+      reader.read(&tampered.into()),
+      Err(Error::UnexpectedChunk(_))
+    ));
+
+    store.apply(reader.read(&last).unwrap());
+    assert_eq!(reader.finish().unwrap().hash(), block.hash());
+    assert_eq!(store.iter().count(), 10);
+    for (address, account) in accounts(10) {
+      assert_eq!(store.get(&address).unwrap().state, account.state);
+    }
+  }
+
+  #[test]
+  fn incomplete_snapshot() {
+    let snapshot = SnapshotWriter::new(
+      Block::zero(),
+      accounts(5),
+      NonZeroUsize::new(2).unwrap(),
+    )
+    .finish();
+    assert_eq!(snapshot.chunks.len(), 3);
+    assert!(matches!(
+      SnapshotReader::new(snapshot).finish(),
+      Err(Error::Incomplete(3))
+    ));
+  }
+}
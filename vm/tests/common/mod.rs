@@ -6,12 +6,47 @@ use {
 pub mod token_ops;
 
 use {
-  anoma_primitives::{Account, Address, Code, Param, Predicate, PredicateTree},
+  anoma_primitives::{
+    Account,
+    Address,
+    Code,
+    Intent,
+    Param,
+    Predicate,
+    PredicateTree,
+  },
   anoma_vm::{State, StateDiff},
-  ed25519_dalek::PublicKey,
+  ed25519_dalek::{Keypair, PublicKey, Signer},
   rmp_serde::to_vec,
 };
 
+/// A predicate from the standard library installed at '/stdpred/v1'.
+pub fn stdpred(entrypoint: &str, params: Vec<Param>) -> PredicateTree {
+  PredicateTree::Id(Predicate {
+    code: Code::AccountRef("/stdpred/v1".parse().unwrap(), entrypoint.into()),
+    params,
+  })
+}
+
+pub fn and(a: PredicateTree, b: PredicateTree) -> PredicateTree {
+  PredicateTree::And(Box::new(a), Box::new(b))
+}
+
+/// The calldata key under which signatures by this keypair are
+/// looked up by the standard library.
+pub fn b58(keypair: &Keypair) -> String {
+  bs58::encode(keypair.public.as_bytes()).into_string()
+}
+
+/// Signs the signing hash of an intent and attaches the signature
+/// to its calldata.
+pub fn sign(intent: &mut Intent, keypair: &Keypair) {
+  let signature = keypair.sign(&intent.signing_hash().to_bytes());
+  intent
+    .calldata
+    .insert(b58(keypair), signature.to_bytes().to_vec());
+}
+
 /// Creates a statediff that has the standard predicates library
 /// installed in sate at '/stdpred/v1'. Almost everything
 /// will require this.
@@ -35,15 +70,13 @@ pub fn install_standard_library() -> StateDiff {
       "../../../target/wasm32-unknown-unknown/release/stdpred.wasm"
     )
     .to_vec(),
-    predicates: PredicateTree::And(
-      Box::new(PredicateTree::Id(Predicate {
-        code: Code::AccountRef(stdaddr.clone(), "immutable_state".into()),
-        params: vec![Param::Inline(to_vec(&stdaddr).unwrap())],
-      })),
-      Box::new(PredicateTree::Id(Predicate {
-        code: Code::AccountRef(stdaddr.clone(), "immutable_predicates".into()),
-        params: vec![Param::Inline(to_vec(&stdaddr).unwrap())],
-      })),
+    predicates: and(
+      stdpred("immutable_state", vec![Param::Inline(
+        to_vec(&stdaddr).unwrap(),
+      )]),
+      stdpred("immutable_predicates", vec![Param::Inline(
+        to_vec(&stdaddr).unwrap(),
+      )]),
     ),
   });
   stdpred_bytecode
@@ -112,19 +145,16 @@ pub fn create_usdx_token(mint_authority: PublicKey) -> StateDiff {
           Param::AccountRef(tokenaddr.clone()),
         ],
       })),
-      Box::new(PredicateTree::Id(Predicate {
-        code: Code::AccountRef(
-          "/stdpred/v1".parse().unwrap(),
-          "constant".into(),
-        ),
-        params: vec![Param::Inline(to_vec(&false).unwrap())],
-      })),
+      Box::new(stdpred("immutable_predicates", vec![Param::Inline(
+        to_vec(&tokenaddr).unwrap(),
+      )])),
     ),
   });
 
   usdx_token
 }
 
+#[allow(dead_code)] // not every test deploys a token
 pub fn create_initial_blockchain_state(mint_authority: PublicKey) -> StateDiff {
   let mut state = StateDiff::default();
   state.apply(install_standard_library());
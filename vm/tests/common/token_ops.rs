@@ -1,12 +1,13 @@
 #![allow(dead_code)]
 
 use {
+  super::sign,
   anoma_primitives::{
     Account,
     AccountChange,
     Address,
-    Code,
     Basic,
+    Code,
     Intent,
     Param,
     Predicate,
@@ -14,7 +15,7 @@ use {
     Transaction,
   },
   anoma_vm::State,
-  ed25519_dalek::{Keypair, PublicKey, Signer},
+  ed25519_dalek::{Keypair, PublicKey},
   multihash::Multihash,
   rmp_serde::{from_slice, to_vec},
 };
@@ -101,15 +102,7 @@ pub fn mint(
   };
 
   // add mint authority signature to the intent
-  let mint_pk_b58 = bs58::encode(auth_keypair.public.as_bytes()).into_string();
-
-  mint_intent.calldata.insert(
-    mint_pk_b58,
-    auth_keypair
-      .sign(mint_intent.signing_hash().to_bytes().as_slice())
-      .to_bytes()
-      .to_vec(),
-  );
+  sign(&mut mint_intent, auth_keypair);
 
   Ok(Transaction::new(
     vec![mint_intent],
@@ -170,13 +163,7 @@ pub fn transfer(
   );
 
   // sign intent by sender
-  transfer_intent.calldata.insert(
-    bs58::encode(sender_keypair.public.as_bytes()).into_string(),
-    sender_keypair
-      .sign(transfer_intent.signing_hash().to_bytes().as_slice())
-      .to_bytes()
-      .to_vec(),
-  );
+  sign(&mut transfer_intent, sender_keypair);
 
   let new_sender_balance =
     from_slice::<u64>(&state.get(sender).expect("asserted").state)?
@@ -265,13 +252,7 @@ pub fn transfer_unchecked(
   );
 
   // sign intent by sender
-  transfer_intent.calldata.insert(
-    bs58::encode(sender_keypair.public.as_bytes()).into_string(),
-    sender_keypair
-      .sign(transfer_intent.signing_hash().to_bytes().as_slice())
-      .to_bytes()
-      .to_vec(),
-  );
+  sign(&mut transfer_intent, sender_keypair);
 
   let sender_acc_change =
     AccountChange::ReplaceState(to_vec(&sender_new_balance)?);
@@ -0,0 +1,382 @@
+use {
+  anoma_primitives::{
+    Account,
+    AccountChange,
+    Address,
+    BlockContext,
+    Intent,
+    Param,
+    PredicateTree,
+    Transaction,
+  },
+  anoma_vm::{InMemoryStateStore, State},
+  common::{
+    and,
+    create_initial_blockchain_state,
+    precache_predicates_bytecode,
+    sign,
+    stdpred,
+  },
+  ed25519_dalek::{Keypair, PublicKey},
+  multihash::{Multihash, MultihashDigest},
+  rmp_serde::{from_slice, to_vec},
+};
+
+mod common;
+
+/// Height after which the locked funds can be refunded to the sender.
+const TIMEOUT: u64 = 100;
+
+fn block(height: u64) -> BlockContext {
+  BlockContext {
+    height,
+    timestamp: 0,
+  }
+}
+
+fn or(a: PredicateTree, b: PredicateTree) -> PredicateTree {
+  PredicateTree::Or(Box::new(a), Box::new(b))
+}
+
+fn signed_by(pubkey: &PublicKey) -> PredicateTree {
+  stdpred("require_ed25519_signature", vec![Param::Inline(
+    pubkey.to_bytes().to_vec(),
+  )])
+}
+
+/// Predicates of a regular wallet that can receive tokens from anyone
+/// but requires the owner signature to spend them.
+fn wallet_predicates(address: &Address, owner: &PublicKey) -> PredicateTree {
+  or(
+    stdpred("uint_greater_than_equal", vec![
+      Param::ProposalRef(address.clone()),
+      Param::AccountRef(address.clone()),
+    ]),
+    signed_by(owner),
+  )
+}
+
+/// Funds locked in an account with those predicates can be claimed by the
+/// recipient if they reveal the preimage of the hashlock before the timeout,
+/// or refunded to the sender after the timeout.
+fn htlc_predicates(
+  hashlock: &[u8],
+  sender: &PublicKey,
+  recipient: &PublicKey,
+) -> PredicateTree {
+  or(
+    and(
+      and(
+        stdpred("sha256_preimage", vec![
+          Param::Inline(hashlock.to_vec()),
+          Param::Inline(to_vec("preimage").unwrap()),
+        ]),
+        stdpred("before_height", vec![Param::Inline(
+          to_vec(&TIMEOUT).unwrap(),
+        )]),
+      ),
+      signed_by(recipient),
+    ),
+    and(
+      stdpred("after_height", vec![Param::Inline(
+        to_vec(&TIMEOUT).unwrap(),
+      )]),
+      signed_by(sender),
+    ),
+  )
+}
+
+fn balance(state: &impl State, address: &Address) -> u64 {
+  state
+    .get(address)
+    .map(|acc| from_slice(&acc.state).unwrap())
+    .unwrap_or(0)
+}
+
+/// Moves funds from the sender wallet into a new HTLC account.
+fn lock(
+  amount: u64,
+  sender: &Address,
+  sender_keypair: &Keypair,
+  htlc: &Address,
+  htlc_predicates: PredicateTree,
+  recent_blockhash: Multihash,
+  state: &impl State,
+) -> anyhow::Result<Transaction> {
+  let mut intent = Intent::new(
+    recent_blockhash,
+    stdpred("uint_less_than_by", vec![
+      Param::ProposalRef(sender.clone()),
+      Param::AccountRef(sender.clone()),
+      Param::Inline(to_vec(&amount)?),
+    ]),
+  );
+  sign(&mut intent, sender_keypair);
+
+  Ok(Transaction::new(
+    vec![intent],
+    [
+      (
+        sender.clone(),
+        AccountChange::ReplaceState(to_vec(
+          &(balance(state, sender) - amount),
+        )?),
+      ),
+      (
+        htlc.clone(),
+        AccountChange::CreateAccount(Account {
+          state: to_vec(&amount)?,
+          predicates: htlc_predicates,
+        }),
+      ),
+    ]
+    .into_iter()
+    .collect(),
+  ))
+}
+
+/// Moves all funds from an HTLC account into the beneficiary wallet,
+/// optionally revealing the hashlock preimage.
+fn unlock(
+  htlc: &Address,
+  beneficiary: &Address,
+  beneficiary_keypair: &Keypair,
+  preimage: Option<&[u8]>,
+  recent_blockhash: Multihash,
+  state: &impl State,
+) -> anyhow::Result<Transaction> {
+  let amount = balance(state, htlc);
+  let new_balance = balance(state, beneficiary) + amount;
+
+  let mut intent = Intent::new(
+    recent_blockhash,
+    stdpred("uint_equal", vec![
+      Param::ProposalRef(beneficiary.clone()),
+      Param::Inline(to_vec(&new_balance)?),
+    ]),
+  );
+  sign(&mut intent, beneficiary_keypair);
+  if let Some(preimage) = preimage {
+    intent.calldata.insert("preimage".into(), preimage.to_vec());
+  }
+
+  let beneficiary_change = match state.get(beneficiary) {
+    Some(_) => AccountChange::ReplaceState(to_vec(&new_balance)?),
+    None => AccountChange::CreateAccount(Account {
+      state: to_vec(&new_balance)?,
+      predicates: wallet_predicates(beneficiary, &beneficiary_keypair.public),
+    }),
+  };
+
+  Ok(Transaction::new(
+    vec![intent],
+    [
+      (htlc.clone(), AccountChange::ReplaceState(to_vec(&0u64)?)),
+      (beneficiary.clone(), beneficiary_change),
+    ]
+    .into_iter()
+    .collect(),
+  ))
+}
+
+#[test]
+fn hash_time_locked_swap() -> anyhow::Result<()> {
+  let mint_keypair = Keypair::generate(&mut rand::thread_rng());
+  let recent_blockhash = multihash::Code::Sha3_256.digest(b"test4");
+
+  let mut store = InMemoryStateStore::default();
+  store.apply(create_initial_blockchain_state(mint_keypair.public));
+
+  let mut cache = InMemoryStateStore::default();
+  cache.apply(precache_predicates_bytecode(
+    &store,
+    &"/token".parse().unwrap(),
+  ));
+  cache.apply(precache_predicates_bytecode(
+    &store,
+    &"/stdpred/v1".parse().unwrap(),
+  ));
+
+  let alice_keypair = Keypair::generate(&mut rand::thread_rng());
+  let alice_address = &"/token/usdx/alice.eth".parse()?;
+
+  let bob_keypair = Keypair::generate(&mut rand::thread_rng());
+  let bob_address = &"/token/usdx/bob.eth".parse()?;
+
+  store.apply(anoma_vm::execute(
+    common::token_ops::mint(
+      1000,
+      alice_address,
+      &alice_keypair.public,
+      &mint_keypair,
+      recent_blockhash,
+      &store,
+    )?,
+    block(1),
+    &store,
+    &cache,
+  )?);
+
+  // alice locks 300 tokens that bob can claim with the secret
+  let secret = b"swap secret agreed off-chain";
+  let hashlock = multihash::Code::Sha2_256.digest(secret);
+  let htlc = &"/token/usdx/htlc1".parse()?;
+
+  store.apply(anoma_vm::execute(
+    lock(
+      300,
+      alice_address,
+      &alice_keypair,
+      htlc,
+      htlc_predicates(
+        hashlock.digest(),
+        &alice_keypair.public,
+        &bob_keypair.public,
+      ),
+      recent_blockhash,
+      &store,
+    )?,
+    block(10),
+    &store,
+    &cache,
+  )?);
+
+  assert_eq!(balance(&store, alice_address), 700);
+  assert_eq!(balance(&store, htlc), 300);
+
+  // wrong preimage
+  let claim = unlock(
+    htlc,
+    bob_address,
+    &bob_keypair,
+    Some(b"guess"),
+    recent_blockhash,
+    &store,
+  )?;
+  assert!(anoma_vm::execute(claim, block(20), &store, &cache).is_err());
+
+  // no preimage
+  let claim = unlock(
+    htlc,
+    bob_address,
+    &bob_keypair,
+    None,
+    recent_blockhash,
+    &store,
+  )?;
+  assert!(anoma_vm::execute(claim, block(20), &store, &cache).is_err());
+
+  // the sender cannot take the funds back before the timeout
+  let refund = unlock(
+    htlc,
+    alice_address,
+    &alice_keypair,
+    None,
+    recent_blockhash,
+    &store,
+  )?;
+  assert!(anoma_vm::execute(refund, block(20), &store, &cache).is_err());
+
+  // the recipient cannot claim the funds after the timeout
+  let claim = unlock(
+    htlc,
+    bob_address,
+    &bob_keypair,
+    Some(secret),
+    recent_blockhash,
+    &store,
+  )?;
+  assert!(
+    anoma_vm::execute(claim.clone(), block(TIMEOUT + 1), &store, &cache)
+      .is_err()
+  );
+
+  // correct preimage before the timeout
+  store.apply(anoma_vm::execute(claim, block(20), &store, &cache)?);
+
+  assert_eq!(balance(&store, htlc), 0);
+  assert_eq!(balance(&store, bob_address), 300);
+  assert_eq!(balance(&store, alice_address), 700);
+
+  Ok(())
+}
+
+#[test]
+fn hash_time_locked_refund() -> anyhow::Result<()> {
+  let mint_keypair = Keypair::generate(&mut rand::thread_rng());
+  let recent_blockhash = multihash::Code::Sha3_256.digest(b"test5");
+
+  let mut store = InMemoryStateStore::default();
+  store.apply(create_initial_blockchain_state(mint_keypair.public));
+
+  let mut cache = InMemoryStateStore::default();
+  cache.apply(precache_predicates_bytecode(
+    &store,
+    &"/token".parse().unwrap(),
+  ));
+  cache.apply(precache_predicates_bytecode(
+    &store,
+    &"/stdpred/v1".parse().unwrap(),
+  ));
+
+  let alice_keypair = Keypair::generate(&mut rand::thread_rng());
+  let alice_address = &"/token/usdx/alice.eth".parse()?;
+  let bob_keypair = Keypair::generate(&mut rand::thread_rng());
+
+  store.apply(anoma_vm::execute(
+    common::token_ops::mint(
+      1000,
+      alice_address,
+      &alice_keypair.public,
+      &mint_keypair,
+      recent_blockhash,
+      &store,
+    )?,
+    block(1),
+    &store,
+    &cache,
+  )?);
+
+  let hashlock = multihash::Code::Sha2_256.digest(b"never revealed");
+  let htlc = &"/token/usdx/htlc2".parse()?;
+
+  store.apply(anoma_vm::execute(
+    lock(
+      300,
+      alice_address,
+      &alice_keypair,
+      htlc,
+      htlc_predicates(
+        hashlock.digest(),
+        &alice_keypair.public,
+        &bob_keypair.public,
+      ),
+      recent_blockhash,
+      &store,
+    )?,
+    block(10),
+    &store,
+    &cache,
+  )?);
+
+  // bob never claims the funds, after the timeout alice takes them back
+  let refund = unlock(
+    htlc,
+    alice_address,
+    &alice_keypair,
+    None,
+    recent_blockhash,
+    &store,
+  )?;
+  store.apply(anoma_vm::execute(
+    refund,
+    block(TIMEOUT + 1),
+    &store,
+    &cache,
+  )?);
+
+  assert_eq!(balance(&store, htlc), 0);
+  assert_eq!(balance(&store, alice_address), 1000);
+
+  Ok(())
+}
@@ -0,0 +1,119 @@
+use {
+  anoma_primitives::{
+    Account,
+    AccountChange,
+    Address,
+    BlockContext,
+    Intent,
+    Param,
+    Transaction,
+  },
+  anoma_vm::{InMemoryStateStore, RuntimeError, State, StateDiff},
+  common::{
+    b58,
+    create_initial_blockchain_state,
+    precache_predicates_bytecode,
+    sign,
+    stdpred,
+  },
+  ed25519_dalek::Keypair,
+  multihash::MultihashDigest,
+  rmp_serde::{from_slice, to_vec},
+};
+
+mod common;
+
+/// An account whose state can only be replaced by the owner of a key.
+fn setup(owner: &Keypair) -> (InMemoryStateStore, InMemoryStateStore) {
+  let mint_keypair = Keypair::generate(&mut rand::thread_rng());
+  let mut store = InMemoryStateStore::default();
+  store.apply(create_initial_blockchain_state(mint_keypair.public));
+
+  let mut vault = StateDiff::default();
+  vault.set("/vault".parse().unwrap(), Account {
+    state: to_vec(&0u64).unwrap(),
+    predicates: stdpred("require_ed25519_signature", vec![Param::Inline(
+      owner.public.as_bytes().to_vec(),
+    )]),
+  });
+  store.apply(vault);
+
+  let mut cache = InMemoryStateStore::default();
+  cache.apply(precache_predicates_bytecode(
+    &store,
+    &"/stdpred/v1".parse().unwrap(),
+  ));
+  (store, cache)
+}
+
+fn replace_vault(intents: Vec<Intent>) -> Transaction {
+  let vault: Address = "/vault".parse().unwrap();
+  Transaction::new(
+    intents,
+    [(vault, AccountChange::ReplaceState(to_vec(&1u64).unwrap()))].into(),
+  )
+}
+
+fn intent() -> Intent {
+  Intent::new(
+    multihash::Code::Sha3_256.digest(b"recent"),
+    stdpred("constant", vec![Param::Inline(to_vec(&true).unwrap())]),
+  )
+}
+
+#[test]
+fn signatures_over_signing_hash_survive_hashing() -> anyhow::Result<()> {
+  let owner = Keypair::generate(&mut rand::thread_rng());
+  let (store, cache) = setup(&owner);
+
+  // computing the intent hash first must not change the signing hash
+  let mut intent = intent();
+  let hash = *intent.hash();
+  let signing_hash = *intent.signing_hash();
+  assert_ne!(hash, signing_hash);
+
+  sign(&mut intent, &owner);
+
+  // the transaction goes over the wire, so no hashes are cached
+  let tx: Transaction = from_slice(&to_vec(&replace_vault(vec![intent]))?)?;
+  let diff = anoma_vm::execute(tx, BlockContext::default(), &store, &cache)?;
+  assert_eq!(
+    from_slice::<u64>(&diff.get(&"/vault".parse()?).unwrap().state)?,
+    1
+  );
+
+  Ok(())
+}
+
+#[test]
+fn conflicting_calldata_is_rejected() -> anyhow::Result<()> {
+  let owner = Keypair::generate(&mut rand::thread_rng());
+  let (store, cache) = setup(&owner);
+
+  let mut signed = intent();
+  sign(&mut signed, &owner);
+
+  // the same intent included twice is fine
+  anoma_vm::execute(
+    replace_vault(vec![signed.clone(), signed.clone()]),
+    BlockContext::default(),
+    &store,
+    &cache,
+  )?;
+
+  // a duplicate with the same signing hash can't overwrite the signature
+  let mut forged = intent();
+  forged.calldata.insert(b58(&owner), vec![0; 64]);
+  let result = anoma_vm::execute(
+    replace_vault(vec![signed, forged]),
+    BlockContext::default(),
+    &store,
+    &cache,
+  );
+  assert!(matches!(
+    result,
+    Err(RuntimeError::State(e)) if e.to_string().contains("different calldata")
+  ));
+
+  Ok(())
+}
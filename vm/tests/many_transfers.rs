@@ -1,5 +1,13 @@
 use {
-  anoma_primitives::{Account, Address, Code, Param, Predicate, PredicateTree},
+  anoma_primitives::{
+    Account,
+    Address,
+    BlockContext,
+    Code,
+    Param,
+    Predicate,
+    PredicateTree,
+  },
   anoma_vm::{InMemoryStateStore, State, StateDiff},
   common::{create_initial_blockchain_state, precache_predicates_bytecode},
   ed25519_dalek::Keypair,
@@ -83,7 +91,12 @@ fn mint_then_transfers() -> anyhow::Result<()> {
   }
 
   let started = Instant::now();
-  let results = anoma_vm::execute_many(&store, &cache, txs.into_iter());
+  let results = anoma_vm::execute_many(
+    BlockContext::default(),
+    &store,
+    &cache,
+    txs.into_iter(),
+  );
   println!("elapsed: {:?}", started.elapsed());
 
   assert_eq!(results.len(), 1001);
@@ -178,7 +191,12 @@ fn many_independent_transfers() -> anyhow::Result<()> {
   }
 
   let started = Instant::now();
-  let results = anoma_vm::execute_many(&store, &cache, txs.into_iter());
+  let results = anoma_vm::execute_many(
+    BlockContext::default(),
+    &store,
+    &cache,
+    txs.into_iter(),
+  );
   println!("elapsed: {:?}", started.elapsed());
   assert_eq!(results.len(), 1000);
 
@@ -0,0 +1,172 @@
+use {
+  anoma_primitives::{
+    Account,
+    AccountChange,
+    Address,
+    BlockContext,
+    Intent,
+    Param,
+    Transaction,
+  },
+  anoma_vm::{InMemoryStateStore, State, StateDiff},
+  common::{
+    b58,
+    install_standard_library,
+    precache_predicates_bytecode,
+    sign,
+    stdpred,
+  },
+  ed25519_dalek::Keypair,
+  multihash::{Multihash, MultihashDigest},
+  rmp_serde::{from_slice, to_vec, to_vec_named},
+  serde::{Deserialize, Serialize},
+};
+
+mod common;
+
+#[derive(Serialize)]
+struct Collection {
+  authority: String,
+  royalty_bps: u16,
+  royalty_recipient: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Item {
+  owner: String,
+  uri: String,
+}
+
+fn item(owner: &Keypair) -> Item {
+  Item {
+    owner: b58(owner),
+    uri: "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"
+      .into(),
+  }
+}
+
+/// An intent signed by `signer` that expects `owner` to own the item.
+fn owned_by(
+  address: &Address,
+  owner: &Keypair,
+  signer: &Keypair,
+  recent_blockhash: Multihash,
+) -> Intent {
+  let mut intent = Intent::new(
+    recent_blockhash,
+    stdpred("nft_owned_by", vec![
+      Param::Inline(to_vec(address).unwrap()),
+      Param::Inline(owner.public.as_bytes().to_vec()),
+    ]),
+  );
+  sign(&mut intent, signer);
+  intent
+}
+
+fn create_collection(address: &Address, authority: &Keypair) -> StateDiff {
+  let mut diff = StateDiff::default();
+  diff.set(address.clone(), Account {
+    state: to_vec_named(&Collection {
+      authority: b58(authority),
+      royalty_bps: 500,
+      royalty_recipient: b58(authority),
+    })
+    .unwrap(),
+    predicates: stdpred("nft_collection", vec![Param::AccountRef(
+      address.clone(),
+    )]),
+  });
+  diff
+}
+
+fn owner(state: &impl State, address: &Address) -> Option<String> {
+  state
+    .get(address)
+    .map(|account| from_slice::<Item>(&account.state).unwrap().owner)
+}
+
+#[test]
+fn nft_mint_transfer_burn() -> anyhow::Result<()> {
+  let block = BlockContext::default();
+  let recent_blockhash = multihash::Code::Sha3_256.digest(b"test7");
+
+  let authority = Keypair::generate(&mut rand::thread_rng());
+  let alice = Keypair::generate(&mut rand::thread_rng());
+  let bob = Keypair::generate(&mut rand::thread_rng());
+
+  let punks: Address = "/nft/punks".parse()?;
+  let punk42: Address = "/nft/punks/42".parse()?;
+
+  let mut store = InMemoryStateStore::default();
+  store.apply(install_standard_library());
+  store.apply(create_collection(&punks, &authority));
+
+  let mut cache = InMemoryStateStore::default();
+  cache.apply(precache_predicates_bytecode(
+    &store,
+    &"/stdpred/v1".parse().unwrap(),
+  ));
+
+  // minting requires the collection authority signature,
+  // the item itself does not need any predicates of its own
+  let mint = |signer: &Keypair| {
+    Transaction::new(
+      vec![owned_by(&punk42, &alice, signer, recent_blockhash)],
+      [(
+        punk42.clone(),
+        AccountChange::CreateAccount(Account {
+          state: to_vec_named(&item(&alice)).unwrap(),
+          predicates: stdpred("constant", vec![Param::Inline(
+            to_vec(&true).unwrap(),
+          )]),
+        }),
+      )]
+      .into_iter()
+      .collect(),
+    )
+  };
+  assert!(anoma_vm::execute(mint(&alice), block, &store, &cache).is_err());
+  store.apply(anoma_vm::execute(mint(&authority), block, &store, &cache)?);
+  assert_eq!(owner(&store, &punk42), Some(b58(&alice)));
+
+  // transfers are authorized by the current owner
+  let transfer = |signer: &Keypair| {
+    Transaction::new(
+      vec![owned_by(&punk42, &bob, signer, recent_blockhash)],
+      [(
+        punk42.clone(),
+        AccountChange::ReplaceState(to_vec_named(&item(&bob)).unwrap()),
+      )]
+      .into_iter()
+      .collect(),
+    )
+  };
+  assert!(anoma_vm::execute(transfer(&bob), block, &store, &cache).is_err());
+  assert!(
+    anoma_vm::execute(transfer(&authority), block, &store, &cache).is_err()
+  );
+  store.apply(anoma_vm::execute(transfer(&alice), block, &store, &cache)?);
+  assert_eq!(owner(&store, &punk42), Some(b58(&bob)));
+
+  // burning is authorized by the current owner
+  let burn = |signer: &Keypair| {
+    let mut intent = Intent::new(
+      recent_blockhash,
+      stdpred("account_deleted", vec![Param::Inline(
+        to_vec(&punk42).unwrap(),
+      )]),
+    );
+    sign(&mut intent, signer);
+    Transaction::new(
+      vec![intent],
+      [(punk42.clone(), AccountChange::DeleteAccount)]
+        .into_iter()
+        .collect(),
+    )
+  };
+  assert!(anoma_vm::execute(burn(&alice), block, &store, &cache).is_err());
+  store.apply(anoma_vm::execute(burn(&bob), block, &store, &cache)?);
+  assert_eq!(owner(&store, &punk42), None);
+
+  Ok(())
+}
@@ -0,0 +1,283 @@
+use {
+  anoma_primitives::{
+    Account,
+    AccountChange,
+    Address,
+    BlockContext,
+    Intent,
+    Param,
+    PredicateTree,
+    Transaction,
+  },
+  anoma_vm::{InMemoryStateStore, State, StateDiff},
+  common::{
+    and,
+    b58,
+    install_standard_library,
+    precache_predicates_bytecode,
+    sign,
+    stdpred,
+  },
+  ed25519_dalek::Keypair,
+  multihash::{Multihash, MultihashDigest},
+  rmp_serde::{from_slice, to_vec, to_vec_named},
+  serde::{Deserialize, Serialize},
+  std::collections::BTreeMap,
+};
+
+mod common;
+
+#[derive(Serialize)]
+struct Token {
+  authority: String,
+  supply: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Wallet {
+  balance: u64,
+  frozen: bool,
+  allowances: BTreeMap<String, u64>,
+}
+
+fn wallet_predicates(address: &Address, owner: &Keypair) -> PredicateTree {
+  stdpred("token_wallet", vec![
+    Param::AccountRef(address.clone()),
+    Param::Inline(owner.public.as_bytes().to_vec()),
+  ])
+}
+
+fn sent(wallet: &Address, amount: u64) -> PredicateTree {
+  stdpred("token_sent", vec![
+    Param::Inline(to_vec(wallet).unwrap()),
+    Param::Inline(to_vec(&amount).unwrap()),
+  ])
+}
+
+fn received(wallet: &Address, amount: u64) -> PredicateTree {
+  stdpred("token_received", vec![
+    Param::Inline(to_vec(wallet).unwrap()),
+    Param::Inline(to_vec(&amount).unwrap()),
+  ])
+}
+
+fn signed(
+  expectations: PredicateTree,
+  signers: &[&Keypair],
+  recent_blockhash: Multihash,
+) -> Intent {
+  let mut intent = Intent::new(recent_blockhash, expectations);
+  for signer in signers {
+    sign(&mut intent, signer);
+  }
+  intent
+}
+
+fn wallet(state: &impl State, address: &Address) -> Wallet {
+  from_slice(&state.get(address).unwrap().state).unwrap()
+}
+
+fn replace(address: &Address, wallet: &Wallet) -> (Address, AccountChange) {
+  (
+    address.clone(),
+    AccountChange::ReplaceState(to_vec_named(wallet).unwrap()),
+  )
+}
+
+fn create(
+  address: &Address,
+  balance: u64,
+  owner: &Keypair,
+) -> (Address, AccountChange) {
+  (
+    address.clone(),
+    AccountChange::CreateAccount(Account {
+      state: to_vec_named(&Wallet {
+        balance,
+        ..Default::default()
+      })
+      .unwrap(),
+      predicates: wallet_predicates(address, owner),
+    }),
+  )
+}
+
+fn supply(token: &Address, authority: &Keypair, supply: u64) -> StateDiff {
+  let mut diff = StateDiff::default();
+  diff.set(token.clone(), Account {
+    state: to_vec_named(&Token {
+      authority: b58(authority),
+      supply,
+    })
+    .unwrap(),
+    predicates: stdpred("token", vec![Param::AccountRef(token.clone())]),
+  });
+  diff
+}
+
+#[test]
+fn stdpred_token_lifecycle() -> anyhow::Result<()> {
+  let block = BlockContext::default();
+  let recent_blockhash = multihash::Code::Sha3_256.digest(b"test6");
+
+  let authority = Keypair::generate(&mut rand::thread_rng());
+  let alice = Keypair::generate(&mut rand::thread_rng());
+  let bob = Keypair::generate(&mut rand::thread_rng());
+  let carol = Keypair::generate(&mut rand::thread_rng());
+
+  let eurx: Address = "/eurx".parse()?;
+  let alice_address: Address = "/eurx/alice.eth".parse()?;
+  let bob_address: Address = "/eurx/bob.eth".parse()?;
+  let carol_address: Address = "/eurx/carol.eth".parse()?;
+
+  let mut store = InMemoryStateStore::default();
+  store.apply(install_standard_library());
+  store.apply(supply(&eurx, &authority, 0));
+
+  let mut cache = InMemoryStateStore::default();
+  cache.apply(precache_predicates_bytecode(
+    &store,
+    &"/stdpred/v1".parse().unwrap(),
+  ));
+
+  let token_state = |supply: u64| {
+    (
+      eurx.clone(),
+      AccountChange::ReplaceState(
+        to_vec_named(&Token {
+          authority: b58(&authority),
+          supply,
+        })
+        .unwrap(),
+      ),
+    )
+  };
+
+  // minting requires the authority signature
+  let mint = |signer: &Keypair| {
+    Transaction::new(
+      vec![signed(
+        received(&alice_address, 1000),
+        &[signer],
+        recent_blockhash,
+      )],
+      [token_state(1000), create(&alice_address, 1000, &alice)]
+        .into_iter()
+        .collect(),
+    )
+  };
+  assert!(anoma_vm::execute(mint(&alice), block, &store, &cache).is_err());
+  store.apply(anoma_vm::execute(mint(&authority), block, &store, &cache)?);
+  assert_eq!(wallet(&store, &alice_address).balance, 1000);
+
+  // alice sends 300 to bob, only the owner can sign it
+  let transfer = |signer: &Keypair| {
+    Transaction::new(
+      vec![signed(
+        and(sent(&alice_address, 300), received(&bob_address, 300)),
+        &[signer],
+        recent_blockhash,
+      )],
+      [
+        replace(&alice_address, &Wallet {
+          balance: 700,
+          ..Default::default()
+        }),
+        create(&bob_address, 300, &bob),
+      ]
+      .into_iter()
+      .collect(),
+    )
+  };
+  assert!(anoma_vm::execute(transfer(&bob), block, &store, &cache).is_err());
+  store.apply(anoma_vm::execute(transfer(&alice), block, &store, &cache)?);
+  assert_eq!(wallet(&store, &alice_address).balance, 700);
+  assert_eq!(wallet(&store, &bob_address).balance, 300);
+
+  // alice approves carol to spend 100 on her behalf
+  let alice_unchanged = stdpred("predicates_unchanged", vec![Param::Inline(
+    to_vec(&alice_address)?,
+  )]);
+  let approved = |balance, allowance| Wallet {
+    balance,
+    allowances: [(b58(&carol), allowance)].into_iter().collect(),
+    ..Default::default()
+  };
+  store.apply(anoma_vm::execute(
+    Transaction::new(
+      vec![signed(alice_unchanged.clone(), &[&alice], recent_blockhash)],
+      [replace(&alice_address, &approved(700, 100))]
+        .into_iter()
+        .collect(),
+    ),
+    block,
+    &store,
+    &cache,
+  )?);
+
+  // carol spends 60 from the allowance into her own wallet
+  let spend = |amount: u64| {
+    Transaction::new(
+      vec![signed(
+        and(
+          sent(&alice_address, amount),
+          received(&carol_address, amount),
+        ),
+        &[&carol],
+        recent_blockhash,
+      )],
+      [
+        replace(&alice_address, &approved(700 - amount, 100 - amount)),
+        create(&carol_address, amount, &carol),
+      ]
+      .into_iter()
+      .collect(),
+    )
+  };
+  store.apply(anoma_vm::execute(spend(60), block, &store, &cache)?);
+  assert_eq!(wallet(&store, &alice_address), approved(640, 40));
+  assert_eq!(wallet(&store, &carol_address).balance, 60);
+
+  // the authority freezes alice's wallet, she can't spend anymore
+  let frozen = Wallet {
+    frozen: true,
+    ..approved(640, 40)
+  };
+  let freeze = |signer: &Keypair| {
+    Transaction::new(
+      vec![signed(alice_unchanged.clone(), &[signer], recent_blockhash)],
+      [replace(&alice_address, &frozen)].into_iter().collect(),
+    )
+  };
+  assert!(anoma_vm::execute(freeze(&alice), block, &store, &cache).is_err());
+  store.apply(anoma_vm::execute(
+    freeze(&authority),
+    block,
+    &store,
+    &cache,
+  )?);
+
+  let frozen_spend = Transaction::new(
+    vec![signed(
+      and(sent(&alice_address, 40), received(&bob_address, 40)),
+      &[&alice],
+      recent_blockhash,
+    )],
+    [
+      replace(&alice_address, &Wallet {
+        balance: 600,
+        ..frozen.clone()
+      }),
+      replace(&bob_address, &Wallet {
+        balance: 340,
+        ..Default::default()
+      }),
+    ]
+    .into_iter()
+    .collect(),
+  );
+  assert!(anoma_vm::execute(frozen_spend, block, &store, &cache).is_err());
+  assert_eq!(wallet(&store, &alice_address), frozen);
+
+  Ok(())
+}
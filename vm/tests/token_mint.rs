@@ -1,6 +1,13 @@
 mod common;
 use {
-  anoma_primitives::{Address, Code, Param, Predicate, PredicateTree},
+  anoma_primitives::{
+    Address,
+    BlockContext,
+    Code,
+    Param,
+    Predicate,
+    PredicateTree,
+  },
   anoma_vm::{InMemoryStateStore, State},
   common::{create_initial_blockchain_state, precache_predicates_bytecode},
   ed25519_dalek::Keypair,
@@ -37,7 +44,8 @@ fn mint_tokens() -> anyhow::Result<()> {
   )?;
 
   // run transaction in the VM and get state diff
-  let outdiff = anoma_vm::execute(mint_tx, &store, &cache)?;
+  let outdiff =
+    anoma_vm::execute(mint_tx, BlockContext::default(), &store, &cache)?;
 
   assert_eq!(outdiff.iter().count(), 2);
   assert!(outdiff.get(&"/token/usdx".parse()?).is_some());
@@ -124,7 +132,12 @@ fn mint_tokens() -> anyhow::Result<()> {
   )?;
 
   // second mint tx
-  store.apply(anoma_vm::execute(second_mint, &store, &cache)?);
+  store.apply(anoma_vm::execute(
+    second_mint,
+    BlockContext::default(),
+    &store,
+    &cache,
+  )?);
 
   // prev mint 1000 + second mint 500
   assert_eq!(
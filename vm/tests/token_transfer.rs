@@ -1,4 +1,5 @@
 use {
+  anoma_primitives::BlockContext,
   anoma_vm::{InMemoryStateStore, State},
   common::{create_initial_blockchain_state, precache_predicates_bytecode},
   ed25519_dalek::Keypair,
@@ -41,6 +42,7 @@ fn transfer_token() -> anyhow::Result<()> {
       recent_blockhash,
       &store,
     )?,
+    BlockContext::default(),
     &store,
     &cache,
   )?);
@@ -62,6 +64,7 @@ fn transfer_token() -> anyhow::Result<()> {
       recent_blockhash,
       &store,
     )?,
+    BlockContext::default(),
     &store,
     &cache,
   )?);